@@ -0,0 +1,239 @@
+use crate::errors::DeserializeError;
+use crate::quirks::Quirks;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Well-known public ACME certificate authorities.
+///
+/// Each CA can be selected by name, which allows configuration files to refer
+/// to it instead of its directory URL:
+///
+/// ```no_run
+/// # use acme_proto::{DirectoryCache, HttpClient, KnownCa};
+/// # use std::str::FromStr;
+/// # async fn example<C: HttpClient>(
+/// #     client: &C,
+/// #     cache: &DirectoryCache,
+/// # ) -> Result<(), Box<dyn std::error::Error>> {
+/// let ca = KnownCa::from_str("letsencrypt-staging")?;
+/// let directory = cache.get(client, ca.directory_url()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+pub enum KnownCa {
+	#[serde(rename = "letsencrypt")]
+	LetsEncrypt,
+	#[serde(rename = "letsencrypt-staging")]
+	LetsEncryptStaging,
+	#[serde(rename = "zerossl")]
+	ZeroSsl,
+	#[serde(rename = "buypass")]
+	Buypass,
+	#[serde(rename = "buypass-staging")]
+	BuypassStaging,
+	#[serde(rename = "google")]
+	GoogleTrustServices,
+	#[serde(rename = "google-staging")]
+	GoogleTrustServicesStaging,
+	#[serde(rename = "sslcom-rsa")]
+	SslComRsa,
+	#[serde(rename = "sslcom-ecc")]
+	SslComEcc,
+}
+
+impl KnownCa {
+	pub fn all() -> &'static [KnownCa] {
+		&[
+			KnownCa::LetsEncrypt,
+			KnownCa::LetsEncryptStaging,
+			KnownCa::ZeroSsl,
+			KnownCa::Buypass,
+			KnownCa::BuypassStaging,
+			KnownCa::GoogleTrustServices,
+			KnownCa::GoogleTrustServicesStaging,
+			KnownCa::SslComRsa,
+			KnownCa::SslComEcc,
+		]
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			KnownCa::LetsEncrypt => "letsencrypt",
+			KnownCa::LetsEncryptStaging => "letsencrypt-staging",
+			KnownCa::ZeroSsl => "zerossl",
+			KnownCa::Buypass => "buypass",
+			KnownCa::BuypassStaging => "buypass-staging",
+			KnownCa::GoogleTrustServices => "google",
+			KnownCa::GoogleTrustServicesStaging => "google-staging",
+			KnownCa::SslComRsa => "sslcom-rsa",
+			KnownCa::SslComEcc => "sslcom-ecc",
+		}
+	}
+
+	pub fn directory_url(&self) -> &'static str {
+		match self {
+			KnownCa::LetsEncrypt => "https://acme-v02.api.letsencrypt.org/directory",
+			KnownCa::LetsEncryptStaging => "https://acme-staging-v02.api.letsencrypt.org/directory",
+			KnownCa::ZeroSsl => "https://acme.zerossl.com/v2/DV90",
+			KnownCa::Buypass => "https://api.buypass.com/acme/directory",
+			KnownCa::BuypassStaging => "https://api.test4.buypass.no/acme/directory",
+			KnownCa::GoogleTrustServices => "https://dv.acme-v02.api.pki.goog/directory",
+			KnownCa::GoogleTrustServicesStaging => {
+				"https://dv.acme-v02.test-api.pki.goog/directory"
+			}
+			KnownCa::SslComRsa => "https://acme.ssl.com/sslcom-dv-rsa",
+			KnownCa::SslComEcc => "https://acme.ssl.com/sslcom-dv-ecc",
+		}
+	}
+
+	/// Whether this is a test environment, which issues untrusted
+	/// certificates.
+	pub fn is_staging(&self) -> bool {
+		matches!(
+			self,
+			KnownCa::LetsEncryptStaging
+				| KnownCa::BuypassStaging
+				| KnownCa::GoogleTrustServicesStaging
+		)
+	}
+
+	/// Test environment of this CA, if it has one.
+	pub fn staging(&self) -> Option<KnownCa> {
+		match self {
+			KnownCa::LetsEncrypt | KnownCa::LetsEncryptStaging => Some(KnownCa::LetsEncryptStaging),
+			KnownCa::Buypass | KnownCa::BuypassStaging => Some(KnownCa::BuypassStaging),
+			KnownCa::GoogleTrustServices | KnownCa::GoogleTrustServicesStaging => {
+				Some(KnownCa::GoogleTrustServicesStaging)
+			}
+			KnownCa::ZeroSsl | KnownCa::SslComRsa | KnownCa::SslComEcc => None,
+		}
+	}
+
+	/// Production environment of this CA.
+	pub fn production(&self) -> KnownCa {
+		match self {
+			KnownCa::LetsEncryptStaging => KnownCa::LetsEncrypt,
+			KnownCa::BuypassStaging => KnownCa::Buypass,
+			KnownCa::GoogleTrustServicesStaging => KnownCa::GoogleTrustServices,
+			ca => *ca,
+		}
+	}
+
+	/// Whether the CA requires an external account binding on account
+	/// creation. The directory should advertise it as well, but having it
+	/// known in advance allows to fail early on missing credentials.
+	pub fn requires_external_account(&self) -> bool {
+		matches!(
+			self,
+			KnownCa::ZeroSsl
+				| KnownCa::GoogleTrustServices
+				| KnownCa::GoogleTrustServicesStaging
+				| KnownCa::SslComRsa
+				| KnownCa::SslComEcc
+		)
+	}
+
+	/// Deviations from the specifications to tolerate with this CA.
+	pub fn quirks(&self) -> Quirks {
+		match self {
+			KnownCa::LetsEncrypt | KnownCa::LetsEncryptStaging => Quirks::boulder(),
+			_ => Quirks::default(),
+		}
+	}
+
+	/// Known deviations from the specifications and other peculiarities of
+	/// the CA, in a human-readable form.
+	pub fn notes(&self) -> &'static [&'static str] {
+		match self {
+			KnownCa::LetsEncrypt | KnownCa::LetsEncryptStaging => &[
+				"account objects do not include the orders URL (Boulder issue #3335)",
+				"pre-authorization (newAuthz) is not supported",
+			],
+			KnownCa::ZeroSsl => &[
+				"external account binding credentials are provided by the ZeroSSL dashboard or API",
+				"orders may stay in the processing state for a while, the Retry-After header should be honored",
+			],
+			KnownCa::Buypass | KnownCa::BuypassStaging => &[
+				"wildcard identifiers are not supported",
+				"certificates are valid for 180 days",
+			],
+			KnownCa::GoogleTrustServices | KnownCa::GoogleTrustServicesStaging => &[
+				"external account binding credentials can only be used once and expire after 7 days",
+				"staging and production require distinct external account binding credentials",
+			],
+			KnownCa::SslComRsa | KnownCa::SslComEcc => &[
+				"RSA and ECDSA certificates are issued from distinct directories",
+				"external account binding credentials are provided by the SSL.com account",
+			],
+		}
+	}
+}
+
+impl fmt::Display for KnownCa {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+impl FromStr for KnownCa {
+	type Err = DeserializeError;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		KnownCa::all()
+			.iter()
+			.find(|ca| ca.name().eq_ignore_ascii_case(name.trim()))
+			.copied()
+			.ok_or_else(|| {
+				DeserializeError::new("certificate authority", format!("{}: unknown name", name))
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::KnownCa;
+	use std::str::FromStr;
+
+	#[test]
+	fn names() {
+		for ca in KnownCa::all() {
+			assert_eq!(KnownCa::from_str(ca.name()).unwrap(), *ca);
+			assert_eq!(ca.to_string(), ca.name());
+			let json = format!("\"{}\"", ca.name());
+			assert_eq!(serde_json::from_str::<KnownCa>(&json).unwrap(), *ca);
+			assert!(ca.directory_url().starts_with("https://"));
+			assert!(!ca.notes().is_empty());
+			assert!(ca.quirks().extra_fields);
+		}
+		assert_eq!(
+			KnownCa::from_str("LetsEncrypt-Staging").unwrap(),
+			KnownCa::LetsEncryptStaging
+		);
+		assert!(KnownCa::from_str("letsencrypt-test").is_err());
+		assert!(KnownCa::from_str("").is_err());
+	}
+
+	#[test]
+	fn environments() {
+		for ca in KnownCa::all() {
+			assert!(!ca.production().is_staging(), "{}", ca);
+			assert_eq!(ca.production().staging(), ca.staging(), "{}", ca);
+			if let Some(staging) = ca.staging() {
+				assert!(staging.is_staging(), "{}", ca);
+				assert_eq!(staging.production(), ca.production(), "{}", ca);
+				assert_eq!(
+					staging.requires_external_account(),
+					ca.requires_external_account()
+				);
+			}
+		}
+		assert_eq!(KnownCa::ZeroSsl.staging(), None);
+		assert!(KnownCa::LetsEncryptStaging.quirks().optional_account_orders);
+		assert_eq!(
+			KnownCa::LetsEncrypt.staging(),
+			Some(KnownCa::LetsEncryptStaging)
+		);
+	}
+}
@@ -0,0 +1,245 @@
+//! Cryptographic backends.
+//!
+//! Signing, hashing and key generation are delegated to a backend selected at
+//! compile time using cargo features:
+//!
+//! - `crypto_rustcrypto` (default): pure-Rust implementation using the
+//!   RustCrypto crates;
+//! - `crypto_openssl`: implementation using the system's OpenSSL library.
+//!
+//! When both features are enabled, OpenSSL is used by default.
+
+use crate::errors::KeyError;
+use crate::key::KeyType;
+use crate::structs::Jwk;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+#[cfg(feature = "crypto_openssl")]
+mod openssl;
+#[cfg(feature = "crypto_rustcrypto")]
+mod rustcrypto;
+
+#[cfg(feature = "crypto_openssl")]
+pub use self::openssl::OpenSslBackend;
+#[cfg(feature = "crypto_rustcrypto")]
+pub use self::rustcrypto::RustCryptoBackend;
+
+#[cfg(not(any(feature = "crypto_openssl", feature = "crypto_rustcrypto")))]
+compile_error!(
+	"at least one of the `crypto_openssl` or `crypto_rustcrypto` features must be enabled"
+);
+
+/// Backend used by the rest of the crate.
+#[cfg(feature = "crypto_openssl")]
+pub type DefaultBackend = OpenSslBackend;
+/// Backend used by the rest of the crate.
+#[cfg(all(feature = "crypto_rustcrypto", not(feature = "crypto_openssl")))]
+pub type DefaultBackend = RustCryptoBackend;
+
+/// Cryptographic primitives required by the ACME protocol.
+///
+/// Private keys are exchanged as unencrypted PKCS#8 DER documents and
+/// signatures use the JWS format (RFC 7518): RSA keys sign using RS256 and
+/// ECDSA signatures are the concatenation of R and S.
+pub trait CryptoBackend {
+	fn sha256(data: &[u8]) -> [u8; 32];
+
+	fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8>;
+
+	fn hmac_sha512(key: &[u8], data: &[u8]) -> Vec<u8>;
+
+	/// Fills the buffer using a cryptographically secure random generator.
+	fn fill_random(buf: &mut [u8]) -> Result<(), KeyError>;
+
+	/// Generates a new private key, returned as a PKCS#8 DER document.
+	fn generate_key(key_type: KeyType) -> Result<Vec<u8>, KeyError>;
+
+	fn key_type(pkcs8: &[u8]) -> Result<KeyType, KeyError>;
+
+	fn sec1_to_pkcs8(sec1: &[u8]) -> Result<Vec<u8>, KeyError>;
+
+	/// Exports an elliptic curve key in the SEC1 format. The curve must always
+	/// be specified in the key parameters.
+	fn pkcs8_to_sec1(pkcs8: &[u8]) -> Result<Vec<u8>, KeyError>;
+
+	/// Public part of the key, without the optional `alg` and `use` members.
+	fn public_jwk(pkcs8: &[u8]) -> Result<Jwk, KeyError>;
+
+	fn sign(pkcs8: &[u8], data: &[u8]) -> Result<Vec<u8>, KeyError>;
+
+	fn verify(jwk: &Jwk, data: &[u8], signature: &[u8]) -> Result<(), KeyError>;
+}
+
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+	DefaultBackend::sha256(data)
+}
+
+pub(crate) fn random_u64() -> Result<u64, KeyError> {
+	let mut buf = [0; 8];
+	DefaultBackend::fill_random(&mut buf)?;
+	Ok(u64::from_le_bytes(buf))
+}
+
+pub(crate) fn b64(data: &[u8]) -> String {
+	URL_SAFE_NO_PAD.encode(data)
+}
+
+pub(crate) fn b64_decode(data: &str) -> Result<Vec<u8>, KeyError> {
+	URL_SAFE_NO_PAD
+		.decode(data)
+		.map_err(|e| KeyError::new(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::CryptoBackend;
+	use crate::key::{test_keys, KeyType};
+	use crate::structs::Jwk;
+
+	const SIGNING_INPUT: &[u8] = b"eyJhbGciOiJFUzI1NiJ9.eyJ0ZXN0Ijp0cnVlfQ";
+	const HMAC_KEY: &[u8] = b"secret key used for testing";
+	const HS256_SIG: &str = "2BoUFOOcFAnwizP9vvHlvwfJbh0JYhFueo-beTzdx-g";
+	const HS512_SIG: &str =
+		"xcAya8pWCUojDCkbsYMk9IG0U9FUiYbmZOVjJaqtt1lRXX5mHwSLj3QAucndk9WirtBJPeXAfvFeRsUWDG8rPg";
+	const ED25519_SIG: &str =
+		"45vAWbNnd3WBkPStSPHHo-O77Jr1gPtotWHL7p_600QP6o-1O40NCsdX14O7i9vOTU5_gf7Rb23ydqCK3QSDCg";
+	const RS256_SIG: &str = "manxMaYb4sKnxnxPt9zYvJ1FUEP4x8lpJBE24cK2ak11y9fHQ1Se7yFWKIA0xEgTTfN_eimdE574ZSPp9iG_gOom6LgeGCM_HtnMmCilxtL2k28CYYHQmmPYryMUyZH6AmGF7Ox2fUirMN5K-l3c7hiAKZ2nXo0EXKOK-j1dUuBRPEMYHpeEclR-mKuq5KmVw_y9szyXADD9sfe0GOSQ6mN2PAaUJZJB_k087_CSjl3Lr-X95CvYBgq3Mv9KbvbzBuXlXPmBPso5eWQmBSaGfAsmvPj02B_vG8-IvAnyezdzjlzftuMaSTnIlRp9i3ZwgtgVPGQkRM9yzfvKsCwVUg";
+	const ES256_SIG: &str =
+		"D2fgvgoMr4SHBHeS65kM6rxo0UmD2OQL6Q00hhFuc6N7tO6NqQKEKKO38wR3JfvZVh9fpwIEWTJIV5rWOBcWmw";
+	const ES384_SIG: &str = "ck8JU6kKKAIMTK4eaWi_6jVXh59PRFeU6kMZ8nfA9SzakdDzGW-eDon_0T--y18EskAnWrA6Z36Dyt11h51E6bT5OXjXM6LnmaC8U7hlJNyOCaZptqPD7qV5KfwAkpvR";
+	const ES512_SIG: &str = "AMC3NFGFzYp3wZLUIYpMAH8L1YZhjsaYhhkHtItJXKr_SoWkYw9wCTBns0o8u97FyWIbc-QS00X0TGla0q6ZKYpfAGaWcfxWiIUdGRDJ2LAbToBxP27sTibn-QJX9UajP8jkL8CThgYSo5c5sqnE8NFSAT8lDtBb-wzA8xBMIxPSHycG";
+
+	fn der(pem: &str) -> Vec<u8> {
+		sec1::der::pem::decode_vec(pem.as_bytes()).unwrap().1
+	}
+
+	fn digest<B: CryptoBackend>() {
+		assert_eq!(
+			super::b64(&B::sha256(b"abc")),
+			"ungWv48Bz-pBQUDeXa4iI7ADYaOWF3qctBD_YfIAFa0"
+		);
+		assert_eq!(
+			super::b64(&B::hmac_sha256(HMAC_KEY, SIGNING_INPUT)),
+			HS256_SIG
+		);
+		assert_eq!(
+			super::b64(&B::hmac_sha512(HMAC_KEY, SIGNING_INPUT)),
+			HS512_SIG
+		);
+		let (mut a, mut b) = ([0; 32], [0; 32]);
+		B::fill_random(&mut a).unwrap();
+		B::fill_random(&mut b).unwrap();
+		assert_ne!(a, b);
+	}
+
+	fn keys<B: CryptoBackend>() {
+		let tests = [
+			(test_keys::ED25519_PKCS8, KeyType::Ed25519),
+			(test_keys::P256_PKCS8, KeyType::EcdsaP256),
+			(test_keys::P521_PKCS8, KeyType::EcdsaP521),
+			(test_keys::RSA2048_PKCS8, KeyType::Rsa2048),
+		];
+		for (pem, key_type) in tests {
+			let pkcs8 = der(pem);
+			assert_eq!(B::key_type(&pkcs8).unwrap(), key_type);
+		}
+		let sec1 = der(test_keys::P256_SEC1);
+		let pkcs8 = B::sec1_to_pkcs8(&sec1).unwrap();
+		assert_eq!(B::key_type(&pkcs8).unwrap(), KeyType::EcdsaP256);
+		assert_eq!(B::pkcs8_to_sec1(&pkcs8).unwrap(), sec1);
+		assert_eq!(B::pkcs8_to_sec1(&der(test_keys::P256_PKCS8)).unwrap(), sec1);
+		assert!(B::pkcs8_to_sec1(&der(test_keys::ED25519_PKCS8)).is_err());
+		assert!(B::key_type(b"not a key").is_err());
+		assert!(B::sec1_to_pkcs8(b"not a key").is_err());
+	}
+
+	fn deterministic_signatures<B: CryptoBackend>() {
+		for (pem, signature) in [
+			(test_keys::ED25519_PKCS8, ED25519_SIG),
+			(test_keys::RSA2048_PKCS8, RS256_SIG),
+		] {
+			let sig = B::sign(&der(pem), SIGNING_INPUT).unwrap();
+			assert_eq!(super::b64(&sig), signature);
+		}
+	}
+
+	fn verify_vectors<B: CryptoBackend>() {
+		for (pem, signature) in [
+			(test_keys::ED25519_PKCS8, ED25519_SIG),
+			(test_keys::RSA2048_PKCS8, RS256_SIG),
+			(test_keys::P256_PKCS8, ES256_SIG),
+			(test_keys::P384_SEC1, ES384_SIG),
+			(test_keys::P521_PKCS8, ES512_SIG),
+		] {
+			let jwk = crate::AccountKey::from_pem(pem).unwrap().jwk();
+			let sig = super::b64_decode(signature).unwrap();
+			let res = B::verify(&jwk, SIGNING_INPUT, &sig);
+			assert!(res.is_ok(), "{}: {:?}", jwk.key_type(), res);
+			let res = B::verify(&jwk, b"tampered input", &sig);
+			assert!(res.is_err(), "{}: tampered input accepted", jwk.key_type());
+		}
+	}
+
+	fn sign_verify<B: CryptoBackend, V: CryptoBackend>() {
+		for key_type in [
+			KeyType::EcdsaP256,
+			KeyType::EcdsaP384,
+			KeyType::EcdsaP521,
+			KeyType::Ed25519,
+		] {
+			let pkcs8 = B::generate_key(key_type).unwrap();
+			assert_eq!(V::key_type(&pkcs8).unwrap(), key_type);
+			let jwk: Jwk = B::public_jwk(&pkcs8).unwrap();
+			assert_eq!(V::public_jwk(&pkcs8).unwrap(), jwk);
+			let sig = B::sign(&pkcs8, SIGNING_INPUT).unwrap();
+			let res = V::verify(&jwk, SIGNING_INPUT, &sig);
+			assert!(res.is_ok(), "{}: {:?}", key_type, res);
+		}
+	}
+
+	#[cfg(any(feature = "crypto_openssl", feature = "crypto_rustcrypto"))]
+	macro_rules! backend_tests {
+		($name: ident, $backend: ty) => {
+			mod $name {
+				#[test]
+				fn digest() {
+					super::digest::<$backend>();
+				}
+
+				#[test]
+				fn keys() {
+					super::keys::<$backend>();
+				}
+
+				#[test]
+				fn deterministic_signatures() {
+					super::deterministic_signatures::<$backend>();
+				}
+
+				#[test]
+				fn verify_vectors() {
+					super::verify_vectors::<$backend>();
+				}
+
+				#[test]
+				fn sign_verify() {
+					super::sign_verify::<$backend, $backend>();
+				}
+			}
+		};
+	}
+
+	#[cfg(feature = "crypto_openssl")]
+	backend_tests!(openssl, crate::crypto::OpenSslBackend);
+	#[cfg(feature = "crypto_rustcrypto")]
+	backend_tests!(rustcrypto, crate::crypto::RustCryptoBackend);
+
+	#[test]
+	#[cfg(all(feature = "crypto_openssl", feature = "crypto_rustcrypto"))]
+	fn cross_backend() {
+		use crate::crypto::{OpenSslBackend, RustCryptoBackend};
+		sign_verify::<OpenSslBackend, RustCryptoBackend>();
+		sign_verify::<RustCryptoBackend, OpenSslBackend>();
+	}
+}
@@ -0,0 +1,263 @@
+use crate::crypto::{b64, b64_decode, CryptoBackend};
+use crate::errors::KeyError;
+use crate::key::KeyType;
+use crate::structs::{EcdsaCurve, EddsaCurve, Jwk};
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::{hash, MessageDigest};
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Private, Public};
+use openssl::rsa::Rsa;
+use openssl::sign::{Signer, Verifier};
+
+/// Backend based on the OpenSSL library.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpenSslBackend;
+
+impl CryptoBackend for OpenSslBackend {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		openssl::sha::sha256(data)
+	}
+
+	fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+		PKey::hmac(key)
+			.and_then(|key| Signer::new(MessageDigest::sha256(), &key)?.sign_oneshot_to_vec(data))
+			.expect("HMAC can take a key of any size")
+	}
+
+	fn hmac_sha512(key: &[u8], data: &[u8]) -> Vec<u8> {
+		PKey::hmac(key)
+			.and_then(|key| Signer::new(MessageDigest::sha512(), &key)?.sign_oneshot_to_vec(data))
+			.expect("HMAC can take a key of any size")
+	}
+
+	fn fill_random(buf: &mut [u8]) -> Result<(), KeyError> {
+		openssl::rand::rand_bytes(buf).map_err(openssl_error)
+	}
+
+	fn generate_key(key_type: KeyType) -> Result<Vec<u8>, KeyError> {
+		let key = match key_type {
+			KeyType::Rsa2048 => Rsa::generate(2048).and_then(PKey::from_rsa),
+			KeyType::Rsa3072 => Rsa::generate(3072).and_then(PKey::from_rsa),
+			KeyType::Rsa4096 => Rsa::generate(4096).and_then(PKey::from_rsa),
+			KeyType::EcdsaP256 => generate_ec(EcdsaCurve::P256),
+			KeyType::EcdsaP384 => generate_ec(EcdsaCurve::P384),
+			KeyType::EcdsaP521 => generate_ec(EcdsaCurve::P521),
+			KeyType::Ed25519 => PKey::generate_ed25519(),
+		}
+		.map_err(openssl_error)?;
+		key.private_key_to_pkcs8().map_err(openssl_error)
+	}
+
+	fn key_type(pkcs8: &[u8]) -> Result<KeyType, KeyError> {
+		let key = from_pkcs8(pkcs8)?;
+		match key.id() {
+			Id::RSA => KeyType::from_rsa_size(key.bits() as usize),
+			Id::EC => {
+				let key = key.ec_key().map_err(openssl_error)?;
+				let key_type = match ec_curve(&key)? {
+					EcdsaCurve::P256 => KeyType::EcdsaP256,
+					EcdsaCurve::P384 => KeyType::EcdsaP384,
+					EcdsaCurve::P521 => KeyType::EcdsaP521,
+				};
+				Ok(key_type)
+			}
+			Id::ED25519 => Ok(KeyType::Ed25519),
+			_ => Err(unsupported_pkcs8()),
+		}
+	}
+
+	fn sec1_to_pkcs8(sec1: &[u8]) -> Result<Vec<u8>, KeyError> {
+		let key = EcKey::private_key_from_der(sec1)
+			.map_err(|_| KeyError::new("unsupported or invalid SEC1 private key"))?;
+		ec_curve(&key)?;
+		PKey::from_ec_key(key)
+			.and_then(|key| key.private_key_to_pkcs8())
+			.map_err(openssl_error)
+	}
+
+	fn pkcs8_to_sec1(pkcs8: &[u8]) -> Result<Vec<u8>, KeyError> {
+		let key = from_pkcs8(pkcs8)?;
+		if key.id() != Id::EC {
+			return Err(KeyError::new("SEC1 only applies to elliptic curve keys"));
+		}
+		let key = key.ec_key().map_err(openssl_error)?;
+		ec_curve(&key)?;
+		key.private_key_to_der().map_err(openssl_error)
+	}
+
+	fn public_jwk(pkcs8: &[u8]) -> Result<Jwk, KeyError> {
+		let key = from_pkcs8(pkcs8)?;
+		match key.id() {
+			Id::RSA => {
+				let key = key.rsa().map_err(openssl_error)?;
+				Ok(Jwk::Rsa {
+					alg: None,
+					key_use: None,
+					e: b64(&key.e().to_vec()),
+					n: b64(&key.n().to_vec()),
+				})
+			}
+			Id::EC => {
+				let key = key.ec_key().map_err(openssl_error)?;
+				let crv = ec_curve(&key)?;
+				let mut ctx = BigNumContext::new().map_err(openssl_error)?;
+				let mut x = BigNum::new().map_err(openssl_error)?;
+				let mut y = BigNum::new().map_err(openssl_error)?;
+				key.public_key()
+					.affine_coordinates(key.group(), &mut x, &mut y, &mut ctx)
+					.map_err(openssl_error)?;
+				let size = field_size(crv);
+				Ok(Jwk::Ecdsa {
+					alg: false,
+					crv,
+					key_use: None,
+					x: b64(&x.to_vec_padded(size).map_err(openssl_error)?),
+					y: b64(&y.to_vec_padded(size).map_err(openssl_error)?),
+				})
+			}
+			Id::ED25519 => Ok(Jwk::Eddsa {
+				alg: false,
+				crv: EddsaCurve::Ed25519,
+				key_use: None,
+				x: b64(&key.raw_public_key().map_err(openssl_error)?),
+			}),
+			_ => Err(unsupported_pkcs8()),
+		}
+	}
+
+	fn sign(pkcs8: &[u8], data: &[u8]) -> Result<Vec<u8>, KeyError> {
+		let key = from_pkcs8(pkcs8)?;
+		match key.id() {
+			Id::RSA => Signer::new(MessageDigest::sha256(), &key)
+				.and_then(|mut signer| signer.sign_oneshot_to_vec(data))
+				.map_err(openssl_error),
+			Id::EC => {
+				let key = key.ec_key().map_err(openssl_error)?;
+				let crv = ec_curve(&key)?;
+				let digest = hash(ec_digest(crv), data).map_err(openssl_error)?;
+				let sig = EcdsaSig::sign(&digest, &key).map_err(openssl_error)?;
+				let size = field_size(crv);
+				let mut signature = sig.r().to_vec_padded(size).map_err(openssl_error)?;
+				signature.extend(sig.s().to_vec_padded(size).map_err(openssl_error)?);
+				Ok(signature)
+			}
+			Id::ED25519 => Signer::new_without_digest(&key)
+				.and_then(|mut signer| signer.sign_oneshot_to_vec(data))
+				.map_err(openssl_error),
+			_ => Err(unsupported_pkcs8()),
+		}
+	}
+
+	fn verify(jwk: &Jwk, data: &[u8], signature: &[u8]) -> Result<(), KeyError> {
+		let valid = match jwk {
+			Jwk::Rsa { e, n, .. } => {
+				let key = Rsa::from_public_components(bn(n)?, bn(e)?)
+					.and_then(PKey::from_rsa)
+					.map_err(openssl_error)?;
+				verify_pkey(&key, Some(MessageDigest::sha256()), data, signature)?
+			}
+			Jwk::Ecdsa { crv, x, y, .. } => {
+				let group = ec_group(*crv).map_err(openssl_error)?;
+				let (x, y) = (bn(x)?, bn(y)?);
+				let key = EcKey::from_public_key_affine_coordinates(&group, &x, &y)
+					.map_err(openssl_error)?;
+				let size = field_size(*crv);
+				if signature.len() != 2 * size as usize {
+					return Err(KeyError::new("invalid signature: wrong length"));
+				}
+				let (r, s) = signature.split_at(size as usize);
+				let r = BigNum::from_slice(r).map_err(openssl_error)?;
+				let s = BigNum::from_slice(s).map_err(openssl_error)?;
+				let digest = hash(ec_digest(*crv), data).map_err(openssl_error)?;
+				EcdsaSig::from_private_components(r, s)
+					.and_then(|sig| sig.verify(&digest, &key))
+					.map_err(openssl_error)?
+			}
+			Jwk::Eddsa { crv, x, .. } => {
+				if *crv != EddsaCurve::Ed25519 {
+					return Err(KeyError::new(format!("{}: unsupported curve", crv)));
+				}
+				let key = PKey::public_key_from_raw_bytes(&b64_decode(x)?, Id::ED25519)
+					.map_err(openssl_error)?;
+				verify_pkey(&key, None, data, signature)?
+			}
+		};
+		if valid {
+			Ok(())
+		} else {
+			Err(KeyError::new("invalid signature"))
+		}
+	}
+}
+
+fn openssl_error(e: openssl::error::ErrorStack) -> KeyError {
+	KeyError::new(e.to_string())
+}
+
+fn unsupported_pkcs8() -> KeyError {
+	KeyError::new("unsupported or invalid PKCS#8 private key")
+}
+
+fn from_pkcs8(pkcs8: &[u8]) -> Result<PKey<Private>, KeyError> {
+	PKey::private_key_from_pkcs8(pkcs8).map_err(|_| unsupported_pkcs8())
+}
+
+fn bn(data: &str) -> Result<BigNum, KeyError> {
+	BigNum::from_slice(&b64_decode(data)?).map_err(openssl_error)
+}
+
+fn ec_group(crv: EcdsaCurve) -> Result<EcGroup, openssl::error::ErrorStack> {
+	let nid = match crv {
+		EcdsaCurve::P256 => Nid::X9_62_PRIME256V1,
+		EcdsaCurve::P384 => Nid::SECP384R1,
+		EcdsaCurve::P521 => Nid::SECP521R1,
+	};
+	EcGroup::from_curve_name(nid)
+}
+
+fn ec_curve<T: openssl::pkey::HasParams>(key: &EcKey<T>) -> Result<EcdsaCurve, KeyError> {
+	match key.group().curve_name() {
+		Some(Nid::X9_62_PRIME256V1) => Ok(EcdsaCurve::P256),
+		Some(Nid::SECP384R1) => Ok(EcdsaCurve::P384),
+		Some(Nid::SECP521R1) => Ok(EcdsaCurve::P521),
+		_ => Err(KeyError::new("unsupported elliptic curve")),
+	}
+}
+
+fn ec_digest(crv: EcdsaCurve) -> MessageDigest {
+	match crv {
+		EcdsaCurve::P256 => MessageDigest::sha256(),
+		EcdsaCurve::P384 => MessageDigest::sha384(),
+		EcdsaCurve::P521 => MessageDigest::sha512(),
+	}
+}
+
+fn field_size(crv: EcdsaCurve) -> i32 {
+	match crv {
+		EcdsaCurve::P256 => 32,
+		EcdsaCurve::P384 => 48,
+		EcdsaCurve::P521 => 66,
+	}
+}
+
+fn generate_ec(crv: EcdsaCurve) -> Result<PKey<Private>, openssl::error::ErrorStack> {
+	let group = ec_group(crv)?;
+	EcKey::generate(&group).and_then(PKey::from_ec_key)
+}
+
+fn verify_pkey(
+	key: &PKey<Public>,
+	digest: Option<MessageDigest>,
+	data: &[u8],
+	signature: &[u8],
+) -> Result<bool, KeyError> {
+	let verifier = match digest {
+		Some(digest) => Verifier::new(digest, key),
+		None => Verifier::new_without_digest(key),
+	};
+	verifier
+		.and_then(|mut verifier| verifier.verify_oneshot(signature, data))
+		.map_err(openssl_error)
+}
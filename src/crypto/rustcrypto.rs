@@ -0,0 +1,289 @@
+use crate::crypto::{b64, b64_decode, CryptoBackend};
+use crate::errors::KeyError;
+use crate::key::KeyType;
+use crate::structs::{EcdsaCurve, EddsaCurve, Jwk};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, EncodePrivateKey};
+use hmac::{Hmac, Mac};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::AssociatedOid;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rsa::signature::{SignatureEncoding, Signer, Verifier};
+use rsa::traits::PublicKeyParts;
+use sec1::der::Encode;
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::Zeroizing;
+
+/// Backend based on the pure-Rust RustCrypto crates.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RustCryptoBackend;
+
+enum PrivateKey {
+	Rsa(rsa::RsaPrivateKey),
+	P256(p256::SecretKey),
+	P384(p384::SecretKey),
+	P521(p521::SecretKey),
+	Ed25519(ed25519_dalek::SigningKey),
+}
+
+impl PrivateKey {
+	fn from_pkcs8(data: &[u8]) -> Result<Self, KeyError> {
+		if let Ok(k) = ed25519_dalek::SigningKey::from_pkcs8_der(data) {
+			return Ok(PrivateKey::Ed25519(k));
+		}
+		if let Ok(k) = p256::SecretKey::from_pkcs8_der(data) {
+			return Ok(PrivateKey::P256(k));
+		}
+		if let Ok(k) = p384::SecretKey::from_pkcs8_der(data) {
+			return Ok(PrivateKey::P384(k));
+		}
+		if let Ok(k) = p521::SecretKey::from_pkcs8_der(data) {
+			return Ok(PrivateKey::P521(k));
+		}
+		rsa::RsaPrivateKey::from_pkcs8_der(data)
+			.map(PrivateKey::Rsa)
+			.map_err(|_| KeyError::new("unsupported or invalid PKCS#8 private key"))
+	}
+
+	fn to_pkcs8(&self) -> Result<Vec<u8>, KeyError> {
+		let doc = match self {
+			PrivateKey::Rsa(k) => k.to_pkcs8_der(),
+			PrivateKey::P256(k) => k.to_pkcs8_der(),
+			PrivateKey::P384(k) => k.to_pkcs8_der(),
+			PrivateKey::P521(k) => k.to_pkcs8_der(),
+			// Version 1 documents, without the public key, are the most widely
+			// supported.
+			PrivateKey::Ed25519(k) => ed25519_dalek::pkcs8::KeypairBytes {
+				secret_key: k.to_bytes(),
+				public_key: None,
+			}
+			.to_pkcs8_der(),
+		}
+		.map_err(|e| KeyError::new(e.to_string()))?;
+		Ok(doc.as_bytes().to_vec())
+	}
+}
+
+impl CryptoBackend for RustCryptoBackend {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		Sha256::digest(data).into()
+	}
+
+	fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+		let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take a key of any size");
+		mac.update(data);
+		mac.finalize().into_bytes().to_vec()
+	}
+
+	fn hmac_sha512(key: &[u8], data: &[u8]) -> Vec<u8> {
+		let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC can take a key of any size");
+		mac.update(data);
+		mac.finalize().into_bytes().to_vec()
+	}
+
+	fn fill_random(buf: &mut [u8]) -> Result<(), KeyError> {
+		OsRng
+			.try_fill_bytes(buf)
+			.map_err(|e| KeyError::new(e.to_string()))
+	}
+
+	fn generate_key(key_type: KeyType) -> Result<Vec<u8>, KeyError> {
+		let key = match key_type {
+			KeyType::Rsa2048 => generate_rsa(2048)?,
+			KeyType::Rsa3072 => generate_rsa(3072)?,
+			KeyType::Rsa4096 => generate_rsa(4096)?,
+			KeyType::EcdsaP256 => PrivateKey::P256(p256::SecretKey::random(&mut OsRng)),
+			KeyType::EcdsaP384 => PrivateKey::P384(p384::SecretKey::random(&mut OsRng)),
+			KeyType::EcdsaP521 => PrivateKey::P521(p521::SecretKey::random(&mut OsRng)),
+			KeyType::Ed25519 => {
+				PrivateKey::Ed25519(ed25519_dalek::SigningKey::generate(&mut OsRng))
+			}
+		};
+		key.to_pkcs8()
+	}
+
+	fn key_type(pkcs8: &[u8]) -> Result<KeyType, KeyError> {
+		let key_type = match PrivateKey::from_pkcs8(pkcs8)? {
+			PrivateKey::Rsa(k) => KeyType::from_rsa_size(k.size() * 8)?,
+			PrivateKey::P256(_) => KeyType::EcdsaP256,
+			PrivateKey::P384(_) => KeyType::EcdsaP384,
+			PrivateKey::P521(_) => KeyType::EcdsaP521,
+			PrivateKey::Ed25519(_) => KeyType::Ed25519,
+		};
+		Ok(key_type)
+	}
+
+	fn sec1_to_pkcs8(sec1: &[u8]) -> Result<Vec<u8>, KeyError> {
+		let key = if let Ok(k) = p256::SecretKey::from_sec1_der(sec1) {
+			PrivateKey::P256(k)
+		} else if let Ok(k) = p384::SecretKey::from_sec1_der(sec1) {
+			PrivateKey::P384(k)
+		} else {
+			p521::SecretKey::from_sec1_der(sec1)
+				.map(PrivateKey::P521)
+				.map_err(|_| KeyError::new("unsupported or invalid SEC1 private key"))?
+		};
+		key.to_pkcs8()
+	}
+
+	fn pkcs8_to_sec1(pkcs8: &[u8]) -> Result<Vec<u8>, KeyError> {
+		// The RustCrypto crates omit the curve from the key parameters, which
+		// some tools require.
+		let (private_key, public_key, curve) = match PrivateKey::from_pkcs8(pkcs8)? {
+			PrivateKey::P256(k) => (
+				k.to_bytes().to_vec(),
+				k.public_key().to_encoded_point(false).as_bytes().to_vec(),
+				p256::NistP256::OID,
+			),
+			PrivateKey::P384(k) => (
+				k.to_bytes().to_vec(),
+				k.public_key().to_encoded_point(false).as_bytes().to_vec(),
+				p384::NistP384::OID,
+			),
+			PrivateKey::P521(k) => (
+				k.to_bytes().to_vec(),
+				k.public_key().to_encoded_point(false).as_bytes().to_vec(),
+				p521::NistP521::OID,
+			),
+			_ => return Err(KeyError::new("SEC1 only applies to elliptic curve keys")),
+		};
+		let private_key = Zeroizing::new(private_key);
+		sec1::EcPrivateKey {
+			private_key: &private_key,
+			parameters: Some(sec1::EcParameters::NamedCurve(curve)),
+			public_key: Some(&public_key),
+		}
+		.to_der()
+		.map_err(|e| KeyError::new(e.to_string()))
+	}
+
+	fn public_jwk(pkcs8: &[u8]) -> Result<Jwk, KeyError> {
+		let jwk = match PrivateKey::from_pkcs8(pkcs8)? {
+			PrivateKey::Rsa(k) => Jwk::Rsa {
+				alg: None,
+				key_use: None,
+				e: b64(&k.e().to_bytes_be()),
+				n: b64(&k.n().to_bytes_be()),
+			},
+			PrivateKey::P256(k) => {
+				let point = k.public_key().to_encoded_point(false);
+				ecdsa_jwk(EcdsaCurve::P256, point.x(), point.y())
+			}
+			PrivateKey::P384(k) => {
+				let point = k.public_key().to_encoded_point(false);
+				ecdsa_jwk(EcdsaCurve::P384, point.x(), point.y())
+			}
+			PrivateKey::P521(k) => {
+				let point = k.public_key().to_encoded_point(false);
+				ecdsa_jwk(EcdsaCurve::P521, point.x(), point.y())
+			}
+			PrivateKey::Ed25519(k) => Jwk::Eddsa {
+				alg: false,
+				crv: EddsaCurve::Ed25519,
+				key_use: None,
+				x: b64(k.verifying_key().as_bytes()),
+			},
+		};
+		Ok(jwk)
+	}
+
+	fn sign(pkcs8: &[u8], data: &[u8]) -> Result<Vec<u8>, KeyError> {
+		let signature = match PrivateKey::from_pkcs8(pkcs8)? {
+			PrivateKey::Rsa(k) => rsa::pkcs1v15::SigningKey::<Sha256>::new(k)
+				.try_sign(data)
+				.map_err(|e| KeyError::new(e.to_string()))?
+				.to_vec(),
+			PrivateKey::P256(k) => {
+				let sig: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(k).sign(data);
+				sig.to_bytes().to_vec()
+			}
+			PrivateKey::P384(k) => {
+				let sig: p384::ecdsa::Signature = p384::ecdsa::SigningKey::from(k).sign(data);
+				sig.to_bytes().to_vec()
+			}
+			PrivateKey::P521(k) => {
+				let sk = p521::ecdsa::SigningKey::from_bytes(&k.to_bytes())
+					.map_err(|e| KeyError::new(e.to_string()))?;
+				let sig: p521::ecdsa::Signature = sk.sign(data);
+				sig.to_bytes().to_vec()
+			}
+			PrivateKey::Ed25519(k) => k.sign(data).to_bytes().to_vec(),
+		};
+		Ok(signature)
+	}
+
+	fn verify(jwk: &Jwk, data: &[u8], signature: &[u8]) -> Result<(), KeyError> {
+		let invalid =
+			|e: &dyn std::fmt::Display| KeyError::new(format!("invalid signature: {}", e));
+		match jwk {
+			Jwk::Rsa { e, n, .. } => {
+				let key = rsa::RsaPublicKey::new(
+					rsa::BigUint::from_bytes_be(&b64_decode(n)?),
+					rsa::BigUint::from_bytes_be(&b64_decode(e)?),
+				)
+				.map_err(|e| KeyError::new(e.to_string()))?;
+				let sig = rsa::pkcs1v15::Signature::try_from(signature).map_err(|e| invalid(&e))?;
+				rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key)
+					.verify(data, &sig)
+					.map_err(|e| invalid(&e))
+			}
+			Jwk::Ecdsa { crv, x, y, .. } => {
+				let mut point = vec![0x04];
+				point.extend_from_slice(&b64_decode(x)?);
+				point.extend_from_slice(&b64_decode(y)?);
+				match crv {
+					EcdsaCurve::P256 => {
+						let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&point)
+							.map_err(|e| KeyError::new(e.to_string()))?;
+						let sig = p256::ecdsa::Signature::from_slice(signature)
+							.map_err(|e| invalid(&e))?;
+						key.verify(data, &sig).map_err(|e| invalid(&e))
+					}
+					EcdsaCurve::P384 => {
+						let key = p384::ecdsa::VerifyingKey::from_sec1_bytes(&point)
+							.map_err(|e| KeyError::new(e.to_string()))?;
+						let sig = p384::ecdsa::Signature::from_slice(signature)
+							.map_err(|e| invalid(&e))?;
+						key.verify(data, &sig).map_err(|e| invalid(&e))
+					}
+					EcdsaCurve::P521 => {
+						let key = p521::ecdsa::VerifyingKey::from_sec1_bytes(&point)
+							.map_err(|e| KeyError::new(e.to_string()))?;
+						let sig = p521::ecdsa::Signature::from_slice(signature)
+							.map_err(|e| invalid(&e))?;
+						key.verify(data, &sig).map_err(|e| invalid(&e))
+					}
+				}
+			}
+			Jwk::Eddsa { crv, x, .. } => {
+				if *crv != EddsaCurve::Ed25519 {
+					return Err(KeyError::new(format!("{}: unsupported curve", crv)));
+				}
+				let x: [u8; 32] = b64_decode(x)?
+					.try_into()
+					.map_err(|_| KeyError::new("invalid Ed25519 public key"))?;
+				let key = ed25519_dalek::VerifyingKey::from_bytes(&x)
+					.map_err(|e| KeyError::new(e.to_string()))?;
+				let sig =
+					ed25519_dalek::Signature::from_slice(signature).map_err(|e| invalid(&e))?;
+				key.verify(data, &sig).map_err(|e| invalid(&e))
+			}
+		}
+	}
+}
+
+fn generate_rsa(bits: usize) -> Result<PrivateKey, KeyError> {
+	let key =
+		rsa::RsaPrivateKey::new(&mut OsRng, bits).map_err(|e| KeyError::new(e.to_string()))?;
+	Ok(PrivateKey::Rsa(key))
+}
+
+fn ecdsa_jwk<T: AsRef<[u8]>>(crv: EcdsaCurve, x: Option<&T>, y: Option<&T>) -> Jwk {
+	Jwk::Ecdsa {
+		alg: false,
+		crv,
+		key_use: None,
+		x: x.map(|v| b64(v.as_ref())).unwrap_or_default(),
+		y: y.map(|v| b64(v.as_ref())).unwrap_or_default(),
+	}
+}
@@ -0,0 +1,454 @@
+use crate::errors::HttpError;
+use crate::http::{HttpClient, HttpResponse};
+use crate::structs::Directory;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+type FetchLock = Arc<async_lock::Mutex<()>>;
+
+#[derive(Debug)]
+struct CachedDirectory {
+	directory: Arc<Directory>,
+	expires: Instant,
+	/// Whether the directory was fetched again because an endpoint was
+	/// missing from the cached one.
+	refreshed: bool,
+}
+
+/// In-memory cache of ACME directories, by directory URL.
+///
+/// Cloning the cache is cheap and every clone shares the same entries, so a
+/// single cache can be handed to every account using the same CA. Entries
+/// are kept as long as allowed by the `Cache-Control` header of the response,
+/// or for one hour if the server did not send any caching directive.
+///
+/// A directory is fetched again before its expiration when an endpoint is
+/// missing from it (see `endpoint`) or when the server answers a request
+/// with the 404 status code (see `observe`), since both may indicate that
+/// the CA changed its endpoints.
+///
+/// Concurrent fetches of the same directory are coalesced: while a directory
+/// is being fetched, other callers wait for the response instead of sending
+/// their own request.
+#[derive(Clone, Debug)]
+pub struct DirectoryCache {
+	entries: Arc<Mutex<HashMap<String, CachedDirectory>>>,
+	fetch_locks: Arc<Mutex<HashMap<String, FetchLock>>>,
+	default_ttl: Duration,
+}
+
+impl Default for DirectoryCache {
+	fn default() -> Self {
+		Self {
+			entries: Arc::new(Mutex::new(HashMap::new())),
+			fetch_locks: Arc::new(Mutex::new(HashMap::new())),
+			default_ttl: DEFAULT_TTL,
+		}
+	}
+}
+
+impl DirectoryCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Lifetime of the directories served without any caching directive.
+	pub fn default_ttl(mut self, ttl: Duration) -> Self {
+		self.default_ttl = ttl;
+		self
+	}
+
+	/// Returns the directory, which is only fetched if it is not cached or if
+	/// the cached one expired.
+	pub async fn get<C: HttpClient + ?Sized>(
+		&self,
+		client: &C,
+		url: &str,
+	) -> Result<Arc<Directory>, HttpError> {
+		if let Some((directory, _)) = self.cached(url)? {
+			return Ok(directory);
+		}
+		let lock = self.fetch_lock(url)?;
+		let _guard = lock.lock().await;
+		match self.cached(url)? {
+			Some((directory, _)) => Ok(directory),
+			None => self.fetch(client, url, false).await,
+		}
+	}
+
+	/// Fetches the directory, regardless of the cached one.
+	pub async fn refresh<C: HttpClient + ?Sized>(
+		&self,
+		client: &C,
+		url: &str,
+	) -> Result<Arc<Directory>, HttpError> {
+		let lock = self.fetch_lock(url)?;
+		let _guard = lock.lock().await;
+		self.fetch(client, url, false).await
+	}
+
+	/// Fetches the directory and caches it. The fetch lock of the directory
+	/// must be held.
+	async fn fetch<C: HttpClient + ?Sized>(
+		&self,
+		client: &C,
+		url: &str,
+		refreshed: bool,
+	) -> Result<Arc<Directory>, HttpError> {
+		let res = client.get(url).await?;
+		if !res.is_success() {
+			self.invalidate(url)?;
+			return Err(HttpError::new(format!(
+				"{}: unexpected HTTP status {}",
+				url, res.status
+			)));
+		}
+		let body = std::str::from_utf8(&res.body)
+			.map_err(|e| HttpError::new(format!("{}: {}", url, e)))?;
+		let directory = Arc::new(Directory::from_str(body)?);
+		let mut entries = self.lock()?;
+		match cache_lifetime(&res) {
+			Some(ttl) => {
+				let ttl = ttl.unwrap_or(self.default_ttl);
+				entries.insert(
+					url.to_string(),
+					CachedDirectory {
+						directory: directory.clone(),
+						expires: Instant::now() + ttl,
+						refreshed,
+					},
+				);
+			}
+			None => {
+				entries.remove(url);
+			}
+		}
+		Ok(directory)
+	}
+
+	/// Returns an endpoint of the directory. If the cached directory does not
+	/// have this endpoint, it is fetched again before giving up. This only
+	/// happens once per cached directory, so that optional endpoints the CA
+	/// does not provide do not cause a request every time.
+	///
+	/// ```no_run
+	/// # use acme_proto::{DirectoryCache, HttpClient, HttpError};
+	/// # const DIRECTORY_URL: &str = "https://acme-staging-v02.api.letsencrypt.org/directory";
+	/// # async fn example<C: HttpClient>(client: &C, cache: &DirectoryCache) -> Result<(), HttpError> {
+	/// let url = cache
+	///     .endpoint(client, DIRECTORY_URL, |d| d.new_authz.as_deref())
+	///     .await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub async fn endpoint<C, F>(
+		&self,
+		client: &C,
+		url: &str,
+		select: F,
+	) -> Result<String, HttpError>
+	where
+		C: HttpClient + ?Sized,
+		F: Fn(&Directory) -> Option<&str>,
+	{
+		if let Some((directory, _)) = self.cached(url)? {
+			if let Some(endpoint) = select(&directory) {
+				return Ok(endpoint.to_string());
+			}
+		}
+		let lock = self.fetch_lock(url)?;
+		let _guard = lock.lock().await;
+		let directory = match self.cached(url)? {
+			Some((directory, false)) if select(&directory).is_none() => {
+				self.fetch(client, url, true).await?
+			}
+			Some((directory, _)) => directory,
+			None => self.fetch(client, url, false).await?,
+		};
+		select(&directory)
+			.map(|e| e.to_string())
+			.ok_or_else(|| HttpError::new(format!("{}: endpoint not provided by the CA", url)))
+	}
+
+	/// Inspects a response received from an endpoint of the directory, which
+	/// is dropped from the cache if the endpoint does not exist anymore.
+	pub fn observe(&self, directory_url: &str, response: &HttpResponse) -> Result<(), HttpError> {
+		if response.status == 404 {
+			self.invalidate(directory_url)?;
+		}
+		Ok(())
+	}
+
+	/// Drops the directory from the cache.
+	pub fn invalidate(&self, url: &str) -> Result<(), HttpError> {
+		self.lock()?.remove(url);
+		Ok(())
+	}
+
+	fn cached(&self, url: &str) -> Result<Option<(Arc<Directory>, bool)>, HttpError> {
+		let entries = self.lock()?;
+		Ok(entries
+			.get(url)
+			.filter(|e| e.expires > Instant::now())
+			.map(|e| (e.directory.clone(), e.refreshed)))
+	}
+
+	fn fetch_lock(&self, url: &str) -> Result<FetchLock, HttpError> {
+		let mut locks = self
+			.fetch_locks
+			.lock()
+			.map_err(|e| HttpError::new(e.to_string()))?;
+		Ok(locks.entry(url.to_string()).or_default().clone())
+	}
+
+	fn lock(
+		&self,
+	) -> Result<std::sync::MutexGuard<'_, HashMap<String, CachedDirectory>>, HttpError> {
+		self.entries
+			.lock()
+			.map_err(|e| HttpError::new(e.to_string()))
+	}
+}
+
+/// How long a response may be cached according to its `Cache-Control`
+/// and `Age` headers (RFC 9111). `None` if it must not be cached at all and
+/// `Some(None)` if the server did not say.
+fn cache_lifetime(res: &HttpResponse) -> Option<Option<Duration>> {
+	let mut max_age = None;
+	for directive in res
+		.header_values("Cache-Control")
+		.flat_map(|v| v.split(','))
+	{
+		let (name, value) = match directive.split_once('=') {
+			Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+			None => (directive.trim(), None),
+		};
+		if name.eq_ignore_ascii_case("no-store") || name.eq_ignore_ascii_case("no-cache") {
+			return None;
+		}
+		if name.eq_ignore_ascii_case("max-age") {
+			match value.and_then(|v| v.parse::<u64>().ok()) {
+				Some(secs) => max_age = Some(secs),
+				None => return None,
+			}
+		}
+	}
+	let age = res
+		.header("Age")
+		.and_then(|v| v.trim().parse::<u64>().ok())
+		.unwrap_or(0);
+	match max_age {
+		Some(max_age) if max_age <= age => None,
+		Some(max_age) => Some(Some(Duration::from_secs(max_age - age))),
+		None => Some(None),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{cache_lifetime, DirectoryCache};
+	use crate::errors::HttpError;
+	use crate::http::{HttpClient, HttpResponse};
+	use async_trait::async_trait;
+	use std::sync::Mutex;
+	use std::time::Duration;
+
+	const URL: &str = "https://example.org/directory";
+
+	struct TestClient {
+		responses: Mutex<Vec<HttpResponse>>,
+		requests: Mutex<usize>,
+	}
+
+	impl TestClient {
+		fn new(responses: &[HttpResponse]) -> Self {
+			Self {
+				responses: Mutex::new(responses.iter().rev().cloned().collect()),
+				requests: Mutex::new(0),
+			}
+		}
+
+		fn requests(&self) -> usize {
+			*self.requests.lock().unwrap()
+		}
+	}
+
+	#[async_trait]
+	impl HttpClient for TestClient {
+		async fn get(&self, _url: &str) -> Result<HttpResponse, HttpError> {
+			*self.requests.lock().unwrap() += 1;
+			// Let concurrent callers run while the request is in flight.
+			tokio::task::yield_now().await;
+			self.responses
+				.lock()
+				.unwrap()
+				.pop()
+				.ok_or_else(|| HttpError::new("no more responses"))
+		}
+
+		async fn post_as_get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+			Err(HttpError::new(format!("{}: unexpected request", url)))
+		}
+	}
+
+	fn directory(new_authz: bool, cache_control: Option<&str>) -> HttpResponse {
+		let new_authz = if new_authz {
+			r#""newAuthz": "https://example.org/acme/new-authz","#
+		} else {
+			""
+		};
+		let body = format!(
+			r#"{{
+				"keyChange": "https://example.org/acme/key-change",
+				"newAccount": "https://example.org/acme/new-acct",
+				{}
+				"newNonce": "https://example.org/acme/new-nonce",
+				"newOrder": "https://example.org/acme/new-order",
+				"revokeCert": "https://example.org/acme/revoke-cert"
+			}}"#,
+			new_authz
+		);
+		let res = HttpResponse::new(200, body.as_bytes());
+		match cache_control {
+			Some(value) => res.with_header("Cache-Control", value),
+			None => res,
+		}
+	}
+
+	#[test]
+	fn lifetime() {
+		let res = |headers: &[(&str, &str)]| {
+			headers
+				.iter()
+				.fold(HttpResponse::new(200, b""), |r, (n, v)| r.with_header(n, v))
+		};
+		assert_eq!(cache_lifetime(&res(&[])), Some(None));
+		assert_eq!(
+			cache_lifetime(&res(&[("Cache-Control", "public, max-age=600")])),
+			Some(Some(Duration::from_secs(600)))
+		);
+		assert_eq!(
+			cache_lifetime(&res(&[
+				("cache-control", "max-age=\"600\""),
+				("Age", "100")
+			])),
+			Some(Some(Duration::from_secs(500)))
+		);
+		assert_eq!(
+			cache_lifetime(&res(&[("Cache-Control", "max-age=60"), ("Age", "60")])),
+			None
+		);
+		assert_eq!(
+			cache_lifetime(&res(&[
+				("Cache-Control", "max-age=60"),
+				("Cache-Control", "no-cache")
+			])),
+			None
+		);
+		assert_eq!(cache_lifetime(&res(&[("Cache-Control", "no-store")])), None);
+		assert_eq!(
+			cache_lifetime(&res(&[("Cache-Control", "max-age=abc")])),
+			None
+		);
+	}
+
+	#[tokio::test]
+	async fn cache() {
+		let client = TestClient::new(&[
+			directory(true, Some("max-age=600")),
+			directory(true, Some("no-store")),
+			directory(true, None),
+		]);
+		let cache = DirectoryCache::new();
+		let shared = cache.clone();
+		let d1 = cache.get(&client, URL).await.unwrap();
+		let d2 = shared.get(&client, URL).await.unwrap();
+		assert_eq!(d1.new_nonce, d2.new_nonce);
+		assert_eq!(client.requests(), 1);
+
+		cache.invalidate(URL).unwrap();
+		cache.get(&client, URL).await.unwrap();
+		cache.get(&client, URL).await.unwrap();
+		assert_eq!(client.requests(), 3);
+		cache.get(&client, URL).await.unwrap();
+		assert_eq!(client.requests(), 3);
+	}
+
+	#[tokio::test]
+	async fn default_ttl() {
+		let client = TestClient::new(&[directory(true, None), directory(true, None)]);
+		let cache = DirectoryCache::new().default_ttl(Duration::ZERO);
+		cache.get(&client, URL).await.unwrap();
+		cache.get(&client, URL).await.unwrap();
+		assert_eq!(client.requests(), 2);
+	}
+
+	#[tokio::test]
+	async fn refresh() {
+		let client = TestClient::new(&[
+			directory(false, None),
+			directory(true, None),
+			HttpResponse::new(404, b""),
+			directory(false, None),
+		]);
+		let cache = DirectoryCache::new();
+		let new_nonce = cache
+			.endpoint(&client, URL, |d| Some(d.new_nonce.as_str()))
+			.await;
+		assert_eq!(new_nonce.unwrap(), "https://example.org/acme/new-nonce");
+		assert_eq!(client.requests(), 1);
+		let new_authz = cache
+			.endpoint(&client, URL, |d| d.new_authz.as_deref())
+			.await;
+		assert_eq!(new_authz.unwrap(), "https://example.org/acme/new-authz");
+		assert_eq!(client.requests(), 2);
+
+		cache.observe(URL, &HttpResponse::new(400, b"")).unwrap();
+		cache.get(&client, URL).await.unwrap();
+		assert_eq!(client.requests(), 2);
+		cache.observe(URL, &HttpResponse::new(404, b"")).unwrap();
+		assert!(cache.get(&client, URL).await.is_err());
+		let new_authz = cache
+			.endpoint(&client, URL, |d| d.new_authz.as_deref())
+			.await;
+		assert!(new_authz.is_err());
+		assert_eq!(client.requests(), 4);
+	}
+
+	#[tokio::test]
+	async fn refresh_once() {
+		let client = TestClient::new(&[directory(false, None), directory(false, None)]);
+		let cache = DirectoryCache::new();
+		cache.get(&client, URL).await.unwrap();
+		for _ in 0..3 {
+			let new_authz = cache
+				.endpoint(&client, URL, |d| d.new_authz.as_deref())
+				.await;
+			assert!(new_authz.is_err());
+		}
+		assert_eq!(client.requests(), 2);
+	}
+
+	#[tokio::test]
+	async fn single_flight() {
+		let client = TestClient::new(&[directory(false, None), directory(false, None)]);
+		let cache = DirectoryCache::new();
+		let (d1, d2, d3) = tokio::join!(
+			cache.get(&client, URL),
+			cache.get(&client, URL),
+			cache.endpoint(&client, URL, |d| Some(d.new_order.as_str())),
+		);
+		assert!(d1.is_ok() && d2.is_ok() && d3.is_ok());
+		assert_eq!(client.requests(), 1);
+		let (a1, a2) = tokio::join!(
+			cache.endpoint(&client, URL, |d| d.new_authz.as_deref()),
+			cache.endpoint(&client, URL, |d| d.new_authz.as_deref()),
+		);
+		assert!(a1.is_err() && a2.is_err());
+		assert_eq!(client.requests(), 2);
+	}
+}
@@ -1,13 +1,443 @@
 use std::fmt;
 
+/// Error returned when a document cannot be parsed.
+///
+/// For JSON documents, the error also locates the offending member, both by
+/// its path (such as `challenges[0].url`) and by its position in the
+/// document. The underlying `serde_json::Error` is available as the source.
 #[derive(Debug)]
 pub struct DeserializeError {
 	pub(crate) data_type: String,
 	pub(crate) err_msg: String,
+	pub(crate) context: Option<Box<JsonContext>>,
+}
+
+/// Location of a JSON error, boxed to keep `DeserializeError` small.
+#[derive(Debug)]
+pub(crate) struct JsonContext {
+	path: Option<String>,
+	location: Option<(usize, usize)>,
+	snippet: Option<String>,
+	source: serde_json::Error,
+}
+
+impl DeserializeError {
+	pub(crate) fn new<T: ToString>(data_type: &str, err_msg: T) -> Self {
+		Self {
+			data_type: data_type.to_string(),
+			err_msg: err_msg.to_string(),
+			context: None,
+		}
+	}
+
+	/// Error raised by `serde_json` while parsing `data`, at the given path.
+	pub(crate) fn json(
+		data_type: &str,
+		data: Option<&[u8]>,
+		path: &str,
+		e: serde_json::Error,
+	) -> Self {
+		let mut err_msg = e.to_string();
+		let location = (e.line() != 0).then(|| (e.line(), e.column()));
+		if let Some((line, column)) = location {
+			let suffix = format!(" at line {} column {}", line, column);
+			if err_msg.ends_with(&suffix) {
+				err_msg.truncate(err_msg.len() - suffix.len());
+			}
+		}
+		let context = JsonContext {
+			path: (!path.is_empty() && path != ".").then(|| path.to_string()),
+			location,
+			snippet: data
+				.zip(location)
+				.and_then(|(data, (line, column))| snippet(data, line, column)),
+			source: e,
+		};
+		Self {
+			data_type: data_type.to_string(),
+			err_msg,
+			context: Some(Box::new(context)),
+		}
+	}
+
+	/// Type of the object that could not be parsed.
+	pub fn data_type(&self) -> &str {
+		&self.data_type
+	}
+
+	/// Path of the offending member, such as `challenges[0].url`.
+	pub fn path(&self) -> Option<&str> {
+		self.context.as_ref()?.path.as_deref()
+	}
+
+	/// Line of the error in the document, starting at 1.
+	pub fn line(&self) -> Option<usize> {
+		self.location().map(|(line, _)| line)
+	}
+
+	/// Column of the error in the document, starting at 1.
+	pub fn column(&self) -> Option<usize> {
+		self.location().map(|(_, column)| column)
+	}
+
+	/// Excerpt of the document around the error, in which anything looking
+	/// like a token, a nonce or key material is redacted, so it can be logged.
+	pub fn snippet(&self) -> Option<&str> {
+		self.context.as_ref()?.snippet.as_deref()
+	}
+
+	fn location(&self) -> Option<(usize, usize)> {
+		self.context.as_ref()?.location
+	}
+}
+
+/// Excerpt of a line of the document, around the given column.
+fn snippet(data: &[u8], line: usize, column: usize) -> Option<String> {
+	const CONTEXT: usize = 32;
+	// Runs of base64url characters at least this long are redacted.
+	const SECRET_LEN: usize = 16;
+
+	let data = String::from_utf8_lossy(data);
+	let line: Vec<char> = data.lines().nth(line.checked_sub(1)?)?.chars().collect();
+	let is_b64 = |c: &char| c.is_ascii_alphanumeric() || *c == '-' || *c == '_';
+	// Secrets are redacted on the whole line, so that none of them is only
+	// partially redacted at the edges of the excerpt.
+	let mut redacted = Vec::with_capacity(line.len());
+	let mut center = None;
+	let mut rest = &line[..];
+	while let Some(c) = rest.first() {
+		let len = if is_b64(c) {
+			rest.iter().take_while(|c| is_b64(c)).count()
+		} else {
+			1
+		};
+		if len >= SECRET_LEN {
+			redacted.extend("[redacted]".chars());
+		} else {
+			redacted.extend(&rest[..len]);
+		}
+		rest = &rest[len..];
+		if center.is_none() && line.len() - rest.len() >= column {
+			center = Some(redacted.len());
+		}
+	}
+	let center = center.unwrap_or(redacted.len());
+	let start = center.saturating_sub(CONTEXT);
+	let end = (center + CONTEXT).min(redacted.len());
+	let mut snippet: String = redacted[start..end].iter().collect();
+	if start > 0 {
+		snippet.insert(0, '…');
+	}
+	if end < redacted.len() {
+		snippet.push('…');
+	}
+	let snippet = snippet.trim().to_string();
+	(!snippet.is_empty()).then_some(snippet)
 }
 
 impl fmt::Display for DeserializeError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		writeln!(f, "invalid {} object: {}", self.data_type, self.err_msg)
+		write!(f, "invalid {} object: {}", self.data_type, self.err_msg)?;
+		if let Some(path) = self.path() {
+			write!(f, " at `{}`", path)?;
+		}
+		if let Some((line, column)) = self.location() {
+			write!(f, " (line {}, column {})", line, column)?;
+		}
+		writeln!(f)
+	}
+}
+
+impl std::error::Error for DeserializeError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.context
+			.as_ref()
+			.map(|c| &c.source as &(dyn std::error::Error + 'static))
+	}
+}
+
+#[derive(Debug)]
+pub struct SolverError {
+	pub(crate) err_msg: String,
+}
+
+impl SolverError {
+	pub fn new<T: ToString>(err_msg: T) -> Self {
+		Self {
+			err_msg: err_msg.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for SolverError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "challenge solver error: {}", self.err_msg)
+	}
+}
+
+impl std::error::Error for SolverError {}
+
+#[derive(Debug)]
+pub struct StoreError {
+	pub(crate) err_msg: String,
+}
+
+impl StoreError {
+	pub fn new<T: ToString>(err_msg: T) -> Self {
+		Self {
+			err_msg: err_msg.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for StoreError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "storage error: {}", self.err_msg)
+	}
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<std::io::Error> for StoreError {
+	fn from(e: std::io::Error) -> Self {
+		Self::new(e)
+	}
+}
+
+impl From<serde_json::Error> for StoreError {
+	fn from(e: serde_json::Error) -> Self {
+		Self::new(e)
+	}
+}
+
+#[derive(Debug)]
+pub struct KeyError {
+	pub(crate) err_msg: String,
+}
+
+impl KeyError {
+	pub fn new<T: ToString>(err_msg: T) -> Self {
+		Self {
+			err_msg: err_msg.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for KeyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "key error: {}", self.err_msg)
+	}
+}
+
+impl std::error::Error for KeyError {}
+
+#[derive(Debug)]
+pub struct RequestError {
+	pub(crate) err_msg: String,
+}
+
+impl RequestError {
+	pub fn new<T: ToString>(err_msg: T) -> Self {
+		Self {
+			err_msg: err_msg.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for RequestError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "invalid request: {}", self.err_msg)
+	}
+}
+
+impl std::error::Error for RequestError {}
+
+/// Failure to schedule a renewal, see `SuggestedWindow::random_instant`.
+#[derive(Debug)]
+pub struct RenewalError {
+	pub(crate) err_msg: String,
+}
+
+impl RenewalError {
+	pub fn new<T: ToString>(err_msg: T) -> Self {
+		Self {
+			err_msg: err_msg.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for RenewalError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "renewal error: {}", self.err_msg)
+	}
+}
+
+impl std::error::Error for RenewalError {}
+
+impl From<KeyError> for RenewalError {
+	fn from(e: KeyError) -> Self {
+		Self::new(e.err_msg)
+	}
+}
+
+#[derive(Debug)]
+pub struct HttpError {
+	pub(crate) err_msg: String,
+}
+
+impl HttpError {
+	pub fn new<T: ToString>(err_msg: T) -> Self {
+		Self {
+			err_msg: err_msg.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for HttpError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "HTTP error: {}", self.err_msg)
+	}
+}
+
+impl std::error::Error for HttpError {}
+
+/// Violations of the specifications found in a resource, see `Validate`.
+#[derive(Debug)]
+pub struct ValidationError {
+	pub(crate) violations: Vec<String>,
+}
+
+impl ValidationError {
+	/// Every violation found, prefixed by the path of the member concerned.
+	pub fn violations(&self) -> &[String] {
+		&self.violations
+	}
+}
+
+impl fmt::Display for ValidationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "the object does not comply with the specifications:")?;
+		for violation in &self.violations {
+			writeln!(f, " - {}", violation)?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Error returned when decoding a response, see `Quirks::decode`.
+#[derive(Debug)]
+pub enum ResponseError {
+	/// The server returned a problem document (RFC 8555 section 6.7).
+	Problem {
+		status: u16,
+		error: crate::structs::Error,
+	},
+	/// The response is not the one expected, because of its status or its
+	/// content type.
+	Http(HttpError),
+	/// The body of the response is invalid.
+	Deserialize(DeserializeError),
+}
+
+impl fmt::Display for ResponseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ResponseError::Problem { status, error } => write!(f, "HTTP {}: {}", status, error),
+			ResponseError::Http(e) => write!(f, "{}", e),
+			ResponseError::Deserialize(e) => write!(f, "{}", e),
+		}
+	}
+}
+
+impl std::error::Error for ResponseError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ResponseError::Problem { .. } => None,
+			ResponseError::Http(e) => Some(e),
+			ResponseError::Deserialize(e) => Some(e),
+		}
+	}
+}
+
+impl From<HttpError> for ResponseError {
+	fn from(e: HttpError) -> Self {
+		Self::Http(e)
+	}
+}
+
+impl From<DeserializeError> for ResponseError {
+	fn from(e: DeserializeError) -> Self {
+		Self::Deserialize(e)
+	}
+}
+
+impl From<ResponseError> for HttpError {
+	fn from(e: ResponseError) -> Self {
+		match e {
+			ResponseError::Http(e) => e,
+			e => Self::new(e.to_string().trim_end()),
+		}
+	}
+}
+
+impl From<DeserializeError> for HttpError {
+	fn from(e: DeserializeError) -> Self {
+		Self::new(e.to_string().trim_end())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::structs::{Authorization, Directory};
+	use std::error::Error as _;
+	use std::str::FromStr;
+
+	#[test]
+	fn deserialize_error_location() {
+		let data = r#"{
+			"status": "pending",
+			"identifier": {"type": "dns", "value": "example.org"},
+			"challenges": [{
+				"type": "http-01",
+				"token": "DGyRejmCefe7v4NfDGDKfA", "url": "chall/prV_B7yEyA4", "status": "pending"
+			}]
+		}"#;
+		let err = Authorization::from_str(data).unwrap_err();
+		assert_eq!(err.data_type(), "authorization");
+		assert_eq!(err.path(), Some("challenges[0].url"));
+		assert_eq!(err.line(), Some(6));
+		assert_eq!(err.column(), Some(65));
+		assert_eq!(
+			err.snippet(),
+			Some(r#"…ed]", "url": "chall/prV_B7yEyA4", "status": "pending""#)
+		);
+		assert_eq!(
+			err.to_string(),
+			"invalid authorization object: invalid URL `chall/prV_B7yEyA4`: relative URL without a base at `challenges[0].url` (line 6, column 65)\n"
+		);
+		let source = err.source().unwrap();
+		assert!(source.downcast_ref::<serde_json::Error>().is_some());
+	}
+
+	#[test]
+	fn deserialize_error_syntax() {
+		let err = Directory::from_str("{\"newNonce\": ").unwrap_err();
+		assert_eq!(err.path(), Some("newNonce"));
+		assert_eq!(err.line(), Some(1));
+		let err = Directory::from_str("[]").unwrap_err();
+		assert!(err.path().is_none());
+		assert_eq!(err.line(), Some(1));
+		let err = crate::structs::NewAuthorization::from_str(
+			r#"{"identifier": {"type": "dns", "value": "example.org"}} {}"#,
+		)
+		.unwrap_err();
+		assert!(err.path().is_none());
+		assert_eq!(err.column(), Some(57));
+		assert!(err.to_string().contains("trailing characters"));
+		let err = crate::structs::CertificateId::from_str("invalid").unwrap_err();
+		assert!(err.path().is_none() && err.line().is_none() && err.snippet().is_none());
+		assert!(err.source().is_none());
 	}
 }
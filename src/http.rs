@@ -0,0 +1,400 @@
+use crate::errors::HttpError;
+use async_trait::async_trait;
+use std::fmt;
+
+/// HTTP response received from an ACME server.
+///
+/// This crate does not send HTTP requests by itself: the caller's HTTP
+/// client builds these responses, see [`HttpClient`].
+#[derive(Clone, Debug, Default)]
+pub struct HttpResponse {
+	pub status: u16,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+	pub fn new(status: u16, body: &[u8]) -> Self {
+		Self {
+			status,
+			headers: Vec::new(),
+			body: body.to_vec(),
+		}
+	}
+
+	pub fn with_header(mut self, name: &str, value: &str) -> Self {
+		self.headers.push((name.to_string(), value.to_string()));
+		self
+	}
+
+	pub fn is_success(&self) -> bool {
+		(200..300).contains(&self.status)
+	}
+
+	/// Value of the first header with the given name, which is case
+	/// insensitive.
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers
+			.iter()
+			.find(|(n, _)| n.eq_ignore_ascii_case(name))
+			.map(|(_, v)| v.as_str())
+	}
+
+	pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.headers
+			.iter()
+			.filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+			.map(|(_, v)| v.as_str())
+	}
+
+	/// Targets of the `Link` headers with the given relation type (RFC 8288),
+	/// resolved against the request URL.
+	pub fn links(&self, rel: &str, request_url: &str) -> Vec<String> {
+		self.header_values("Link")
+			.flat_map(split_links)
+			.filter(|(_, params)| {
+				params.iter().any(|(name, value)| {
+					name.eq_ignore_ascii_case("rel")
+						&& value
+							.split_ascii_whitespace()
+							.any(|r| r.eq_ignore_ascii_case(rel))
+				})
+			})
+			.map(|(target, _)| resolve_url(request_url, &target))
+			.collect()
+	}
+}
+
+/// HTTP client provided by the caller.
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+	/// Sends an unauthenticated GET request, which ACME only uses for the
+	/// directory.
+	async fn get(&self, url: &str) -> Result<HttpResponse, HttpError>;
+
+	/// Sends a POST-as-GET request (RFC 8555 section 6.3), signed using the
+	/// account key.
+	async fn post_as_get(&self, url: &str) -> Result<HttpResponse, HttpError>;
+}
+
+type Link = (String, Vec<(String, String)>);
+
+fn split_links(header: &str) -> Vec<Link> {
+	let mut links = Vec::new();
+	let mut rest = header.trim_start();
+	while let Some(start) = rest.strip_prefix('<') {
+		let end = match start.find('>') {
+			Some(end) => end,
+			None => break,
+		};
+		let target = start[..end].to_string();
+		rest = &start[end + 1..];
+		let mut params = Vec::new();
+		loop {
+			rest = rest.trim_start();
+			match rest.strip_prefix(';') {
+				Some(r) => rest = r.trim_start(),
+				None => break,
+			}
+			let name_end = rest.find(['=', ';', ',']).unwrap_or(rest.len());
+			let name = rest[..name_end].trim().to_string();
+			rest = &rest[name_end..];
+			let mut value = String::new();
+			if let Some(r) = rest.strip_prefix('=') {
+				let r = r.trim_start();
+				if let Some(quoted) = r.strip_prefix('"') {
+					let end = quoted.find('"').unwrap_or(quoted.len());
+					value = quoted[..end].to_string();
+					rest = quoted.get(end + 1..).unwrap_or("");
+				} else {
+					let end = r.find([';', ',']).unwrap_or(r.len());
+					value = r[..end].trim().to_string();
+					rest = &r[end..];
+				}
+			}
+			params.push((name, value));
+		}
+		links.push((target, params));
+		rest = rest.trim_start();
+		rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+	}
+	links
+}
+
+/// Resolves a reference relative to a base URL (RFC 3986 section 5.2), as
+/// found in ACME `Link` and `Location` headers.
+///
+/// If the base URL is not absolute, the reference is returned as is.
+pub(crate) fn resolve_url(base: &str, reference: &str) -> String {
+	let base = UriParts::parse(base);
+	let r = UriParts::parse(reference);
+	let base_scheme = match base.scheme {
+		Some(scheme) => scheme,
+		None => return reference.to_string(),
+	};
+	let target = if r.scheme.is_some() {
+		UriParts {
+			path: remove_dot_segments(&r.path),
+			..r
+		}
+	} else if r.authority.is_some() {
+		UriParts {
+			scheme: Some(base_scheme),
+			path: remove_dot_segments(&r.path),
+			..r
+		}
+	} else {
+		let (path, query) = if r.path.is_empty() {
+			(base.path, r.query.or(base.query))
+		} else if r.path.starts_with('/') {
+			(remove_dot_segments(&r.path), r.query)
+		} else {
+			let merged = match base.path.rfind('/') {
+				Some(i) => format!("{}{}", &base.path[..=i], r.path),
+				None if base.authority.is_some() => format!("/{}", r.path),
+				None => r.path.to_string(),
+			};
+			(remove_dot_segments(&merged), r.query)
+		};
+		UriParts {
+			scheme: Some(base_scheme),
+			authority: base.authority,
+			path,
+			query,
+			fragment: r.fragment,
+		}
+	};
+	target.to_string()
+}
+
+/// Components of a URI reference (RFC 3986 section 3).
+struct UriParts<'a> {
+	scheme: Option<&'a str>,
+	authority: Option<&'a str>,
+	path: String,
+	query: Option<&'a str>,
+	fragment: Option<&'a str>,
+}
+
+impl<'a> UriParts<'a> {
+	fn parse(uri: &'a str) -> Self {
+		let (rest, fragment) = match uri.split_once('#') {
+			Some((rest, fragment)) => (rest, Some(fragment)),
+			None => (uri, None),
+		};
+		let (rest, query) = match rest.split_once('?') {
+			Some((rest, query)) => (rest, Some(query)),
+			None => (rest, None),
+		};
+		// The scheme ends at the first colon, which must come before any
+		// slash (RFC 3986 section 3.1).
+		let (scheme, rest) = match rest.split_once(':') {
+			Some((scheme, rest)) if is_scheme(scheme) => (Some(scheme), rest),
+			_ => (None, rest),
+		};
+		let (authority, path) = match rest.strip_prefix("//") {
+			Some(rest) => {
+				let end = rest.find('/').unwrap_or(rest.len());
+				(Some(&rest[..end]), &rest[end..])
+			}
+			None => (None, rest),
+		};
+		Self {
+			scheme,
+			authority,
+			path: path.to_string(),
+			query,
+			fragment,
+		}
+	}
+}
+
+impl fmt::Display for UriParts<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(scheme) = self.scheme {
+			write!(f, "{}:", scheme)?;
+		}
+		if let Some(authority) = self.authority {
+			write!(f, "//{}", authority)?;
+		}
+		write!(f, "{}", self.path)?;
+		if let Some(query) = self.query {
+			write!(f, "?{}", query)?;
+		}
+		if let Some(fragment) = self.fragment {
+			write!(f, "#{}", fragment)?;
+		}
+		Ok(())
+	}
+}
+
+fn is_scheme(s: &str) -> bool {
+	let mut chars = s.chars();
+	chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+		&& chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+}
+
+/// Removes the `.` and `..` segments of a path (RFC 3986 section 5.2.4).
+fn remove_dot_segments(path: &str) -> String {
+	let mut input = path;
+	let mut output = String::with_capacity(path.len());
+	while !input.is_empty() {
+		if let Some(rest) = input
+			.strip_prefix("../")
+			.or_else(|| input.strip_prefix("./"))
+		{
+			input = rest;
+		} else if input.starts_with("/./") || input == "/." {
+			input = &input[2..];
+			if input.is_empty() {
+				input = "/";
+			}
+		} else if input.starts_with("/../") || input == "/.." {
+			input = &input[3..];
+			if input.is_empty() {
+				input = "/";
+			}
+			output.truncate(output.rfind('/').unwrap_or(0));
+		} else if input == "." || input == ".." {
+			input = "";
+		} else {
+			let end = input[1..].find('/').map(|i| i + 1).unwrap_or(input.len());
+			output.push_str(&input[..end]);
+			input = &input[end..];
+		}
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{resolve_url, HttpResponse};
+
+	#[test]
+	fn headers() {
+		let res = HttpResponse::new(200, b"{}")
+			.with_header("Content-Type", "application/json")
+			.with_header("link", "<https://example.com/a>;rel=\"up\"")
+			.with_header("Link", "<https://example.com/b>;rel=\"up\"");
+		assert!(res.is_success());
+		assert!(!HttpResponse::new(404, b"").is_success());
+		assert_eq!(res.header("content-type"), Some("application/json"));
+		assert_eq!(res.header_values("LINK").count(), 2);
+		assert!(res.header("Location").is_none());
+	}
+
+	#[test]
+	fn links() {
+		let res = HttpResponse::new(200, b"")
+			.with_header(
+				"Link",
+				"<https://example.com/acme/directory>;rel=\"index\", </orders/2?cursor=a,b>; rel=next",
+			)
+			.with_header("Link", "<3>; title=\"x;y\"; rel=\"prev next\"");
+		assert_eq!(
+			res.links("next", "https://example.com/acme/acct/1/orders"),
+			[
+				"https://example.com/orders/2?cursor=a,b",
+				"https://example.com/acme/acct/1/3"
+			]
+		);
+		assert_eq!(
+			res.links("index", "https://example.com/acme/acct/1/orders"),
+			["https://example.com/acme/directory"]
+		);
+		assert!(res.links("up", "https://example.com/").is_empty());
+	}
+
+	#[test]
+	fn resolve() {
+		let base = "https://example.com/acme/orders?cursor=1";
+		assert_eq!(
+			resolve_url(base, "http://example.org/x"),
+			"http://example.org/x"
+		);
+		assert_eq!(
+			resolve_url(base, "//example.org/x"),
+			"https://example.org/x"
+		);
+		assert_eq!(resolve_url(base, "/x"), "https://example.com/x");
+		assert_eq!(resolve_url(base, "x?y=1"), "https://example.com/acme/x?y=1");
+		assert_eq!(
+			resolve_url("https://example.com", "x"),
+			"https://example.com/x"
+		);
+		assert_eq!(
+			resolve_url("https://example.com?q", "x"),
+			"https://example.com/x"
+		);
+		assert_eq!(resolve_url("not a URL", "x"), "x");
+	}
+
+	#[test]
+	fn resolve_relative_with_colon() {
+		let base = "https://example.com/acme/acct/1/orders";
+		assert_eq!(
+			resolve_url(base, "/orders?next=https://x"),
+			"https://example.com/orders?next=https://x"
+		);
+		assert_eq!(
+			resolve_url(base, "orders/a:b"),
+			"https://example.com/acme/acct/1/orders/a:b"
+		);
+		assert_eq!(
+			resolve_url(base, "./a:b"),
+			"https://example.com/acme/acct/1/a:b"
+		);
+		assert_eq!(resolve_url(base, "urn:ietf:x"), "urn:ietf:x");
+	}
+
+	#[test]
+	fn resolve_query_and_fragment() {
+		let base = "https://example.com/acme/orders?cursor=1#top";
+		assert_eq!(
+			resolve_url(base, "?cursor=2"),
+			"https://example.com/acme/orders?cursor=2"
+		);
+		assert_eq!(
+			resolve_url(base, "#next"),
+			"https://example.com/acme/orders?cursor=1#next"
+		);
+		assert_eq!(
+			resolve_url(base, ""),
+			"https://example.com/acme/orders?cursor=1"
+		);
+		assert_eq!(resolve_url(base, "x#y"), "https://example.com/acme/x#y");
+	}
+
+	#[test]
+	fn resolve_dot_segments() {
+		// Examples from RFC 3986 section 5.4.
+		let base = "http://a/b/c/d;p?q";
+		let tests = [
+			("g", "http://a/b/c/g"),
+			("./g", "http://a/b/c/g"),
+			("g/", "http://a/b/c/g/"),
+			(".", "http://a/b/c/"),
+			("./", "http://a/b/c/"),
+			("..", "http://a/b/"),
+			("../", "http://a/b/"),
+			("../g", "http://a/b/g"),
+			("../..", "http://a/"),
+			("../../g", "http://a/g"),
+			("../../../g", "http://a/g"),
+			("/./g", "http://a/g"),
+			("/../g", "http://a/g"),
+			("g.", "http://a/b/c/g."),
+			("..g", "http://a/b/c/..g"),
+			("./../g", "http://a/b/g"),
+			("g/./h", "http://a/b/c/g/h"),
+			("g/../h", "http://a/b/c/h"),
+			("g;x=1/../y", "http://a/b/c/y"),
+			("g?y/./x", "http://a/b/c/g?y/./x"),
+			("http:g", "http:g"),
+			("//g/../h", "http://g/h"),
+		];
+		for (reference, expected) in tests {
+			assert_eq!(resolve_url(base, reference), expected, "{}", reference);
+		}
+	}
+}
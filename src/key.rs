@@ -0,0 +1,314 @@
+#[cfg(any(feature = "crypto_openssl", feature = "crypto_rustcrypto"))]
+use crate::crypto::CryptoBackend;
+use crate::crypto::DefaultBackend;
+use crate::errors::KeyError;
+use crate::structs::{Jwk, JwsAlgorithm};
+use sec1::der::pem::LineEnding;
+use std::fmt;
+use zeroize::Zeroizing;
+
+#[cfg(test)]
+pub(crate) mod test_keys;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyType {
+	Rsa2048,
+	Rsa3072,
+	Rsa4096,
+	EcdsaP256,
+	EcdsaP384,
+	EcdsaP521,
+	Ed25519,
+}
+
+impl KeyType {
+	pub(crate) fn from_rsa_size(bits: usize) -> Result<Self, KeyError> {
+		match bits {
+			2048 => Ok(KeyType::Rsa2048),
+			3072 => Ok(KeyType::Rsa3072),
+			4096 => Ok(KeyType::Rsa4096),
+			s => Err(KeyError::new(format!("{}: unsupported RSA key size", s))),
+		}
+	}
+}
+
+impl fmt::Display for KeyType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			KeyType::Rsa2048 => "rsa2048",
+			KeyType::Rsa3072 => "rsa3072",
+			KeyType::Rsa4096 => "rsa4096",
+			KeyType::EcdsaP256 => "ecdsa_p256",
+			KeyType::EcdsaP384 => "ecdsa_p384",
+			KeyType::EcdsaP521 => "ecdsa_p521",
+			KeyType::Ed25519 => "ed25519",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+/// Private key of an ACME account.
+///
+/// Cryptographic operations are handled by the backend selected using cargo
+/// features, see [`CryptoBackend`].
+#[derive(Clone)]
+pub struct AccountKey {
+	key_type: KeyType,
+	pkcs8: Zeroizing<Vec<u8>>,
+	jwk: Jwk,
+}
+
+impl AccountKey {
+	pub fn generate(key_type: KeyType) -> Result<Self, KeyError> {
+		let pkcs8 = Zeroizing::new(DefaultBackend::generate_key(key_type)?);
+		let jwk = DefaultBackend::public_jwk(&pkcs8)?;
+		Ok(Self {
+			key_type,
+			pkcs8,
+			jwk,
+		})
+	}
+
+	pub fn from_pkcs8_der(data: &[u8]) -> Result<Self, KeyError> {
+		let key_type = DefaultBackend::key_type(data)?;
+		let jwk = DefaultBackend::public_jwk(data)?;
+		Ok(Self {
+			key_type,
+			pkcs8: Zeroizing::new(data.to_vec()),
+			jwk,
+		})
+	}
+
+	pub fn from_pkcs8_pem(data: &str) -> Result<Self, KeyError> {
+		let (label, der) = pem_to_der(data)?;
+		if label != "PRIVATE KEY" {
+			return Err(KeyError::new(format!(
+				"{}: not a PKCS#8 private key",
+				label
+			)));
+		}
+		Self::from_pkcs8_der(&der)
+	}
+
+	/// Imports an elliptic curve private key in the SEC1 format (RFC 5915).
+	pub fn from_sec1_der(data: &[u8]) -> Result<Self, KeyError> {
+		let pkcs8 = Zeroizing::new(DefaultBackend::sec1_to_pkcs8(data)?);
+		Self::from_pkcs8_der(&pkcs8)
+	}
+
+	pub fn from_sec1_pem(data: &str) -> Result<Self, KeyError> {
+		let (label, der) = pem_to_der(data)?;
+		if label != "EC PRIVATE KEY" {
+			return Err(KeyError::new(format!("{}: not a SEC1 private key", label)));
+		}
+		Self::from_sec1_der(&der)
+	}
+
+	/// Imports a PEM encoded private key, either in the PKCS#8 or SEC1 format.
+	pub fn from_pem(data: &str) -> Result<Self, KeyError> {
+		let (label, der) = pem_to_der(data)?;
+		match label.as_str() {
+			"PRIVATE KEY" => Self::from_pkcs8_der(&der),
+			"EC PRIVATE KEY" => Self::from_sec1_der(&der),
+			_ => Err(KeyError::new(format!("{}: unsupported PEM label", label))),
+		}
+	}
+
+	pub fn to_pkcs8_der(&self) -> Result<Vec<u8>, KeyError> {
+		Ok(self.pkcs8.to_vec())
+	}
+
+	pub fn to_pkcs8_pem(&self) -> Result<String, KeyError> {
+		der_to_pem("PRIVATE KEY", &self.pkcs8)
+	}
+
+	/// Exports an elliptic curve private key in the SEC1 format (RFC 5915).
+	///
+	/// The curve is always specified in the key parameters.
+	pub fn to_sec1_der(&self) -> Result<Vec<u8>, KeyError> {
+		match self.key_type {
+			KeyType::EcdsaP256 | KeyType::EcdsaP384 | KeyType::EcdsaP521 => {
+				DefaultBackend::pkcs8_to_sec1(&self.pkcs8)
+			}
+			_ => Err(KeyError::new(format!(
+				"{}: SEC1 only applies to elliptic curve keys",
+				self.key_type
+			))),
+		}
+	}
+
+	pub fn to_sec1_pem(&self) -> Result<String, KeyError> {
+		let der = Zeroizing::new(self.to_sec1_der()?);
+		der_to_pem("EC PRIVATE KEY", &der)
+	}
+
+	pub fn key_type(&self) -> KeyType {
+		self.key_type
+	}
+
+	/// Public part of the key, without the optional `alg` and `use` members.
+	pub fn jwk(&self) -> Jwk {
+		self.jwk.clone()
+	}
+
+	/// Algorithm used to sign JWS with this key.
+	pub fn algorithm(&self) -> JwsAlgorithm {
+		self.jwk.algorithm()
+	}
+
+	/// Signs the JWS signing input, the signature is returned in the format
+	/// defined by RFC 7518.
+	pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>, KeyError> {
+		DefaultBackend::sign(&self.pkcs8, data)
+	}
+}
+
+impl fmt::Debug for AccountKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("AccountKey")
+			.field("key_type", &self.key_type)
+			.finish_non_exhaustive()
+	}
+}
+
+fn pem_to_der(data: &str) -> Result<(String, Zeroizing<Vec<u8>>), KeyError> {
+	let (label, der) =
+		sec1::der::pem::decode_vec(data.as_bytes()).map_err(|e| KeyError::new(e.to_string()))?;
+	Ok((label.to_string(), Zeroizing::new(der)))
+}
+
+fn der_to_pem(label: &str, der: &[u8]) -> Result<String, KeyError> {
+	sec1::der::pem::encode_string(label, LineEnding::LF, der)
+		.map_err(|e| KeyError::new(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{test_keys, AccountKey, KeyType};
+	#[cfg(any(feature = "crypto_openssl", feature = "crypto_rustcrypto"))]
+	use crate::crypto::CryptoBackend;
+	use crate::crypto::DefaultBackend;
+	use crate::structs::JwsAlgorithm;
+
+	#[test]
+	fn import_keys() {
+		let tests = [
+			(
+				test_keys::ED25519_PKCS8,
+				KeyType::Ed25519,
+				r#"{"crv":"Ed25519","kty":"OKP","x":"ZTTINmHzickPshRgzdcJmgYtd9zQ4ih0SZ7yupbGwuk"}"#,
+				"iEwU9hemSZofodKbJyE60F2mT8bfAx-oemyWtGikQR4",
+			),
+			(
+				test_keys::P256_PKCS8,
+				KeyType::EcdsaP256,
+				r#"{"crv":"P-256","kty":"EC","x":"V-I3UQNlh3cF4Mev9uEPE2VZOpgniVo5BK4dQwOL9w0","y":"YaUl9i9YLFxKAnZQmpSBq69lKGY9cMTTdy-TPcbICc4"}"#,
+				"ldAQBXxSNclyDuv6EdmgCErq4KwCFIEj06u4IZFU4sY",
+			),
+			(
+				test_keys::P384_SEC1,
+				KeyType::EcdsaP384,
+				r#"{"crv":"P-384","kty":"EC","x":"HhB9l-Ehw5-ttcOh0KEGH_u7RT9UrVwRrz4-Fl9DlBTQsk7Nd_neCezrgHxcAWoI","y":"cZ8wWvA72uDYwFZxFJeW-yfeH5NyHNckWSbQL83-ut0OmbjcTMKrf0WfaMWr6Fkm"}"#,
+				"GR63kl7ig8sy78ySsWiN2kJaadtlmhxFXtBkd7r1bw0",
+			),
+			(
+				test_keys::P521_PKCS8,
+				KeyType::EcdsaP521,
+				r#"{"crv":"P-521","kty":"EC","x":"AdHHGeCr-eL-BXtXsKVxgoNkQMdI6FqwC4m1jLqwHDI7w76AtYi1MZrnmPR0e5p095q75c3pegU1YMW9RZPlG930","y":"AUPb2uo-O5ifYYbRs8Fk8f8lLdPFZLffoeRwqM9i1b_t4rPBVa9OPAUXM3-Hb8Pd7wsvbPlZ3Fm-OKP47FKxSjyy"}"#,
+				"P3dnDWQ3b4Ng5tlzG89tANAMGhd67CEMQRfW2uV-Gr8",
+			),
+			(
+				test_keys::RSA2048_PKCS8,
+				KeyType::Rsa2048,
+				r#"{"kty":"RSA","e":"AQAB","n":"rcizhD-IMqgnbpV0bSC96qoQMVptOyi5p96ZGGkeqEbCm04ta-wIDFlNbJBQ36vGLR6znG1hrdU_GGs62dKKqg5uhPXZiYYVRujkYoglhrwFNH5z60BVW5nC041i-4t9wQFGWtM2zNG6QIF11-ytJcjPSyUdJ0mP2pngGpnLJf9QU9bSugP5C9C7hnFdpCXPabFsQC7T-Cs-I61a8KsmCDfw9NTj_YuijIYL4Oujfy32kYbzXOfu6yY5m7y4ngh69_deAgFf_kipkaZYIm1bhDsoGGPbvBxB6tcGQUiv8LEDRt8qvlNNB1Oo0Qqwy9dc-bimAOSZGnDkJfLMWY5yTQ"}"#,
+				"vWIYg_HTKyGvcGmMlpcVz1Gzr5U7hoKxlZzD76x4EyE",
+			),
+		];
+		for (pem, key_type, jwk, thumbprint) in tests {
+			let key = AccountKey::from_pem(pem);
+			assert!(key.is_ok(), "import of {} key failed", key_type);
+			let key = key.unwrap();
+			assert_eq!(key.key_type(), key_type);
+			assert_eq!(serde_json::to_string(&key.jwk()).unwrap(), jwk);
+			assert_eq!(key.jwk().thumbprint(), thumbprint);
+		}
+	}
+
+	#[test]
+	fn pkcs8_round_trip() {
+		for pem in [
+			test_keys::ED25519_PKCS8,
+			test_keys::P256_PKCS8,
+			test_keys::P384_SEC1,
+			test_keys::P521_PKCS8,
+			test_keys::RSA2048_PKCS8,
+		] {
+			let key = AccountKey::from_pem(pem).unwrap();
+			let thumbprint = key.jwk().thumbprint();
+			let from_pem = AccountKey::from_pkcs8_pem(&key.to_pkcs8_pem().unwrap()).unwrap();
+			assert_eq!(from_pem.jwk().thumbprint(), thumbprint);
+			let from_der = AccountKey::from_pkcs8_der(&key.to_pkcs8_der().unwrap()).unwrap();
+			assert_eq!(from_der.jwk().thumbprint(), thumbprint);
+		}
+		let key = AccountKey::from_pkcs8_pem(test_keys::P256_PKCS8).unwrap();
+		assert_eq!(key.to_pkcs8_pem().unwrap(), test_keys::P256_PKCS8);
+	}
+
+	#[test]
+	fn sec1_round_trip() {
+		let key = AccountKey::from_sec1_pem(test_keys::P256_SEC1).unwrap();
+		assert_eq!(key.key_type(), KeyType::EcdsaP256);
+		assert_eq!(key.to_sec1_pem().unwrap(), test_keys::P256_SEC1);
+		let pkcs8 = AccountKey::from_pkcs8_pem(test_keys::P256_PKCS8).unwrap();
+		assert_eq!(pkcs8.to_sec1_der().unwrap(), key.to_sec1_der().unwrap());
+		let key = AccountKey::from_pem(test_keys::P521_PKCS8).unwrap();
+		let from_der = AccountKey::from_sec1_der(&key.to_sec1_der().unwrap()).unwrap();
+		assert_eq!(from_der.key_type(), KeyType::EcdsaP521);
+		assert_eq!(from_der.jwk().thumbprint(), key.jwk().thumbprint());
+	}
+
+	#[test]
+	fn sec1_invalid_type() {
+		for pem in [test_keys::ED25519_PKCS8, test_keys::RSA2048_PKCS8] {
+			let key = AccountKey::from_pem(pem).unwrap();
+			assert!(key.to_sec1_der().is_err());
+			assert!(key.to_sec1_pem().is_err());
+		}
+	}
+
+	#[test]
+	fn import_invalid() {
+		assert!(AccountKey::from_pkcs8_der(b"not a key").is_err());
+		assert!(AccountKey::from_sec1_der(b"not a key").is_err());
+		assert!(AccountKey::from_pem("not a key").is_err());
+		assert!(AccountKey::from_pkcs8_pem(test_keys::P256_SEC1).is_err());
+		assert!(AccountKey::from_sec1_pem(test_keys::P256_PKCS8).is_err());
+	}
+
+	#[test]
+	fn generate_keys() {
+		for key_type in [
+			KeyType::EcdsaP256,
+			KeyType::EcdsaP384,
+			KeyType::EcdsaP521,
+			KeyType::Ed25519,
+			KeyType::Rsa2048,
+		] {
+			let key = AccountKey::generate(key_type).unwrap();
+			assert_eq!(key.key_type(), key_type);
+			let imported = AccountKey::from_pkcs8_der(&key.to_pkcs8_der().unwrap()).unwrap();
+			assert_eq!(imported.key_type(), key_type);
+			assert_eq!(imported.jwk().thumbprint(), key.jwk().thumbprint());
+		}
+	}
+
+	#[test]
+	fn sign() {
+		let key = AccountKey::from_pem(test_keys::P384_SEC1).unwrap();
+		assert_eq!(key.algorithm(), JwsAlgorithm::Es384);
+		let signature = key.sign(b"data to sign").unwrap();
+		assert_eq!(signature.len(), 96);
+		let res = DefaultBackend::verify(&key.jwk(), b"data to sign", &signature);
+		assert!(res.is_ok(), "{:?}", res);
+	}
+}
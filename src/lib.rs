@@ -1,2 +1,51 @@
+mod ca;
+mod crypto;
+mod discovery;
 mod errors;
+mod http;
+mod key;
+mod pagination;
+mod preauthorization;
+mod quirks;
+mod response;
+mod signer;
+mod solver;
+mod store;
 mod structs;
+#[cfg(feature = "test_server")]
+mod test_server;
+mod time;
+mod validation;
+
+pub use ca::KnownCa;
+#[cfg(feature = "crypto_openssl")]
+pub use crypto::OpenSslBackend;
+#[cfg(feature = "crypto_rustcrypto")]
+pub use crypto::RustCryptoBackend;
+pub use crypto::{CryptoBackend, DefaultBackend};
+pub use discovery::DirectoryCache;
+pub use errors::{
+	DeserializeError, HttpError, KeyError, RenewalError, RequestError, ResponseError, SolverError,
+	StoreError, ValidationError,
+};
+pub use http::{HttpClient, HttpResponse};
+pub use key::{AccountKey, KeyType};
+pub use pagination::AccountOrders;
+pub use preauthorization::PreAuthorization;
+pub use quirks::Quirks;
+pub use response::ResponseBody;
+pub use signer::{JwsSigner, SignatureFormat};
+#[cfg(feature = "http01_responder")]
+pub use solver::Http01Responder;
+pub use solver::{ChallengeSolver, ChallengeSolvers};
+#[cfg(feature = "dns01_rfc2136")]
+pub use solver::{Rfc2136Solver, TsigAlgorithm, TsigKey};
+#[cfg(feature = "tls_alpn01_certificate")]
+pub use solver::{TlsAlpn01Certificate, ACME_TLS_ALPN_PROTOCOL};
+#[cfg(feature = "file_store")]
+pub use store::FileStore;
+pub use store::{AccountState, CertificateState, MemoryStore, Store};
+pub use structs::*;
+#[cfg(feature = "test_server")]
+pub use test_server::{TestServer, TestServerConfig, TestServerFault};
+pub use validation::Validate;
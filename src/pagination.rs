@@ -0,0 +1,210 @@
+use crate::errors::HttpError;
+use crate::http::HttpClient;
+use crate::structs::{OrdersList, Url};
+use std::collections::VecDeque;
+
+/// Iterates over the orders of an account, following the `next` links
+/// between the pages of the orders list.
+///
+/// ```no_run
+/// # use acme_proto::{AccountOrders, AccountResource, HttpClient, HttpError, Url};
+/// # async fn audit(order_url: Url) {}
+/// # async fn example<C: HttpClient>(client: &C, account: &AccountResource) -> Result<(), HttpError> {
+/// // Some servers do not provide it, see `Quirks::optional_account_orders`.
+/// let orders_url = match account.orders.as_deref() {
+///     Some(url) => url,
+///     None => return Ok(()),
+/// };
+/// let mut orders = AccountOrders::new(client, orders_url);
+/// while let Some(order_url) = orders.next().await {
+///     audit(order_url?).await;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct AccountOrders<'a, C: HttpClient + ?Sized> {
+	client: &'a C,
+	next_url: Option<String>,
+	visited: Vec<String>,
+	buffer: VecDeque<Url>,
+}
+
+impl<'a, C: HttpClient + ?Sized> AccountOrders<'a, C> {
+	/// Starts at the `orders` URL of the account.
+	pub fn new(client: &'a C, orders_url: &str) -> Self {
+		Self {
+			client,
+			next_url: Some(orders_url.to_string()),
+			visited: Vec::new(),
+			buffer: VecDeque::new(),
+		}
+	}
+
+	/// Fetches the next page of the list. Orders buffered by `next` are not
+	/// returned again.
+	pub async fn next_page(&mut self) -> Option<Result<OrdersList, HttpError>> {
+		let url = self.next_url.take()?;
+		if self.visited.contains(&url) {
+			return Some(Err(HttpError::new(format!(
+				"{}: loop in the orders list pagination",
+				url
+			))));
+		}
+		let res = match self.client.post_as_get(&url).await {
+			Ok(res) => res,
+			Err(e) => return Some(Err(e)),
+		};
+		if !res.is_success() {
+			return Some(Err(HttpError::new(format!(
+				"{}: unexpected HTTP status {}",
+				url, res.status
+			))));
+		}
+		let list = match OrdersList::from_slice(&res.body) {
+			Ok(list) => list,
+			Err(e) => return Some(Err(e.into())),
+		};
+		self.next_url = res.links("next", &url).into_iter().next();
+		self.visited.push(url);
+		Some(Ok(list))
+	}
+
+	/// URL of the next order.
+	pub async fn next(&mut self) -> Option<Result<Url, HttpError>> {
+		loop {
+			if let Some(order) = self.buffer.pop_front() {
+				return Some(Ok(order));
+			}
+			match self.next_page().await? {
+				Ok(list) => self.buffer.extend(list.orders),
+				Err(e) => return Some(Err(e)),
+			}
+		}
+	}
+
+	/// Fetches every remaining order URL.
+	pub async fn collect(mut self) -> Result<Vec<Url>, HttpError> {
+		let mut orders = Vec::new();
+		while let Some(order) = self.next().await {
+			orders.push(order?);
+		}
+		Ok(orders)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::AccountOrders;
+	use crate::errors::HttpError;
+	use crate::http::{HttpClient, HttpResponse};
+	use async_trait::async_trait;
+	use std::collections::HashMap;
+	use std::sync::Mutex;
+
+	#[derive(Default)]
+	struct TestClient {
+		pages: HashMap<String, HttpResponse>,
+		requests: Mutex<Vec<String>>,
+	}
+
+	impl TestClient {
+		fn page(mut self, url: &str, orders: &[&str], next: Option<&str>) -> Self {
+			let body = serde_json::json!({ "orders": orders }).to_string();
+			let mut res = HttpResponse::new(200, body.as_bytes())
+				.with_header("Content-Type", "application/json");
+			if let Some(next) = next {
+				res = res.with_header("Link", &format!("<{}>;rel=\"next\"", next));
+			}
+			self.pages.insert(url.to_string(), res);
+			self
+		}
+	}
+
+	#[async_trait]
+	impl HttpClient for TestClient {
+		async fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+			Err(HttpError::new(format!("{}: unexpected GET request", url)))
+		}
+
+		async fn post_as_get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+			self.requests.lock().unwrap().push(url.to_string());
+			Ok(self
+				.pages
+				.get(url)
+				.cloned()
+				.unwrap_or_else(|| HttpResponse::new(404, b"")))
+		}
+	}
+
+	#[tokio::test]
+	async fn pagination() {
+		let client = TestClient::default()
+			.page(
+				"https://example.com/acme/orders/1",
+				&[
+					"https://example.com/acme/order/a",
+					"https://example.com/acme/order/b",
+				],
+				Some("/acme/orders/1?cursor=2"),
+			)
+			.page("https://example.com/acme/orders/1?cursor=2", &[], Some("3"))
+			.page(
+				"https://example.com/acme/orders/3",
+				&["https://example.com/acme/order/c"],
+				None,
+			);
+		let orders = AccountOrders::new(&client, "https://example.com/acme/orders/1")
+			.collect()
+			.await;
+		assert!(orders.is_ok(), "{:?}", orders);
+		assert_eq!(
+			orders.unwrap(),
+			[
+				"https://example.com/acme/order/a",
+				"https://example.com/acme/order/b",
+				"https://example.com/acme/order/c"
+			]
+		);
+		assert_eq!(client.requests.lock().unwrap().len(), 3);
+	}
+
+	#[tokio::test]
+	async fn pagination_pages() {
+		let client = TestClient::default().page(
+			"https://example.com/orders",
+			&["https://example.com/order/a"],
+			None,
+		);
+		let mut orders = AccountOrders::new(&client, "https://example.com/orders");
+		let page = orders.next_page().await.unwrap().unwrap();
+		assert_eq!(page.orders, ["https://example.com/order/a"]);
+		assert!(orders.next_page().await.is_none());
+		assert!(orders.next().await.is_none());
+	}
+
+	#[tokio::test]
+	async fn pagination_errors() {
+		let client = TestClient::default()
+			.page(
+				"https://example.com/orders/1",
+				&["https://example.com/order/a"],
+				Some("https://example.com/orders/2"),
+			)
+			.page("https://example.com/orders/2", &[], Some("/orders/1"));
+		let mut orders = AccountOrders::new(&client, "https://example.com/orders/1");
+		assert_eq!(
+			orders.next().await.unwrap().unwrap(),
+			"https://example.com/order/a"
+		);
+		assert!(orders.next().await.unwrap().is_err());
+		assert!(orders.next().await.is_none());
+
+		let client = TestClient::default().page(
+			"https://example.com/orders/1",
+			&[],
+			Some("https://example.com/orders/404"),
+		);
+		let orders = AccountOrders::new(&client, "https://example.com/orders/1");
+		assert!(orders.collect().await.is_err());
+	}
+}
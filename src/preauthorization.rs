@@ -0,0 +1,297 @@
+use crate::errors::RequestError;
+use crate::structs::{Authorization, AuthorizationStatus, Directory, Identifier, NewAuthorization};
+use std::future::Future;
+
+/// Pre-authorization of identifiers ahead of orders (RFC 8555 section 7.4.1).
+///
+/// The identifiers are first compared to the authorizations already known by
+/// the client, so that only the missing ones are requested. Once created,
+/// authorizations which are still pending have to be solved like any other
+/// authorization, and can be recorded afterward.
+#[derive(Clone, Debug)]
+pub struct PreAuthorization {
+	covered: Vec<Identifier>,
+	missing: Vec<Identifier>,
+	pending: Vec<Authorization>,
+}
+
+impl PreAuthorization {
+	pub fn new(identifiers: &[Identifier], authorizations: &[Authorization]) -> Self {
+		let mut plan = Self {
+			covered: Vec::new(),
+			missing: Vec::new(),
+			pending: Vec::new(),
+		};
+		for identifier in identifiers {
+			if plan.covered.contains(identifier) || plan.missing.contains(identifier) {
+				continue;
+			}
+			if authorizations.iter().any(|a| a.covers(identifier)) {
+				plan.covered.push(identifier.clone());
+			} else {
+				plan.missing.push(identifier.clone());
+			}
+		}
+		plan
+	}
+
+	/// Identifiers already covered by a valid authorization.
+	pub fn covered(&self) -> &[Identifier] {
+		&self.covered
+	}
+
+	/// Identifiers which still require an authorization.
+	pub fn missing(&self) -> &[Identifier] {
+		&self.missing
+	}
+
+	/// Pending authorizations created by `create_missing`, including when it
+	/// failed afterward, which have to be solved or deactivated.
+	pub fn pending(&self) -> &[Authorization] {
+		&self.pending
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.missing.is_empty()
+	}
+
+	/// Requests to send in order to create the missing authorizations, which
+	/// have not already been created and are pending.
+	pub fn requests(&self) -> Vec<NewAuthorization> {
+		self.missing
+			.iter()
+			.filter(|i| !self.pending.iter().any(|p| p.identifier == **i))
+			.map(NewAuthorization::new)
+			.collect()
+	}
+
+	/// Marks the identifiers covered by this authorization as such.
+	pub fn record(&mut self, authz: &Authorization) {
+		let (covered, missing): (Vec<_>, Vec<_>) =
+			self.missing.drain(..).partition(|i| authz.covers(i));
+		self.missing = missing;
+		self.pending.retain(|p| !covered.contains(&p.identifier));
+		self.covered.extend(covered);
+	}
+
+	/// Creates the missing authorizations.
+	///
+	/// `create` is expected to send the newAuthz request and return the
+	/// resulting authorization. Authorizations which are already valid are
+	/// recorded, the pending ones are returned so that their challenges can be
+	/// solved. Authorizations in any other state are treated as failures.
+	///
+	/// Every request is checked before the first one is sent. If a request
+	/// fails, the authorizations created so far remain available using
+	/// `pending`, and calling this function again only requests the others.
+	pub async fn create_missing<F, Fut, E>(
+		&mut self,
+		directory: &Directory,
+		mut create: F,
+	) -> Result<Vec<Authorization>, E>
+	where
+		F: FnMut(NewAuthorization) -> Fut,
+		Fut: Future<Output = Result<Authorization, E>>,
+		E: From<RequestError>,
+	{
+		let requests = self.requests();
+		for req in &requests {
+			req.validate(directory)?;
+		}
+		let mut pending = Vec::new();
+		for req in requests {
+			let authz = create(req.clone()).await?;
+			match &authz.status {
+				AuthorizationStatus::Valid => self.record(&authz),
+				AuthorizationStatus::Pending => {
+					self.pending.push(authz.clone());
+					pending.push(authz);
+				}
+				status => {
+					return Err(RequestError::new(format!(
+						"{}: unexpected authorization status: {:?}",
+						req.identifier(),
+						status
+					))
+					.into())
+				}
+			}
+		}
+		Ok(pending)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::PreAuthorization;
+	use crate::errors::RequestError;
+	use crate::structs::{Authorization, Directory, Identifier, NewAuthorization};
+	use std::str::FromStr;
+
+	const DIRECTORY: &str = r#"{
+		"keyChange": "https://example.org/acme/key-change",
+		"newAccount": "https://example.org/acme/new-acct",
+		"newAuthz": "https://example.org/acme/new-authz",
+		"newNonce": "https://example.org/acme/new-nonce",
+		"newOrder": "https://example.org/acme/new-order",
+		"revokeCert": "https://example.org/acme/revoke-cert"
+	}"#;
+
+	fn authz(status: &str, value: &str) -> Authorization {
+		let data = format!(
+			r#"{{
+				"status": "{}",
+				"expires": "2999-01-01T00:00:00Z",
+				"identifier": {{"type": "dns", "value": "{}"}},
+				"challenges": []
+			}}"#,
+			status, value
+		);
+		Authorization::from_str(&data).unwrap()
+	}
+
+	#[test]
+	fn plan() {
+		let identifiers = [
+			Identifier::dns("a.example.org"),
+			Identifier::dns("b.example.org"),
+			Identifier::dns("a.example.org"),
+			Identifier::dns("c.example.org"),
+		];
+		let known = [
+			authz("valid", "a.example.org"),
+			authz("pending", "b.example.org"),
+		];
+		let mut plan = PreAuthorization::new(&identifiers, &known);
+		assert_eq!(plan.covered(), &[Identifier::dns("a.example.org")]);
+		assert_eq!(
+			plan.missing(),
+			&[
+				Identifier::dns("b.example.org"),
+				Identifier::dns("c.example.org")
+			]
+		);
+		assert!(!plan.is_complete());
+		assert_eq!(plan.requests().len(), 2);
+		plan.record(&authz("valid", "c.example.org"));
+		assert_eq!(plan.missing(), &[Identifier::dns("b.example.org")]);
+		assert_eq!(plan.covered().len(), 2);
+		plan.record(&authz("valid", "b.example.org"));
+		assert!(plan.is_complete());
+	}
+
+	#[tokio::test]
+	async fn create_missing() {
+		let directory = Directory::from_str(DIRECTORY).unwrap();
+		let identifiers = [
+			Identifier::dns("a.example.org"),
+			Identifier::dns("b.example.org"),
+			Identifier::dns("c.example.org"),
+		];
+		let mut plan = PreAuthorization::new(&identifiers, &[authz("valid", "a.example.org")]);
+		let mut requested = Vec::new();
+		let pending = plan
+			.create_missing(&directory, |req: NewAuthorization| {
+				let value = req.identifier().value.clone();
+				requested.push(value.clone());
+				async move {
+					let status = if value == "b.example.org" {
+						"valid"
+					} else {
+						"pending"
+					};
+					Ok::<_, RequestError>(authz(status, &value))
+				}
+			})
+			.await
+			.unwrap();
+		assert_eq!(requested, ["b.example.org", "c.example.org"]);
+		assert_eq!(pending.len(), 1);
+		assert_eq!(pending[0].identifier, Identifier::dns("c.example.org"));
+		assert_eq!(plan.missing(), &[Identifier::dns("c.example.org")]);
+		assert_eq!(plan.pending(), pending.as_slice());
+		assert!(plan.requests().is_empty());
+		plan.record(&authz("valid", "c.example.org"));
+		assert!(plan.is_complete());
+		assert!(plan.pending().is_empty());
+	}
+
+	#[tokio::test]
+	async fn create_missing_partial_failure() {
+		let directory = Directory::from_str(DIRECTORY).unwrap();
+		let identifiers = [
+			Identifier::dns("a.example.org"),
+			Identifier::dns("b.example.org"),
+		];
+		let mut plan = PreAuthorization::new(&identifiers, &[]);
+		let res = plan
+			.create_missing(&directory, |req: NewAuthorization| async move {
+				match req.identifier().value.as_str() {
+					"a.example.org" => Ok(authz("pending", "a.example.org")),
+					_ => Err(RequestError::new("rate limited")),
+				}
+			})
+			.await;
+		assert!(res.is_err());
+		assert_eq!(plan.pending().len(), 1);
+		assert_eq!(
+			plan.pending()[0].identifier,
+			Identifier::dns("a.example.org")
+		);
+		let mut requested = Vec::new();
+		let pending = plan
+			.create_missing(&directory, |req: NewAuthorization| {
+				requested.push(req.identifier().value.clone());
+				async move { Ok::<_, RequestError>(authz("pending", &req.identifier().value)) }
+			})
+			.await
+			.unwrap();
+		assert_eq!(requested, ["b.example.org"]);
+		assert_eq!(pending.len(), 1);
+		assert_eq!(plan.pending().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn create_missing_validates_first() {
+		let directory = Directory::from_str(DIRECTORY).unwrap();
+		let identifiers = [
+			Identifier::dns("a.example.org"),
+			Identifier::dns("*.example.org"),
+		];
+		let mut plan = PreAuthorization::new(&identifiers, &[]);
+		let mut sent = false;
+		let res = plan
+			.create_missing(&directory, |req: NewAuthorization| {
+				sent = true;
+				async move { Ok::<_, RequestError>(authz("pending", &req.identifier().value)) }
+			})
+			.await;
+		assert!(res.is_err());
+		assert!(!sent);
+		assert!(plan.pending().is_empty());
+	}
+
+	#[tokio::test]
+	async fn create_missing_errors() {
+		let mut directory = Directory::from_str(DIRECTORY).unwrap();
+		let identifiers = [Identifier::dns("a.example.org")];
+		let mut plan = PreAuthorization::new(&identifiers, &[]);
+		let res = plan
+			.create_missing(&directory, |req: NewAuthorization| async move {
+				Ok::<_, RequestError>(authz("invalid", &req.identifier().value))
+			})
+			.await;
+		assert!(res.is_err());
+		directory.new_authz = None;
+		let mut sent = false;
+		let res = plan
+			.create_missing(&directory, |_| {
+				sent = true;
+				async { Err::<Authorization, _>(RequestError::new("unexpected request")) }
+			})
+			.await;
+		assert!(res.is_err());
+		assert!(!sent);
+		assert!(!plan.is_complete());
+	}
+}
@@ -0,0 +1,467 @@
+use crate::errors::{DeserializeError, HttpError, ResponseError};
+use crate::http::{resolve_url, HttpResponse};
+use crate::response::{ResponseBody, PROBLEM_JSON};
+use crate::structs::{from_json_reader_checked, from_json_slice_checked, AccountResource, Error};
+use serde::Deserialize;
+use std::io::Read;
+use std::str::FromStr;
+
+/// Deviations from the specifications tolerated when talking to a server.
+///
+/// Since each server has its own set of deviations, quirks are selected at
+/// runtime, which allows a single client to talk to several servers. Some
+/// profiles are provided and can be selected by name: `rfc8555` (which is
+/// also the default), `strict`, `boulder` and `lax`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+	/// Account objects may not include the `orders` URL (see Boulder issue
+	/// #3335). Enabled by default if the `opt_account_orders` feature is.
+	pub optional_account_orders: bool,
+	/// Responses may not have the content type defined by the
+	/// specifications.
+	pub lax_content_type: bool,
+	/// The `Location` header may be a relative reference, use the http scheme
+	/// or be repeated.
+	pub lax_location: bool,
+	/// Objects may have members not defined by the specifications, which are
+	/// ignored. Otherwise, such members are rejected by `decode` and
+	/// `parse_account`.
+	pub extra_fields: bool,
+}
+
+impl Default for Quirks {
+	fn default() -> Self {
+		Self::rfc8555()
+	}
+}
+
+impl Quirks {
+	/// Behavior of servers compliant with RFC 8555, which may add members to
+	/// objects.
+	pub fn rfc8555() -> Self {
+		Self {
+			optional_account_orders: cfg!(feature = "opt_account_orders"),
+			lax_content_type: false,
+			lax_location: false,
+			extra_fields: true,
+		}
+	}
+
+	/// No deviation is tolerated at all, which is mostly useful to test
+	/// servers.
+	pub fn strict() -> Self {
+		Self {
+			optional_account_orders: false,
+			lax_content_type: false,
+			lax_location: false,
+			extra_fields: false,
+		}
+	}
+
+	/// Servers based on Boulder, like Let's Encrypt.
+	pub fn boulder() -> Self {
+		Self {
+			optional_account_orders: true,
+			..Self::rfc8555()
+		}
+	}
+
+	/// Every deviation is tolerated, which may be required by some appliances.
+	pub fn lax() -> Self {
+		Self {
+			optional_account_orders: true,
+			lax_content_type: true,
+			lax_location: true,
+			extra_fields: true,
+		}
+	}
+
+	/// Parses an account object, from either a string or bytes.
+	pub fn parse_account<D: AsRef<[u8]> + ?Sized>(
+		&self,
+		data: &D,
+	) -> Result<AccountResource, DeserializeError> {
+		let account =
+			from_json_slice_checked(data.as_ref(), "account resource", self.extra_fields)?;
+		self.check_account(account)
+	}
+
+	/// Parses an account object from a reader, such as a response body being
+	/// received.
+	pub fn parse_account_reader<R: Read>(
+		&self,
+		reader: R,
+	) -> Result<AccountResource, DeserializeError> {
+		let account = from_json_reader_checked(reader, "account resource", self.extra_fields)?;
+		self.check_account(account)
+	}
+
+	fn check_account(&self, account: AccountResource) -> Result<AccountResource, DeserializeError> {
+		if account.orders.is_none() && !self.optional_account_orders {
+			return Err(DeserializeError::new(
+				"account resource",
+				"missing field `orders`",
+			));
+		}
+		Ok(account)
+	}
+
+	/// Checks the media type of a response, parameters being ignored.
+	pub fn check_content_type(
+		&self,
+		response: &HttpResponse,
+		expected: &str,
+	) -> Result<(), HttpError> {
+		if self.lax_content_type {
+			return Ok(());
+		}
+		let content_type = response
+			.header("Content-Type")
+			.ok_or_else(|| HttpError::new("missing Content-Type header"))?;
+		if !media_type(content_type).eq_ignore_ascii_case(expected) {
+			return Err(HttpError::new(format!(
+				"{}: unexpected content type, expected {}",
+				content_type, expected
+			)));
+		}
+		Ok(())
+	}
+
+	/// Decodes a response, which is either the expected resource or a problem
+	/// document, depending on its status and content type. A problem document
+	/// is therefore never parsed as a resource.
+	///
+	/// ```no_run
+	/// # use acme_proto::{HttpResponse, Order, Quirks, ResponseError};
+	/// # fn example(quirks: &Quirks, response: &HttpResponse) -> Result<(), ResponseError> {
+	/// match quirks.decode::<Order>(response) {
+	///     Ok(order) => println!("order is {:?}", order.status),
+	///     Err(ResponseError::Problem { error, .. }) => eprintln!("{}", error),
+	///     Err(e) => return Err(e),
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn decode<T: ResponseBody>(&self, response: &HttpResponse) -> Result<T, ResponseError> {
+		let is_problem = response
+			.header("Content-Type")
+			.is_some_and(|t| media_type(t).eq_ignore_ascii_case(PROBLEM_JSON));
+		if is_problem {
+			return Err(ResponseError::Problem {
+				status: response.status,
+				error: Error::from_slice(&response.body)?,
+			});
+		}
+		if !response.is_success() {
+			if self.lax_content_type {
+				if let Ok(error) = Error::from_slice(&response.body) {
+					return Err(ResponseError::Problem {
+						status: response.status,
+						error,
+					});
+				}
+			}
+			return Err(
+				HttpError::new(format!("unexpected HTTP status {}", response.status)).into(),
+			);
+		}
+		self.check_content_type(response, T::CONTENT_TYPE)?;
+		Ok(T::decode(&response.body, self)?)
+	}
+
+	/// URL of the resource created by a request, from the `Location` header.
+	pub fn location(
+		&self,
+		response: &HttpResponse,
+		request_url: &str,
+	) -> Result<String, HttpError> {
+		let mut values = response.header_values("Location").map(str::trim);
+		let location = values
+			.next()
+			.ok_or_else(|| HttpError::new("missing Location header"))?;
+		if self.lax_location {
+			return Ok(resolve_url(request_url, location));
+		}
+		if values.next().is_some() {
+			return Err(HttpError::new("multiple Location headers"));
+		}
+		if !location.starts_with("https://") {
+			return Err(HttpError::new(format!(
+				"{}: Location is not an absolute https URL",
+				location
+			)));
+		}
+		Ok(location.to_string())
+	}
+}
+
+/// Media type of a `Content-Type` header, without its parameters.
+fn media_type(content_type: &str) -> &str {
+	content_type.split(';').next().unwrap_or("").trim()
+}
+
+impl FromStr for Quirks {
+	type Err = DeserializeError;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		match name.trim().to_ascii_lowercase().as_str() {
+			"rfc8555" | "default" => Ok(Self::rfc8555()),
+			"strict" => Ok(Self::strict()),
+			"boulder" => Ok(Self::boulder()),
+			"lax" => Ok(Self::lax()),
+			_ => Err(DeserializeError::new(
+				"quirks profile",
+				format!("{}: unknown name", name),
+			)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Quirks;
+	use crate::errors::ResponseError;
+	use crate::http::HttpResponse;
+	use crate::structs::{AccountResource, Authorization, CertificateChain, Directory, Order};
+	use std::str::FromStr;
+
+	const BOULDER_ACCOUNT: &str = r#"{
+		"key": {
+			"kty": "EC",
+			"crv": "P-256",
+			"x": "eGtn9fKmu5jNeygIfB9slVf9n4Q3rAuZIrh7qmg8PYw",
+			"y": "Xkg9J4XJMELZVHn2UzqzkhZ7dgs5m2bWtzKoSnDtPWU"
+		},
+		"contact": ["mailto:admin@example.org"],
+		"initialIp": "192.0.2.1",
+		"createdAt": "2025-01-02T04:00:00Z",
+		"status": "valid"
+	}"#;
+
+	#[test]
+	fn profiles() {
+		assert_eq!(Quirks::default(), Quirks::rfc8555());
+		assert_eq!(Quirks::from_str("Boulder").unwrap(), Quirks::boulder());
+		assert_eq!(Quirks::from_str("default").unwrap(), Quirks::rfc8555());
+		assert_eq!(Quirks::from_str("strict").unwrap(), Quirks::strict());
+		assert_eq!(Quirks::from_str("lax").unwrap(), Quirks::lax());
+		assert!(Quirks::from_str("pebble").is_err());
+		let quirks: Quirks = serde_json::from_str(r#"{"lax_location": true}"#).unwrap();
+		assert_eq!(
+			quirks,
+			Quirks {
+				lax_location: true,
+				..Quirks::default()
+			}
+		);
+	}
+
+	#[test]
+	fn account() {
+		assert!(Quirks::boulder().parse_account(BOULDER_ACCOUNT).is_ok());
+		assert!(Quirks::lax().parse_account(BOULDER_ACCOUNT).is_ok());
+		assert!(Quirks::strict().parse_account(BOULDER_ACCOUNT).is_err());
+		let quirks = Quirks {
+			optional_account_orders: false,
+			..Quirks::boulder()
+		};
+		assert!(quirks.parse_account(BOULDER_ACCOUNT).is_err());
+		assert_eq!(
+			Quirks::rfc8555().parse_account(BOULDER_ACCOUNT).is_ok(),
+			cfg!(feature = "opt_account_orders")
+		);
+
+		let account = r#"{
+			"status": "valid",
+			"orders": "https://example.org/acme/orders/1"
+		}"#;
+		let res = Quirks::strict().parse_account(account);
+		assert!(res.is_ok(), "{:?}", res);
+		let parsed = res.unwrap();
+		assert_eq!(
+			Quirks::strict().parse_account(account.as_bytes()).unwrap(),
+			parsed
+		);
+		assert_eq!(
+			AccountResource::from_slice(account.as_bytes()).unwrap(),
+			parsed
+		);
+		assert_eq!(
+			AccountResource::from_reader(account.as_bytes()).unwrap(),
+			parsed
+		);
+		assert!(Quirks::strict()
+			.parse_account_reader(BOULDER_ACCOUNT.as_bytes())
+			.is_err());
+		assert!(Quirks::strict().parse_account("[]").is_err());
+	}
+
+	#[test]
+	fn content_type() {
+		let res = HttpResponse::new(200, b"{}")
+			.with_header("content-type", "Application/JSON; charset=utf-8");
+		assert!(Quirks::strict()
+			.check_content_type(&res, "application/json")
+			.is_ok());
+		assert!(Quirks::strict()
+			.check_content_type(&res, "application/problem+json")
+			.is_err());
+		let missing = HttpResponse::new(200, b"{}");
+		let other = HttpResponse::new(200, b"{}").with_header("Content-Type", "text/plain");
+		for res in [missing, other] {
+			assert!(Quirks::default()
+				.check_content_type(&res, "application/json")
+				.is_err());
+			assert!(Quirks::lax()
+				.check_content_type(&res, "application/json")
+				.is_ok());
+		}
+	}
+
+	#[test]
+	fn location() {
+		let url = "https://example.org/acme/new-order";
+		let res = HttpResponse::new(201, b"{}")
+			.with_header("Location", "https://example.org/acme/order/1");
+		assert_eq!(
+			Quirks::default().location(&res, url).unwrap(),
+			"https://example.org/acme/order/1"
+		);
+		let relative = HttpResponse::new(201, b"{}").with_header("Location", "order/1");
+		assert!(Quirks::default().location(&relative, url).is_err());
+		assert_eq!(
+			Quirks::lax().location(&relative, url).unwrap(),
+			"https://example.org/acme/order/1"
+		);
+		let repeated = res
+			.clone()
+			.with_header("Location", "https://example.org/acme/order/2");
+		assert!(Quirks::default().location(&repeated, url).is_err());
+		assert_eq!(
+			Quirks::lax().location(&repeated, url).unwrap(),
+			"https://example.org/acme/order/1"
+		);
+		let missing = HttpResponse::new(201, b"{}");
+		assert!(Quirks::lax().location(&missing, url).is_err());
+	}
+
+	#[test]
+	fn decode() {
+		let order = r#"{
+			"status": "pending",
+			"identifiers": [{"type": "dns", "value": "example.org"}],
+			"authorizations": ["https://example.org/acme/authz/1"],
+			"finalize": "https://example.org/acme/order/1/finalize"
+		}"#;
+		let problem = r#"{
+			"type": "urn:ietf:params:acme:error:rateLimited",
+			"detail": "too many new orders"
+		}"#;
+		let res = HttpResponse::new(201, order.as_bytes())
+			.with_header("Content-Type", "application/json");
+		let decoded: Order = Quirks::strict().decode(&res).unwrap();
+		assert_eq!(decoded.authorizations.len(), 1);
+		let res = HttpResponse::new(429, problem.as_bytes())
+			.with_header("Content-Type", "application/problem+json");
+		match Quirks::strict().decode::<Order>(&res) {
+			Err(ResponseError::Problem { status, error }) => {
+				assert_eq!(status, 429);
+				assert_eq!(error.error_type(), "urn:ietf:params:acme:error:rateLimited");
+			}
+			res => panic!("unexpected result: {:?}", res),
+		}
+		// Problem documents without the proper content type are only
+		// recognized with lax quirks.
+		let res = HttpResponse::new(429, problem.as_bytes())
+			.with_header("Content-Type", "application/json");
+		assert!(matches!(
+			Quirks::default().decode::<Order>(&res),
+			Err(ResponseError::Http(_))
+		));
+		assert!(matches!(
+			Quirks::lax().decode::<Order>(&res),
+			Err(ResponseError::Problem { status: 429, .. })
+		));
+		let res = HttpResponse::new(200, problem.as_bytes())
+			.with_header("Content-Type", "application/json");
+		let err = Quirks::default().decode::<Order>(&res).unwrap_err();
+		assert!(matches!(err, ResponseError::Deserialize(_)));
+		let res = HttpResponse::new(200, order.as_bytes())
+			.with_header("Content-Type", "application/pem-certificate-chain");
+		assert!(matches!(
+			Quirks::default().decode::<Order>(&res),
+			Err(ResponseError::Http(_))
+		));
+		let res = HttpResponse::new(400, b"<html></html>")
+			.with_header("Content-Type", "application/problem+json");
+		assert!(matches!(
+			Quirks::default().decode::<Order>(&res),
+			Err(ResponseError::Deserialize(_))
+		));
+	}
+
+	#[test]
+	fn decode_extra_fields() {
+		let decode = |quirks: Quirks, body: &str| {
+			let res = HttpResponse::new(200, body.as_bytes())
+				.with_header("Content-Type", "application/json");
+			quirks.decode::<Authorization>(&res)
+		};
+		let authz = r#"{
+			"status": "pending",
+			"identifier": {"type": "dns", "value": "example.org"},
+			"challenges": [{
+				"type": "http-01",
+				"url": "https://example.org/acme/chall/1",
+				"status": "pending",
+				"token": "DGyRejmCefe7v4NfDGDKfA",
+				"validationRecord": []
+			}]
+		}"#;
+		assert!(decode(Quirks::default(), authz).is_ok());
+		match decode(Quirks::strict(), authz) {
+			Err(ResponseError::Deserialize(e)) => assert_eq!(
+				e.to_string(),
+				"invalid authorization object: unknown member `challenges[0].validationRecord`\n"
+			),
+			res => panic!("unexpected result: {:?}", res),
+		}
+
+		let directory = r#"{
+			"newNonce": "https://example.org/acme/new-nonce",
+			"newAccount": "https://example.org/acme/new-acct",
+			"newOrder": "https://example.org/acme/new-order",
+			"revokeCert": "https://example.org/acme/revoke-cert",
+			"keyChange": "https://example.org/acme/key-change",
+			"meta": {"website": "https://example.org", "caaIdentities": ["example.org"]}
+		}"#;
+		let res = HttpResponse::new(200, directory.as_bytes())
+			.with_header("Content-Type", "application/json");
+		assert!(Quirks::strict().decode::<Directory>(&res).is_ok());
+		let directory = directory.replace("caaIdentities", "caaIdentity");
+		let res = HttpResponse::new(200, directory.as_bytes())
+			.with_header("Content-Type", "application/json");
+		assert!(Quirks::default().decode::<Directory>(&res).is_ok());
+		let err = Quirks::strict().decode::<Directory>(&res).unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("unknown member `meta.caaIdentity`"));
+	}
+
+	#[test]
+	fn decode_other_bodies() {
+		let res = HttpResponse::new(200, BOULDER_ACCOUNT.as_bytes())
+			.with_header("Content-Type", "application/json");
+		assert!(Quirks::boulder().decode::<AccountResource>(&res).is_ok());
+		assert!(Quirks::strict().decode::<AccountResource>(&res).is_err());
+		let chain = "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+		let res = HttpResponse::new(200, chain.as_bytes())
+			.with_header("Content-Type", "application/pem-certificate-chain");
+		let decoded: CertificateChain = Quirks::default().decode(&res).unwrap();
+		assert_eq!(decoded.certificates().len(), 1);
+		let res = HttpResponse::new(200, chain.as_bytes())
+			.with_header("Content-Type", "application/json");
+		assert!(Quirks::default().decode::<CertificateChain>(&res).is_err());
+	}
+}
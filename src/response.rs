@@ -0,0 +1,82 @@
+use crate::errors::DeserializeError;
+use crate::quirks::Quirks;
+use crate::structs::{
+	from_json_slice_checked, AccountResource, Authorization, CertificateChain, Challenge,
+	Directory, Order, OrdersList, RenewalInfo,
+};
+
+pub(crate) const JSON: &str = "application/json";
+pub(crate) const PROBLEM_JSON: &str = "application/problem+json";
+pub(crate) const PEM_CERTIFICATE_CHAIN: &str = "application/pem-certificate-chain";
+
+/// Body of a successful response, see `Quirks::decode`.
+pub trait ResponseBody: Sized {
+	/// Media type defined by the specifications for this body.
+	const CONTENT_TYPE: &'static str;
+
+	fn decode(body: &[u8], quirks: &Quirks) -> Result<Self, DeserializeError>;
+}
+
+macro_rules! json_response_body {
+	($($t: ty: $label: expr),*) => {
+		$(
+			impl ResponseBody for $t {
+				const CONTENT_TYPE: &'static str = JSON;
+
+				fn decode(body: &[u8], quirks: &Quirks) -> Result<Self, DeserializeError> {
+					from_json_slice_checked(body, $label, quirks.extra_fields)
+				}
+			}
+		)*
+	};
+}
+
+json_response_body!(
+	Authorization: "authorization",
+	Challenge: "challenge",
+	Order: "order",
+	OrdersList: "orders list",
+	RenewalInfo: "renewal information"
+);
+
+impl ResponseBody for Directory {
+	const CONTENT_TYPE: &'static str = JSON;
+
+	/// Members not defined by the specifications are kept in `extra` instead
+	/// of being ignored, hence they are checked afterward.
+	fn decode(body: &[u8], quirks: &Quirks) -> Result<Self, DeserializeError> {
+		let directory: Self = from_json_slice_checked(body, "directory", quirks.extra_fields)?;
+		if !quirks.extra_fields {
+			let meta_extra = directory.meta.iter().flat_map(|m| m.extra.keys());
+			let unknown = directory
+				.extra
+				.keys()
+				.cloned()
+				.chain(meta_extra.map(|k| format!("meta.{}", k)))
+				.next();
+			if let Some(path) = unknown {
+				return Err(DeserializeError::new(
+					"directory",
+					format!("unknown member `{}`", path),
+				));
+			}
+		}
+		Ok(directory)
+	}
+}
+
+impl ResponseBody for AccountResource {
+	const CONTENT_TYPE: &'static str = JSON;
+
+	fn decode(body: &[u8], quirks: &Quirks) -> Result<Self, DeserializeError> {
+		quirks.parse_account(body)
+	}
+}
+
+impl ResponseBody for CertificateChain {
+	const CONTENT_TYPE: &'static str = PEM_CERTIFICATE_CHAIN;
+
+	fn decode(body: &[u8], _quirks: &Quirks) -> Result<Self, DeserializeError> {
+		Self::from_slice(body)
+	}
+}
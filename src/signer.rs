@@ -0,0 +1,259 @@
+use crate::errors::KeyError;
+use crate::key::AccountKey;
+use crate::structs::{EcdsaCurve, Jwk, JwsAlgorithm};
+use async_trait::async_trait;
+use sec1::der::asn1::UintRef;
+use sec1::der::{Decode, Reader, SliceReader};
+
+/// Format of the signatures returned by a `JwsSigner`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SignatureFormat {
+	/// Format defined by RFC 7518, where ECDSA signatures are the
+	/// concatenation of R and S. This is what PKCS#11 tokens return.
+	#[default]
+	Raw,
+	/// ECDSA signatures are DER encoded (RFC 3279), as returned by OpenSSL
+	/// and most key management services. Other signatures are left as-is.
+	Der,
+}
+
+/// Signs JWS on behalf of an ACME account.
+///
+/// The private key never has to be known by the crate: the signer receives
+/// the JWS signing input, which is the ASCII representation of the encoded
+/// protected header and payload, and returns the signature. This allows the
+/// account key to be held in a HSM or a key management service.
+#[async_trait]
+pub trait JwsSigner: Send + Sync {
+	/// Public key, without the optional `alg` and `use` members.
+	fn jwk(&self) -> Jwk;
+
+	fn algorithm(&self) -> JwsAlgorithm {
+		self.jwk().algorithm()
+	}
+
+	fn signature_format(&self) -> SignatureFormat {
+		SignatureFormat::Raw
+	}
+
+	async fn sign(&self, signing_input: &[u8]) -> Result<Vec<u8>, KeyError>;
+}
+
+#[async_trait]
+impl JwsSigner for AccountKey {
+	fn jwk(&self) -> Jwk {
+		AccountKey::jwk(self)
+	}
+
+	fn algorithm(&self) -> JwsAlgorithm {
+		AccountKey::algorithm(self)
+	}
+
+	async fn sign(&self, signing_input: &[u8]) -> Result<Vec<u8>, KeyError> {
+		AccountKey::sign(self, signing_input)
+	}
+}
+
+/// Signs the given input and returns the signature in the format defined by
+/// RFC 7518.
+///
+/// Raw ECDSA signatures are checked to have the size required by the curve,
+/// so that a signer returning another format is reported here rather than
+/// by the CA.
+pub(crate) async fn sign_raw<S: JwsSigner + ?Sized>(
+	signer: &S,
+	signing_input: &[u8],
+) -> Result<Vec<u8>, KeyError> {
+	let signature = signer.sign(signing_input).await?;
+	match (signer.signature_format(), signer.jwk()) {
+		(SignatureFormat::Der, Jwk::Ecdsa { crv, .. }) => ecdsa_der_to_raw(&signature, crv),
+		(SignatureFormat::Raw, Jwk::Ecdsa { crv, .. }) => {
+			let expected = 2 * curve_size(crv);
+			if signature.len() != expected {
+				return Err(KeyError::new(format!(
+					"invalid raw ECDSA signature: {} bytes instead of {}",
+					signature.len(),
+					expected
+				)));
+			}
+			Ok(signature)
+		}
+		_ => Ok(signature),
+	}
+}
+
+/// Size of the R and S integers of an ECDSA signature.
+fn curve_size(crv: EcdsaCurve) -> usize {
+	match crv {
+		EcdsaCurve::P256 => 32,
+		EcdsaCurve::P384 => 48,
+		EcdsaCurve::P521 => 66,
+	}
+}
+
+/// Converts a DER encoded ECDSA signature into the concatenation of R and S,
+/// each left-padded to the size of the curve.
+pub(crate) fn ecdsa_der_to_raw(der: &[u8], crv: EcdsaCurve) -> Result<Vec<u8>, KeyError> {
+	let size = curve_size(crv);
+	let invalid = |e: &dyn std::fmt::Display| {
+		KeyError::new(format!("invalid DER encoded ECDSA signature: {}", e))
+	};
+	let mut reader = SliceReader::new(der).map_err(|e| invalid(&e))?;
+	let (r, s) = reader
+		.sequence(|seq| Ok((UintRef::decode(seq)?, UintRef::decode(seq)?)))
+		.map_err(|e| invalid(&e))?;
+	reader.finish(()).map_err(|e| invalid(&e))?;
+	let mut raw = vec![0; 2 * size];
+	for (i, int) in [r, s].iter().enumerate() {
+		let bytes = int.as_bytes();
+		if bytes.len() > size {
+			return Err(invalid(&format!("integer larger than {} bytes", size)));
+		}
+		let end = (i + 1) * size;
+		raw[end - bytes.len()..end].copy_from_slice(bytes);
+	}
+	Ok(raw)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ecdsa_der_to_raw, sign_raw, JwsSigner, SignatureFormat};
+	#[cfg(any(feature = "crypto_openssl", feature = "crypto_rustcrypto"))]
+	use crate::crypto::CryptoBackend;
+	use crate::crypto::DefaultBackend;
+	use crate::errors::KeyError;
+	use crate::key::{test_keys, AccountKey};
+	use crate::structs::{EcdsaCurve, Jwk};
+	use async_trait::async_trait;
+
+	fn der_int(bytes: &[u8]) -> Vec<u8> {
+		let start = bytes
+			.iter()
+			.position(|b| *b != 0)
+			.unwrap_or(bytes.len() - 1);
+		let mut value = bytes[start..].to_vec();
+		if value[0] & 0x80 != 0 {
+			value.insert(0, 0);
+		}
+		let mut int = vec![0x02, value.len() as u8];
+		int.extend(value);
+		int
+	}
+
+	fn to_der(raw: &[u8]) -> Vec<u8> {
+		let (r, s) = raw.split_at(raw.len() / 2);
+		let mut content = der_int(r);
+		content.extend(der_int(s));
+		let mut der = vec![0x30];
+		if content.len() > 0x7f {
+			der.push(0x81);
+		}
+		der.push(content.len() as u8);
+		der.extend(content);
+		der
+	}
+
+	/// Mimics a key management service returning DER encoded signatures.
+	struct DerSigner(AccountKey);
+
+	#[async_trait]
+	impl JwsSigner for DerSigner {
+		fn jwk(&self) -> Jwk {
+			self.0.jwk()
+		}
+
+		fn signature_format(&self) -> SignatureFormat {
+			SignatureFormat::Der
+		}
+
+		async fn sign(&self, signing_input: &[u8]) -> Result<Vec<u8>, KeyError> {
+			Ok(to_der(&self.0.sign(signing_input)?))
+		}
+	}
+
+	/// Signer claiming to return raw signatures, whose output is altered.
+	struct RawSigner(AccountKey, fn(Vec<u8>) -> Vec<u8>);
+
+	#[async_trait]
+	impl JwsSigner for RawSigner {
+		fn jwk(&self) -> Jwk {
+			self.0.jwk()
+		}
+
+		async fn sign(&self, signing_input: &[u8]) -> Result<Vec<u8>, KeyError> {
+			Ok((self.1)(self.0.sign(signing_input)?))
+		}
+	}
+
+	#[test]
+	fn der_to_raw() {
+		let mut raw = vec![0; 64];
+		raw[0] = 0x80;
+		raw[31] = 0x01;
+		raw[63] = 0x02;
+		let der = to_der(&raw);
+		assert_eq!(ecdsa_der_to_raw(&der, EcdsaCurve::P256).unwrap(), raw);
+		assert!(ecdsa_der_to_raw(&der, EcdsaCurve::P384).unwrap()[..16]
+			.iter()
+			.all(|b| *b == 0));
+		let mut raw = vec![0xff; 132];
+		raw[0] = 0x01;
+		raw[66] = 0x00;
+		let der = to_der(&raw);
+		assert_eq!(ecdsa_der_to_raw(&der, EcdsaCurve::P521).unwrap(), raw);
+		assert!(ecdsa_der_to_raw(&der, EcdsaCurve::P256).is_err());
+	}
+
+	#[test]
+	fn der_to_raw_invalid() {
+		let der = to_der(&[0x42; 64]);
+		let mut trailing = der.clone();
+		trailing.push(0);
+		assert!(ecdsa_der_to_raw(&trailing, EcdsaCurve::P256).is_err());
+		assert!(ecdsa_der_to_raw(&der[..der.len() - 1], EcdsaCurve::P256).is_err());
+		assert!(ecdsa_der_to_raw(b"", EcdsaCurve::P256).is_err());
+		assert!(ecdsa_der_to_raw(&[0x42; 64], EcdsaCurve::P256).is_err());
+	}
+
+	#[tokio::test]
+	async fn der_signer() {
+		for pem in [
+			test_keys::P256_PKCS8,
+			test_keys::P384_SEC1,
+			test_keys::P521_PKCS8,
+		] {
+			let signer = DerSigner(AccountKey::from_pem(pem).unwrap());
+			let signature = sign_raw(&signer, b"signing input").await.unwrap();
+			let res = DefaultBackend::verify(&signer.jwk(), b"signing input", &signature);
+			assert!(res.is_ok(), "{}: {:?}", signer.0.key_type(), res);
+		}
+	}
+
+	#[tokio::test]
+	async fn raw_signer() {
+		let key = AccountKey::from_pem(test_keys::P256_PKCS8).unwrap();
+		let signature = sign_raw(&key, b"signing input").await.unwrap();
+		assert_eq!(signature.len(), 64);
+		let res = DefaultBackend::verify(&key.jwk(), b"signing input", &signature);
+		assert!(res.is_ok(), "{:?}", res);
+	}
+
+	#[tokio::test]
+	async fn raw_signer_invalid() {
+		let key = || AccountKey::from_pem(test_keys::P256_PKCS8).unwrap();
+		let truncated = RawSigner(key(), |mut s| {
+			s.pop();
+			s
+		});
+		let res = sign_raw(&truncated, b"signing input").await;
+		assert!(res.is_err());
+		assert!(res
+			.unwrap_err()
+			.to_string()
+			.contains("63 bytes instead of 64"));
+		let der = RawSigner(key(), |s| to_der(&s));
+		assert!(sign_raw(&der, b"signing input").await.is_err());
+		let ed25519 = AccountKey::from_pem(test_keys::ED25519_PKCS8).unwrap();
+		assert!(sign_raw(&ed25519, b"signing input").await.is_ok());
+	}
+}
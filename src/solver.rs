@@ -0,0 +1,366 @@
+use crate::errors::SolverError;
+use crate::structs::{
+	Authorization, Challenge, ChallengeStatus, ChallengeType, Identifier, Jwk, KeyAuthorization,
+};
+use async_trait::async_trait;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[cfg(feature = "http01_responder")]
+mod http01;
+#[cfg(feature = "dns01_rfc2136")]
+mod rfc2136;
+#[cfg(feature = "tls_alpn01_certificate")]
+mod tls_alpn01;
+
+#[cfg(feature = "http01_responder")]
+pub use http01::Http01Responder;
+#[cfg(feature = "dns01_rfc2136")]
+pub use rfc2136::{Rfc2136Solver, TsigAlgorithm, TsigKey};
+#[cfg(feature = "tls_alpn01_certificate")]
+pub use tls_alpn01::{TlsAlpn01Certificate, ACME_TLS_ALPN_PROTOCOL};
+
+/// Provisions and removes the response to a challenge.
+///
+/// A solver handles a single challenge type. For http-01, the key
+/// authorization has to be served under the challenge token, for dns-01 the
+/// TXT record value is given by `KeyAuthorization::dns_txt_value` and for
+/// tls-alpn-01 the certificate extension contains `KeyAuthorization::digest`.
+#[async_trait]
+pub trait ChallengeSolver: Send + Sync {
+	fn challenge_type(&self) -> ChallengeType;
+
+	async fn present(
+		&self,
+		identifier: &Identifier,
+		key_auth: &KeyAuthorization,
+	) -> Result<(), SolverError>;
+
+	async fn cleanup(
+		&self,
+		identifier: &Identifier,
+		key_auth: &KeyAuthorization,
+	) -> Result<(), SolverError>;
+}
+
+/// Set of challenge solvers, at most one per challenge type.
+#[derive(Default)]
+pub struct ChallengeSolvers {
+	solvers: Vec<Box<dyn ChallengeSolver>>,
+}
+
+impl ChallengeSolvers {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Registers a solver, replacing any solver previously registered for
+	/// the same challenge type.
+	pub fn register<S: ChallengeSolver + 'static>(&mut self, solver: S) -> &mut Self {
+		let challenge_type = solver.challenge_type();
+		self.solvers
+			.retain(|s| s.challenge_type() != challenge_type);
+		self.solvers.push(Box::new(solver));
+		self
+	}
+
+	pub fn get(&self, challenge_type: ChallengeType) -> Option<&dyn ChallengeSolver> {
+		self.solvers
+			.iter()
+			.find(|s| s.challenge_type() == challenge_type)
+			.map(|s| s.as_ref())
+	}
+
+	/// Selects the first challenge of the authorization, in the order given by
+	/// the server, that has a registered solver.
+	pub fn select<'a>(
+		&'a self,
+		authz: &'a Authorization,
+	) -> Option<(&'a Challenge, &'a dyn ChallengeSolver)> {
+		authz
+			.challenges
+			.iter()
+			.filter(|c| c.status != ChallengeStatus::Invalid && c.token.is_some())
+			.find_map(|c| self.get(c.challenge_type).map(|s| (c, s)))
+	}
+
+	/// Solves an authorization.
+	///
+	/// The selected challenge's response is presented, then `validate` is
+	/// called. It is expected to tell the server the challenge is ready and
+	/// wait for its validation. Whatever the outcome, the response is cleaned
+	/// up afterward. If `validate` fails, its error is returned, otherwise a
+	/// cleanup failure is reported. If `validate` panics, the response is
+	/// cleaned up before the panic is resumed.
+	///
+	/// The cleanup cannot run if the returned future is dropped before it
+	/// completes, for instance by a timeout or a `select!` around `solve`, in
+	/// which case the response stays published. Time limits should therefore
+	/// be enforced within `validate`, which returns an error once they are
+	/// exceeded.
+	pub async fn solve<F, Fut, T, E>(
+		&self,
+		authz: &Authorization,
+		account_key: &Jwk,
+		validate: F,
+	) -> Result<T, E>
+	where
+		F: FnOnce(&Challenge) -> Fut,
+		Fut: Future<Output = Result<T, E>>,
+		E: From<SolverError>,
+	{
+		let (challenge, solver) = self.select(authz).ok_or_else(|| {
+			SolverError::new(format!(
+				"{}: no solver registered for the proposed challenges",
+				authz.identifier
+			))
+		})?;
+		let key_auth = challenge.key_authorization(account_key).ok_or_else(|| {
+			SolverError::new(format!(
+				"{}: {} challenge has no token",
+				authz.identifier, challenge.challenge_type
+			))
+		})?;
+		let res = CatchUnwind(Box::pin(async {
+			solver.present(&authz.identifier, &key_auth).await?;
+			validate(challenge).await
+		}))
+		.await;
+		let cleanup_res = solver.cleanup(&authz.identifier, &key_auth).await;
+		let res = res.unwrap_or_else(|payload| panic::resume_unwind(payload));
+		match (res, cleanup_res) {
+			(Ok(v), Ok(_)) => Ok(v),
+			(Ok(_), Err(e)) => Err(e.into()),
+			(Err(e), _) => Err(e),
+		}
+	}
+}
+
+/// Future catching the panics raised while polling the inner one.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+	type Output = std::thread::Result<F::Output>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let inner = self.0.as_mut();
+		match panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+			Ok(Poll::Ready(v)) => Poll::Ready(Ok(v)),
+			Ok(Poll::Pending) => Poll::Pending,
+			Err(payload) => Poll::Ready(Err(payload)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{CatchUnwind, ChallengeSolver, ChallengeSolvers};
+	use crate::errors::SolverError;
+	use crate::key::{test_keys, AccountKey};
+	use crate::structs::{Authorization, ChallengeType, Identifier, Jwk, KeyAuthorization};
+	use async_trait::async_trait;
+	use std::str::FromStr;
+	use std::sync::{Arc, Mutex};
+
+	const AUTHZ: &str = r#"{
+		"status": "pending",
+		"identifier": {"type": "dns", "value": "www.example.org"},
+		"challenges": [
+			{
+				"url": "https://example.com/acme/chall/1",
+				"type": "tls-alpn-01",
+				"status": "invalid",
+				"token": "DGyRejmCefe7v4NfDGDKfA"
+			},
+			{
+				"url": "https://example.com/acme/chall/2",
+				"type": "http-01",
+				"status": "pending",
+				"token": "DGyRejmCefe7v4NfDGDKfA"
+			},
+			{
+				"url": "https://example.com/acme/chall/3",
+				"type": "dns-01",
+				"status": "pending",
+				"token": "DGyRejmCefe7v4NfDGDKfA"
+			}
+		]
+	}"#;
+
+	struct TestSolver {
+		challenge_type: ChallengeType,
+		fail_present: bool,
+		events: Arc<Mutex<Vec<String>>>,
+	}
+
+	impl TestSolver {
+		fn new(challenge_type: ChallengeType, events: &Arc<Mutex<Vec<String>>>) -> Self {
+			Self {
+				challenge_type,
+				fail_present: false,
+				events: events.clone(),
+			}
+		}
+	}
+
+	#[async_trait]
+	impl ChallengeSolver for TestSolver {
+		fn challenge_type(&self) -> ChallengeType {
+			self.challenge_type
+		}
+
+		async fn present(
+			&self,
+			identifier: &Identifier,
+			key_auth: &KeyAuthorization,
+		) -> Result<(), SolverError> {
+			self.events.lock().unwrap().push(format!(
+				"present {} {} {}",
+				self.challenge_type,
+				identifier.value,
+				key_auth.token()
+			));
+			if self.fail_present {
+				return Err(SolverError::new("present failed"));
+			}
+			Ok(())
+		}
+
+		async fn cleanup(
+			&self,
+			identifier: &Identifier,
+			_key_auth: &KeyAuthorization,
+		) -> Result<(), SolverError> {
+			self.events.lock().unwrap().push(format!(
+				"cleanup {} {}",
+				self.challenge_type, identifier.value
+			));
+			Ok(())
+		}
+	}
+
+	fn account_key() -> Jwk {
+		AccountKey::from_pem(test_keys::ED25519_PKCS8)
+			.unwrap()
+			.jwk()
+	}
+
+	#[test]
+	fn select_first_registered() {
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let authz = Authorization::from_str(AUTHZ).unwrap();
+		let mut solvers = ChallengeSolvers::new();
+		assert!(solvers.select(&authz).is_none());
+		solvers.register(TestSolver::new(ChallengeType::Dns01, &events));
+		let (c, s) = solvers.select(&authz).unwrap();
+		assert_eq!(c.url, "https://example.com/acme/chall/3");
+		assert_eq!(s.challenge_type(), ChallengeType::Dns01);
+		solvers.register(TestSolver::new(ChallengeType::Http01, &events));
+		let (c, _) = solvers.select(&authz).unwrap();
+		assert_eq!(c.url, "https://example.com/acme/chall/2");
+	}
+
+	#[test]
+	fn select_skips_invalid() {
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let authz = Authorization::from_str(AUTHZ).unwrap();
+		let mut solvers = ChallengeSolvers::new();
+		solvers.register(TestSolver::new(ChallengeType::TlsAlpn01, &events));
+		assert!(solvers.select(&authz).is_none());
+	}
+
+	#[tokio::test]
+	async fn solve_success() {
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let authz = Authorization::from_str(AUTHZ).unwrap();
+		let mut solvers = ChallengeSolvers::new();
+		solvers.register(TestSolver::new(ChallengeType::Http01, &events));
+		let res: Result<String, SolverError> = solvers
+			.solve(&authz, &account_key(), |c| {
+				let url = c.url.to_string();
+				async move { Ok(url) }
+			})
+			.await;
+		assert_eq!(res.unwrap(), "https://example.com/acme/chall/2");
+		assert_eq!(
+			*events.lock().unwrap(),
+			vec![
+				"present http-01 www.example.org DGyRejmCefe7v4NfDGDKfA",
+				"cleanup http-01 www.example.org",
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn solve_cleanup_on_validation_failure() {
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let authz = Authorization::from_str(AUTHZ).unwrap();
+		let mut solvers = ChallengeSolvers::new();
+		solvers.register(TestSolver::new(ChallengeType::Dns01, &events));
+		let res: Result<(), SolverError> = solvers
+			.solve(&authz, &account_key(), |_| async {
+				Err(SolverError::new("validation failed"))
+			})
+			.await;
+		assert!(res.is_err());
+		assert_eq!(events.lock().unwrap().len(), 2);
+		assert_eq!(
+			events.lock().unwrap().last().unwrap(),
+			"cleanup dns-01 www.example.org"
+		);
+	}
+
+	#[tokio::test]
+	async fn solve_cleanup_on_present_failure() {
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let authz = Authorization::from_str(AUTHZ).unwrap();
+		let mut solvers = ChallengeSolvers::new();
+		let mut solver = TestSolver::new(ChallengeType::Dns01, &events);
+		solver.fail_present = true;
+		solvers.register(solver);
+		let validated = Arc::new(Mutex::new(false));
+		let res: Result<(), SolverError> = solvers
+			.solve(&authz, &account_key(), |_| {
+				*validated.lock().unwrap() = true;
+				async { Ok(()) }
+			})
+			.await;
+		assert!(res.is_err());
+		assert!(!*validated.lock().unwrap());
+		assert_eq!(
+			events.lock().unwrap().last().unwrap(),
+			"cleanup dns-01 www.example.org"
+		);
+	}
+
+	#[tokio::test]
+	async fn solve_cleanup_on_validation_panic() {
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let authz = Authorization::from_str(AUTHZ).unwrap();
+		let mut solvers = ChallengeSolvers::new();
+		solvers.register(TestSolver::new(ChallengeType::Http01, &events));
+		let res = CatchUnwind(Box::pin(solvers.solve(&authz, &account_key(), |_| async {
+			panic!("validation panicked");
+			#[allow(unreachable_code)]
+			Ok::<(), SolverError>(())
+		})))
+		.await;
+		assert!(res.is_err());
+		assert_eq!(
+			events.lock().unwrap().last().unwrap(),
+			"cleanup http-01 www.example.org"
+		);
+	}
+
+	#[tokio::test]
+	async fn solve_no_solver() {
+		let authz = Authorization::from_str(AUTHZ).unwrap();
+		let solvers = ChallengeSolvers::new();
+		let res: Result<(), SolverError> = solvers
+			.solve(&authz, &account_key(), |_| async { Ok(()) })
+			.await;
+		assert!(res.is_err());
+	}
+}
@@ -0,0 +1,204 @@
+use crate::errors::SolverError;
+use crate::solver::ChallengeSolver;
+use crate::structs::{ChallengeType, Identifier, KeyAuthorization};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+const CHALLENGE_PATH_PREFIX: &str = "/.well-known/acme-challenge/";
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+type KeyAuthorizations = Arc<Mutex<HashMap<String, String>>>;
+
+/// Standalone HTTP server answering http-01 challenges.
+///
+/// The server runs on the current Tokio runtime until the responder is
+/// dropped. Key authorizations are served from the moment they are presented
+/// until they are cleaned up.
+pub struct Http01Responder {
+	local_addr: SocketAddr,
+	key_auths: KeyAuthorizations,
+	task: JoinHandle<()>,
+}
+
+impl Http01Responder {
+	pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
+		let listener = TcpListener::bind(addr).await?;
+		let local_addr = listener.local_addr()?;
+		let key_auths = KeyAuthorizations::default();
+		let task = tokio::spawn(serve(listener, key_auths.clone()));
+		Ok(Self {
+			local_addr,
+			key_auths,
+			task,
+		})
+	}
+
+	/// Address the server is listening on, which is useful when binding to
+	/// port 0.
+	pub fn local_addr(&self) -> SocketAddr {
+		self.local_addr
+	}
+}
+
+impl Drop for Http01Responder {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+#[async_trait]
+impl ChallengeSolver for Http01Responder {
+	fn challenge_type(&self) -> ChallengeType {
+		ChallengeType::Http01
+	}
+
+	async fn present(
+		&self,
+		_identifier: &Identifier,
+		key_auth: &KeyAuthorization,
+	) -> Result<(), SolverError> {
+		self.key_auths
+			.lock()
+			.map_err(|e| SolverError::new(e.to_string()))?
+			.insert(key_auth.token().to_string(), key_auth.to_string());
+		Ok(())
+	}
+
+	async fn cleanup(
+		&self,
+		_identifier: &Identifier,
+		key_auth: &KeyAuthorization,
+	) -> Result<(), SolverError> {
+		self.key_auths
+			.lock()
+			.map_err(|e| SolverError::new(e.to_string()))?
+			.remove(key_auth.token());
+		Ok(())
+	}
+}
+
+async fn serve(listener: TcpListener, key_auths: KeyAuthorizations) {
+	loop {
+		let stream = match listener.accept().await {
+			Ok((stream, _)) => stream,
+			Err(_) => {
+				// Errors such as EMFILE persist for a while, do not spin on them.
+				tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+				continue;
+			}
+		};
+		let key_auths = key_auths.clone();
+		tokio::spawn(async move {
+			let service = service_fn(move |req| handle_request(req, key_auths.clone()));
+			let _ = http1::Builder::new()
+				.serve_connection(TokioIo::new(stream), service)
+				.await;
+		});
+	}
+}
+
+async fn handle_request(
+	req: Request<Incoming>,
+	key_auths: KeyAuthorizations,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+	if req.method() != Method::GET && req.method() != Method::HEAD {
+		return Ok(empty_response(StatusCode::METHOD_NOT_ALLOWED));
+	}
+	let key_auth = req
+		.uri()
+		.path()
+		.strip_prefix(CHALLENGE_PATH_PREFIX)
+		.and_then(|token| key_auths.lock().ok()?.get(token).cloned());
+	let resp = match key_auth {
+		Some(key_auth) => {
+			let mut resp = Response::new(Full::new(Bytes::from(key_auth)));
+			resp.headers_mut().insert(
+				CONTENT_TYPE,
+				HeaderValue::from_static("application/octet-stream"),
+			);
+			resp
+		}
+		None => empty_response(StatusCode::NOT_FOUND),
+	};
+	Ok(resp)
+}
+
+fn empty_response(status: StatusCode) -> Response<Full<Bytes>> {
+	let mut resp = Response::new(Full::new(Bytes::new()));
+	*resp.status_mut() = status;
+	resp
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Http01Responder;
+	use crate::key::{test_keys, AccountKey};
+	use crate::solver::ChallengeSolver;
+	use crate::structs::{Identifier, KeyAuthorization};
+	use std::net::SocketAddr;
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::TcpStream;
+
+	const TOKEN: &str = "LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0";
+
+	fn key_auth() -> KeyAuthorization {
+		let jwk = AccountKey::from_pem(test_keys::ED25519_PKCS8)
+			.unwrap()
+			.jwk();
+		KeyAuthorization::new(TOKEN, &jwk)
+	}
+
+	async fn get(addr: SocketAddr, method: &str, path: &str) -> String {
+		let mut stream = TcpStream::connect(addr).await.unwrap();
+		let req = format!(
+			"{} {} HTTP/1.1\r\nHost: example.org\r\nConnection: close\r\n\r\n",
+			method, path
+		);
+		stream.write_all(req.as_bytes()).await.unwrap();
+		let mut resp = String::new();
+		stream.read_to_string(&mut resp).await.unwrap();
+		resp
+	}
+
+	#[tokio::test]
+	async fn serve_key_authorization() {
+		let responder = Http01Responder::bind("127.0.0.1:0".parse().unwrap())
+			.await
+			.unwrap();
+		let addr = responder.local_addr();
+		let path = format!("/.well-known/acme-challenge/{}", TOKEN);
+		let id = Identifier::dns("example.org");
+		let ka = key_auth();
+
+		let resp = get(addr, "GET", &path).await;
+		assert!(resp.starts_with("HTTP/1.1 404"), "{}", resp);
+
+		responder.present(&id, &ka).await.unwrap();
+		let resp = get(addr, "GET", &path).await;
+		assert!(resp.starts_with("HTTP/1.1 200"), "{}", resp);
+		assert!(resp.ends_with(&format!("\r\n\r\n{}", ka)), "{}", resp);
+		let resp = get(addr, "POST", &path).await;
+		assert!(resp.starts_with("HTTP/1.1 405"), "{}", resp);
+		let resp = get(addr, "GET", "/.well-known/acme-challenge/derp").await;
+		assert!(resp.starts_with("HTTP/1.1 404"), "{}", resp);
+
+		responder.cleanup(&id, &ka).await.unwrap();
+		let resp = get(addr, "GET", &path).await;
+		assert!(resp.starts_with("HTTP/1.1 404"), "{}", resp);
+	}
+}
@@ -0,0 +1,979 @@
+use crate::crypto::{CryptoBackend, DefaultBackend};
+use crate::errors::SolverError;
+use crate::solver::ChallengeSolver;
+use crate::structs::{ChallengeType, Identifier, IdentifierType, KeyAuthorization};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt;
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use zeroize::Zeroizing;
+
+const TYPE_SOA: u16 = 6;
+const TYPE_TXT: u16 = 16;
+const TYPE_TSIG: u16 = 250;
+const CLASS_IN: u16 = 1;
+const CLASS_NONE: u16 = 254;
+const CLASS_ANY: u16 = 255;
+const OPCODE_UPDATE: u16 = 5 << 11;
+const FLAG_QR: u16 = 1 << 15;
+const RCODE_MASK: u16 = 0x000f;
+const TSIG_FUDGE: u16 = 300;
+const HEADER_LEN: usize = 12;
+const MAX_MSG_LEN: usize = 4096;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TsigAlgorithm {
+	HmacSha256,
+	HmacSha512,
+}
+
+impl TsigAlgorithm {
+	fn name(&self) -> &'static str {
+		match self {
+			TsigAlgorithm::HmacSha256 => "hmac-sha256.",
+			TsigAlgorithm::HmacSha512 => "hmac-sha512.",
+		}
+	}
+}
+
+/// Shared secret used to sign the updates (RFC 8945).
+#[derive(Clone)]
+pub struct TsigKey {
+	name: String,
+	algorithm: TsigAlgorithm,
+	secret: Zeroizing<Vec<u8>>,
+}
+
+impl TsigKey {
+	pub fn new(name: &str, algorithm: TsigAlgorithm, secret: &[u8]) -> Self {
+		Self {
+			name: name.to_string(),
+			algorithm,
+			secret: Zeroizing::new(secret.to_vec()),
+		}
+	}
+
+	/// Creates a key from a base64 encoded secret, as found in BIND and Knot
+	/// configuration files.
+	pub fn from_base64(
+		name: &str,
+		algorithm: TsigAlgorithm,
+		secret: &str,
+	) -> Result<Self, SolverError> {
+		let secret = STANDARD
+			.decode(secret.trim())
+			.map(Zeroizing::new)
+			.map_err(|e| SolverError::new(format!("invalid TSIG secret: {}", e)))?;
+		Ok(Self::new(name, algorithm, &secret))
+	}
+
+	fn mac(&self, data: &[u8]) -> Vec<u8> {
+		match self.algorithm {
+			TsigAlgorithm::HmacSha256 => DefaultBackend::hmac_sha256(&self.secret, data),
+			TsigAlgorithm::HmacSha512 => DefaultBackend::hmac_sha512(&self.secret, data),
+		}
+	}
+}
+
+impl fmt::Debug for TsigKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("TsigKey")
+			.field("name", &self.name)
+			.field("algorithm", &self.algorithm)
+			.finish_non_exhaustive()
+	}
+}
+
+/// dns-01 solver adding and removing the `_acme-challenge` TXT records using
+/// TSIG-signed dynamic updates (RFC 2136).
+///
+/// Once the record has been added, `present` waits until every authoritative
+/// server returns it, so the challenge can be responded to right away.
+#[derive(Clone, Debug)]
+pub struct Rfc2136Solver {
+	server: SocketAddr,
+	zone: String,
+	key: TsigKey,
+	ttl: u32,
+	authoritative_servers: Vec<SocketAddr>,
+	timeout: Duration,
+	propagation_timeout: Duration,
+	propagation_interval: Duration,
+}
+
+impl Rfc2136Solver {
+	/// Creates a solver sending updates for `zone` to the primary `server`.
+	pub fn new(server: SocketAddr, zone: &str, key: TsigKey) -> Self {
+		Self {
+			server,
+			zone: zone.trim_end_matches('.').to_lowercase(),
+			key,
+			ttl: 60,
+			authoritative_servers: Vec::new(),
+			timeout: Duration::from_secs(5),
+			propagation_timeout: Duration::from_secs(120),
+			propagation_interval: Duration::from_secs(2),
+		}
+	}
+
+	pub fn ttl(mut self, ttl: u32) -> Self {
+		self.ttl = ttl;
+		self
+	}
+
+	/// Servers checked for propagation. Defaults to the primary server.
+	pub fn authoritative_servers(mut self, servers: &[SocketAddr]) -> Self {
+		self.authoritative_servers = servers.to_vec();
+		self
+	}
+
+	/// Maximum time to wait for an answer to a single DNS message.
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	pub fn propagation_timeout(mut self, timeout: Duration) -> Self {
+		self.propagation_timeout = timeout;
+		self
+	}
+
+	pub fn propagation_interval(mut self, interval: Duration) -> Self {
+		self.propagation_interval = interval;
+		self
+	}
+
+	fn record_name(&self, identifier: &Identifier) -> Result<String, SolverError> {
+		if identifier.id_type != IdentifierType::Dns {
+			return Err(SolverError::new(format!(
+				"{}: dns-01 only applies to DNS identifiers",
+				identifier
+			)));
+		}
+		let domain = identifier.value.trim_start_matches("*.");
+		let domain = domain.trim_end_matches('.').to_lowercase();
+		if domain != self.zone && !domain.ends_with(&format!(".{}", self.zone)) {
+			return Err(SolverError::new(format!(
+				"{}: not in zone {}",
+				identifier, self.zone
+			)));
+		}
+		Ok(format!("_acme-challenge.{}", domain))
+	}
+
+	async fn update(
+		&self,
+		name: &str,
+		class: u16,
+		ttl: u32,
+		value: &str,
+	) -> Result<(), SolverError> {
+		let mut msg = Message::new(message_id()?, OPCODE_UPDATE);
+		msg.add_record(&self.zone, TYPE_SOA, CLASS_IN, None)?;
+		msg.qdcount = 1;
+		msg.add_record(name, TYPE_TXT, class, Some((ttl, &txt_rdata(value)?)))?;
+		msg.nscount = 1;
+		let (msg, request_mac) = msg.sign(&self.key, unix_time()?, None)?;
+		let resp = exchange(self.server, &msg, self.timeout).await?;
+		let invalid = |e: String| {
+			SolverError::new(format!(
+				"{}: invalid answer from {}: {}",
+				name, self.server, e
+			))
+		};
+		let parsed = Response::parse(&resp)?;
+		if parsed.flags & FLAG_QR == 0 {
+			return Err(invalid("not a response".to_string()));
+		}
+		if parsed.rcode != 0 {
+			return Err(SolverError::new(format!(
+				"{}: dynamic update refused by {}: {}",
+				name,
+				self.server,
+				rcode_name(parsed.rcode)
+			)));
+		}
+		verify_response(&resp, &self.key, &request_mac, unix_time()?).map_err(invalid)
+	}
+
+	async fn wait_propagation(&self, name: &str, value: &str) -> Result<(), SolverError> {
+		let servers = if self.authoritative_servers.is_empty() {
+			vec![self.server]
+		} else {
+			self.authoritative_servers.clone()
+		};
+		let deadline = Instant::now() + self.propagation_timeout;
+		let mut pending = servers;
+		loop {
+			let mut still_pending = Vec::new();
+			for server in pending {
+				match query_txt(server, name, self.timeout).await {
+					Ok(values) if values.iter().any(|v| v == value) => {}
+					_ => still_pending.push(server),
+				}
+			}
+			if still_pending.is_empty() {
+				return Ok(());
+			}
+			if Instant::now() + self.propagation_interval > deadline {
+				let servers: Vec<String> = still_pending.iter().map(|s| s.to_string()).collect();
+				return Err(SolverError::new(format!(
+					"{}: record not propagated to {}",
+					name,
+					servers.join(", ")
+				)));
+			}
+			pending = still_pending;
+			tokio::time::sleep(self.propagation_interval).await;
+		}
+	}
+}
+
+#[async_trait]
+impl ChallengeSolver for Rfc2136Solver {
+	fn challenge_type(&self) -> ChallengeType {
+		ChallengeType::Dns01
+	}
+
+	async fn present(
+		&self,
+		identifier: &Identifier,
+		key_auth: &KeyAuthorization,
+	) -> Result<(), SolverError> {
+		let name = self.record_name(identifier)?;
+		let value = key_auth.dns_txt_value();
+		self.update(&name, CLASS_IN, self.ttl, &value).await?;
+		self.wait_propagation(&name, &value).await
+	}
+
+	async fn cleanup(
+		&self,
+		identifier: &Identifier,
+		key_auth: &KeyAuthorization,
+	) -> Result<(), SolverError> {
+		let name = self.record_name(identifier)?;
+		let value = key_auth.dns_txt_value();
+		self.update(&name, CLASS_NONE, 0, &value).await
+	}
+}
+
+struct Message {
+	id: u16,
+	flags: u16,
+	qdcount: u16,
+	ancount: u16,
+	nscount: u16,
+	arcount: u16,
+	body: Vec<u8>,
+}
+
+impl Message {
+	fn new(id: u16, flags: u16) -> Self {
+		Self {
+			id,
+			flags,
+			qdcount: 0,
+			ancount: 0,
+			nscount: 0,
+			arcount: 0,
+			body: Vec::new(),
+		}
+	}
+
+	/// Appends a record, or a question if `data` is `None`.
+	fn add_record(
+		&mut self,
+		name: &str,
+		rr_type: u16,
+		class: u16,
+		data: Option<(u32, &[u8])>,
+	) -> Result<(), SolverError> {
+		self.body.extend_from_slice(&encode_name(name)?);
+		self.body.extend_from_slice(&rr_type.to_be_bytes());
+		self.body.extend_from_slice(&class.to_be_bytes());
+		if let Some((ttl, rdata)) = data {
+			self.body.extend_from_slice(&ttl.to_be_bytes());
+			self.body
+				.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+			self.body.extend_from_slice(rdata);
+		}
+		Ok(())
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut ret = Vec::with_capacity(HEADER_LEN + self.body.len());
+		for field in [
+			self.id,
+			self.flags,
+			self.qdcount,
+			self.ancount,
+			self.nscount,
+			self.arcount,
+		] {
+			ret.extend_from_slice(&field.to_be_bytes());
+		}
+		ret.extend_from_slice(&self.body);
+		ret
+	}
+
+	/// Appends a TSIG record (RFC 8945, section 4.3), returning the message
+	/// and its MAC. Responses are signed using the MAC of the request as prior
+	/// digest.
+	fn sign(
+		mut self,
+		key: &TsigKey,
+		time_signed: u64,
+		request_mac: Option<&[u8]>,
+	) -> Result<(Vec<u8>, Vec<u8>), SolverError> {
+		let mut signed_data = Vec::new();
+		if let Some(request_mac) = request_mac {
+			signed_data.extend_from_slice(&(request_mac.len() as u16).to_be_bytes());
+			signed_data.extend_from_slice(request_mac);
+		}
+		signed_data.extend_from_slice(&self.to_bytes());
+		signed_data.extend_from_slice(&tsig_variables(key, time_signed, 0, &[])?);
+		let mac = key.mac(&signed_data);
+		let mut rdata = encode_name(key.algorithm.name())?;
+		rdata.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+		rdata.extend_from_slice(&TSIG_FUDGE.to_be_bytes());
+		rdata.extend_from_slice(&(mac.len() as u16).to_be_bytes());
+		rdata.extend_from_slice(&mac);
+		rdata.extend_from_slice(&self.id.to_be_bytes());
+		rdata.extend_from_slice(&0u16.to_be_bytes());
+		rdata.extend_from_slice(&0u16.to_be_bytes());
+		self.add_record(&key.name, TYPE_TSIG, CLASS_ANY, Some((0, &rdata)))?;
+		self.arcount += 1;
+		Ok((self.to_bytes(), mac))
+	}
+}
+
+/// Random message ID, making spoofed answers harder to get accepted.
+fn message_id() -> Result<u16, SolverError> {
+	let mut buf = [0; 2];
+	DefaultBackend::fill_random(&mut buf).map_err(|e| SolverError::new(e.to_string()))?;
+	Ok(u16::from_be_bytes(buf))
+}
+
+fn tsig_variables(
+	key: &TsigKey,
+	time_signed: u64,
+	error: u16,
+	other: &[u8],
+) -> Result<Vec<u8>, SolverError> {
+	let mut ret = encode_name(&key.name)?;
+	ret.extend_from_slice(&CLASS_ANY.to_be_bytes());
+	ret.extend_from_slice(&0u32.to_be_bytes());
+	ret.extend_from_slice(&encode_name(key.algorithm.name())?);
+	ret.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+	ret.extend_from_slice(&TSIG_FUDGE.to_be_bytes());
+	ret.extend_from_slice(&error.to_be_bytes());
+	ret.extend_from_slice(&(other.len() as u16).to_be_bytes());
+	ret.extend_from_slice(other);
+	Ok(ret)
+}
+
+fn unix_time() -> Result<u64, SolverError> {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.map_err(|e| SolverError::new(e.to_string()))
+}
+
+/// Checks that a response is signed with the key, using the MAC of the
+/// request as prior digest (RFC 8945, section 5.3).
+fn verify_response(data: &[u8], key: &TsigKey, request_mac: &[u8], now: u64) -> Result<(), String> {
+	let err = |e: SolverError| e.to_string();
+	let arcount = read_u16(data, 10).map_err(err)?;
+	if arcount == 0 {
+		return Err("unsigned response".to_string());
+	}
+	let mut pos = HEADER_LEN;
+	for _ in 0..read_u16(data, 4).map_err(err)? {
+		pos = skip_name(data, pos).map_err(err)? + 4;
+	}
+	let before_tsig = read_u16(data, 6).map_err(err)? as usize
+		+ read_u16(data, 8).map_err(err)? as usize
+		+ arcount as usize
+		- 1;
+	for _ in 0..before_tsig {
+		pos = read_record(data, pos).map_err(err)?.1;
+	}
+	let tsig_start = pos;
+	let (record, end) = read_record(data, pos).map_err(err)?;
+	if record.rr_type != TYPE_TSIG || end != data.len() {
+		return Err("unsigned response".to_string());
+	}
+	if !decode_name(data, tsig_start)
+		.map_err(err)?
+		.eq_ignore_ascii_case(key.name.trim_end_matches('.'))
+	{
+		return Err("response signed with another key".to_string());
+	}
+	let tsig = Tsig::parse(&data[record.rdata]).map_err(err)?;
+	if !tsig
+		.algorithm
+		.eq_ignore_ascii_case(key.algorithm.name().trim_end_matches('.'))
+	{
+		return Err("response signed with another algorithm".to_string());
+	}
+	if tsig.error != 0 {
+		return Err(format!("TSIG error: {}", rcode_name(tsig.error)));
+	}
+	let mut signed_data = (request_mac.len() as u16).to_be_bytes().to_vec();
+	signed_data.extend_from_slice(request_mac);
+	let mut message = data[..tsig_start].to_vec();
+	message[..2].copy_from_slice(&tsig.original_id.to_be_bytes());
+	message[10..12].copy_from_slice(&(arcount - 1).to_be_bytes());
+	signed_data.extend_from_slice(&message);
+	let variables = tsig_variables(key, tsig.time_signed, tsig.error, tsig.other);
+	signed_data.extend_from_slice(&variables.map_err(err)?);
+	if key.mac(&signed_data) != tsig.mac {
+		return Err("invalid TSIG signature".to_string());
+	}
+	if now.abs_diff(tsig.time_signed) > u64::from(tsig.fudge) {
+		return Err("TSIG signature outside of the allowed time window".to_string());
+	}
+	Ok(())
+}
+
+/// Data of a TSIG record (RFC 8945, section 4.2).
+struct Tsig<'a> {
+	algorithm: String,
+	time_signed: u64,
+	fudge: u16,
+	mac: &'a [u8],
+	original_id: u16,
+	error: u16,
+	other: &'a [u8],
+}
+
+impl<'a> Tsig<'a> {
+	fn parse(rdata: &'a [u8]) -> Result<Self, SolverError> {
+		let truncated = || SolverError::new("truncated TSIG record");
+		let algorithm = decode_name(rdata, 0)?;
+		let pos = skip_name(rdata, 0)?;
+		let time = rdata.get(pos..pos + 6).ok_or_else(truncated)?;
+		let mut time_signed = [0; 8];
+		time_signed[2..].copy_from_slice(time);
+		let fudge = read_u16(rdata, pos + 6)?;
+		let mac_len = read_u16(rdata, pos + 8)? as usize;
+		let pos = pos + 10;
+		let mac = rdata.get(pos..pos + mac_len).ok_or_else(truncated)?;
+		let pos = pos + mac_len;
+		let original_id = read_u16(rdata, pos)?;
+		let error = read_u16(rdata, pos + 2)?;
+		let other_len = read_u16(rdata, pos + 4)? as usize;
+		let other = rdata
+			.get(pos + 6..pos + 6 + other_len)
+			.ok_or_else(truncated)?;
+		Ok(Self {
+			algorithm,
+			time_signed: u64::from_be_bytes(time_signed),
+			fudge,
+			mac,
+			original_id,
+			error,
+			other,
+		})
+	}
+}
+
+struct Record {
+	rr_type: u16,
+	class: u16,
+	rdata: Range<usize>,
+}
+
+struct Response {
+	flags: u16,
+	rcode: u16,
+	answers: Vec<Record>,
+}
+
+impl Response {
+	fn parse(data: &[u8]) -> Result<Self, SolverError> {
+		let flags = read_u16(data, 2)?;
+		let qdcount = read_u16(data, 4)?;
+		let ancount = read_u16(data, 6)?;
+		let mut pos = HEADER_LEN;
+		for _ in 0..qdcount {
+			pos = skip_name(data, pos)? + 4;
+		}
+		let mut answers = Vec::with_capacity(ancount as usize);
+		for _ in 0..ancount {
+			let (record, next) = read_record(data, pos)?;
+			answers.push(record);
+			pos = next;
+		}
+		Ok(Self {
+			flags,
+			rcode: flags & RCODE_MASK,
+			answers,
+		})
+	}
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, SolverError> {
+	data.get(pos..pos + 2)
+		.map(|b| u16::from_be_bytes([b[0], b[1]]))
+		.ok_or_else(|| SolverError::new("truncated DNS message"))
+}
+
+fn skip_name(data: &[u8], mut pos: usize) -> Result<usize, SolverError> {
+	loop {
+		let len = *data
+			.get(pos)
+			.ok_or_else(|| SolverError::new("truncated DNS message"))?;
+		match len {
+			0 => return Ok(pos + 1),
+			l if l & 0xc0 == 0xc0 => return Ok(pos + 2),
+			l => pos += l as usize + 1,
+		}
+	}
+}
+
+/// Decodes an uncompressed name, without the trailing dot.
+fn decode_name(data: &[u8], mut pos: usize) -> Result<String, SolverError> {
+	let mut labels = Vec::new();
+	loop {
+		let len = *data
+			.get(pos)
+			.ok_or_else(|| SolverError::new("truncated DNS message"))? as usize;
+		if len == 0 {
+			return Ok(labels.join("."));
+		}
+		if len & 0xc0 != 0 {
+			return Err(SolverError::new("unexpected compressed name"));
+		}
+		let label = data
+			.get(pos + 1..pos + 1 + len)
+			.ok_or_else(|| SolverError::new("truncated DNS message"))?;
+		labels.push(String::from_utf8_lossy(label).to_string());
+		pos += len + 1;
+	}
+}
+
+fn read_record(data: &[u8], pos: usize) -> Result<(Record, usize), SolverError> {
+	let pos = skip_name(data, pos)?;
+	let rr_type = read_u16(data, pos)?;
+	let class = read_u16(data, pos + 2)?;
+	let rdlen = read_u16(data, pos + 8)? as usize;
+	let start = pos + 10;
+	if data.len() < start + rdlen {
+		return Err(SolverError::new("truncated DNS message"));
+	}
+	let record = Record {
+		rr_type,
+		class,
+		rdata: start..start + rdlen,
+	};
+	Ok((record, start + rdlen))
+}
+
+fn encode_name(name: &str) -> Result<Vec<u8>, SolverError> {
+	let mut ret = Vec::with_capacity(name.len() + 2);
+	for label in name
+		.trim_end_matches('.')
+		.split('.')
+		.filter(|l| !l.is_empty())
+	{
+		if label.len() > 63 {
+			return Err(SolverError::new(format!("{}: label too long", name)));
+		}
+		ret.push(label.len() as u8);
+		ret.extend_from_slice(label.to_lowercase().as_bytes());
+	}
+	ret.push(0);
+	if ret.len() > 255 {
+		return Err(SolverError::new(format!("{}: name too long", name)));
+	}
+	Ok(ret)
+}
+
+fn txt_rdata(value: &str) -> Result<Vec<u8>, SolverError> {
+	if value.len() > 255 {
+		return Err(SolverError::new("TXT value too long"));
+	}
+	let mut ret = vec![value.len() as u8];
+	ret.extend_from_slice(value.as_bytes());
+	Ok(ret)
+}
+
+fn parse_txt_rdata(rdata: &[u8]) -> String {
+	let mut ret = Vec::new();
+	let mut pos = 0;
+	while let Some(len) = rdata.get(pos) {
+		let end = (pos + 1 + *len as usize).min(rdata.len());
+		ret.extend_from_slice(&rdata[pos + 1..end]);
+		pos = end;
+	}
+	String::from_utf8_lossy(&ret).to_string()
+}
+
+fn rcode_name(rcode: u16) -> String {
+	let name = match rcode {
+		1 => "FORMERR",
+		2 => "SERVFAIL",
+		3 => "NXDOMAIN",
+		4 => "NOTIMP",
+		5 => "REFUSED",
+		6 => "YXDOMAIN",
+		7 => "YXRRSET",
+		8 => "NXRRSET",
+		9 => "NOTAUTH",
+		10 => "NOTZONE",
+		16 => "BADSIG",
+		17 => "BADKEY",
+		18 => "BADTIME",
+		22 => "BADTRUNC",
+		_ => return format!("RCODE{}", rcode),
+	};
+	name.to_string()
+}
+
+async fn exchange(
+	server: SocketAddr,
+	msg: &[u8],
+	timeout: Duration,
+) -> Result<Vec<u8>, SolverError> {
+	let local: SocketAddr = if server.is_ipv4() {
+		([0, 0, 0, 0], 0).into()
+	} else {
+		([0u16; 8], 0).into()
+	};
+	let socket = UdpSocket::bind(local)
+		.await
+		.map_err(|e| SolverError::new(e.to_string()))?;
+	socket
+		.connect(server)
+		.await
+		.map_err(|e| SolverError::new(e.to_string()))?;
+	socket
+		.send(msg)
+		.await
+		.map_err(|e| SolverError::new(e.to_string()))?;
+	let id = &msg[..2];
+	let mut buf = vec![0; MAX_MSG_LEN];
+	tokio::time::timeout(timeout, async {
+		loop {
+			let len = socket
+				.recv(&mut buf)
+				.await
+				.map_err(|e| SolverError::new(e.to_string()))?;
+			if len >= HEADER_LEN && &buf[..2] == id {
+				return Ok(buf[..len].to_vec());
+			}
+		}
+	})
+	.await
+	.map_err(|_| SolverError::new(format!("{}: no answer from DNS server", server)))?
+}
+
+async fn query_txt(
+	server: SocketAddr,
+	name: &str,
+	timeout: Duration,
+) -> Result<Vec<String>, SolverError> {
+	let mut msg = Message::new(message_id()?, 0);
+	msg.add_record(name, TYPE_TXT, CLASS_IN, None)?;
+	msg.qdcount = 1;
+	let data = exchange(server, &msg.to_bytes(), timeout).await?;
+	let resp = Response::parse(&data)?;
+	if resp.flags & FLAG_QR == 0 || resp.rcode != 0 {
+		return Err(SolverError::new(format!(
+			"{}: query failed: {}",
+			name,
+			rcode_name(resp.rcode)
+		)));
+	}
+	let values = resp
+		.answers
+		.iter()
+		.filter(|r| r.rr_type == TYPE_TXT && r.class == CLASS_IN)
+		.map(|r| parse_txt_rdata(&data[r.rdata.clone()]))
+		.collect();
+	Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::key::{test_keys, AccountKey};
+	use std::collections::HashMap;
+	use std::sync::{Arc, Mutex};
+
+	const ZONE: &str = "example.org";
+	const KEY_NAME: &str = "acme-update";
+	const KEY_SECRET: &str = "c2VjcmV0IGtleSB1c2VkIGZvciB0ZXN0aW5nIHB1cnBvc2VzIG9ubHk=";
+
+	type Records = Arc<Mutex<HashMap<String, Vec<String>>>>;
+
+	/// How the fake server answers dynamic updates.
+	#[derive(Clone, Copy)]
+	enum Answer {
+		Signed,
+		Refused,
+		Unsigned,
+		WrongMac,
+		Echo,
+	}
+
+	fn key_auth() -> KeyAuthorization {
+		let jwk = AccountKey::from_pem(test_keys::ED25519_PKCS8)
+			.unwrap()
+			.jwk();
+		KeyAuthorization::new("evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA", &jwk)
+	}
+
+	fn tsig_key() -> TsigKey {
+		TsigKey::from_base64(KEY_NAME, TsigAlgorithm::HmacSha256, KEY_SECRET).unwrap()
+	}
+
+	fn read_name(data: &[u8], mut pos: usize) -> String {
+		let mut labels = Vec::new();
+		while data[pos] != 0 {
+			let len = data[pos] as usize;
+			labels.push(String::from_utf8_lossy(&data[pos + 1..pos + 1 + len]).to_string());
+			pos += len + 1;
+		}
+		labels.join(".")
+	}
+
+	/// Verifies the TSIG of a request, returning its MAC.
+	fn verify_tsig(data: &[u8], key: &TsigKey) -> Option<Vec<u8>> {
+		let arcount = read_u16(data, 10).unwrap();
+		if arcount != 1 {
+			return None;
+		}
+		let mut pos = HEADER_LEN;
+		for _ in 0..read_u16(data, 4).unwrap() {
+			pos = skip_name(data, pos).unwrap() + 4;
+		}
+		let count = read_u16(data, 6).unwrap() + read_u16(data, 8).unwrap();
+		for _ in 0..count {
+			pos = read_record(data, pos).unwrap().1;
+		}
+		let tsig_start = pos;
+		let (tsig, _) = read_record(data, pos).unwrap();
+		if tsig.rr_type != TYPE_TSIG || read_name(data, tsig_start) != key.name {
+			return None;
+		}
+		let rdata = &data[tsig.rdata];
+		let alg_end = skip_name(rdata, 0).unwrap();
+		let mut time = [0u8; 8];
+		time[2..].copy_from_slice(&rdata[alg_end..alg_end + 6]);
+		let mac_len = read_u16(rdata, alg_end + 8).unwrap() as usize;
+		let mac = &rdata[alg_end + 10..alg_end + 10 + mac_len];
+		let mut signed = data[..tsig_start].to_vec();
+		signed[10..12].copy_from_slice(&0u16.to_be_bytes());
+		let time = u64::from_be_bytes(time);
+		signed.extend_from_slice(&tsig_variables(key, time, 0, &[]).unwrap());
+		(key.mac(&signed) == mac).then(|| mac.to_vec())
+	}
+
+	fn handle(data: &[u8], records: &Records, answer: Answer) -> Vec<u8> {
+		let id = read_u16(data, 0).unwrap();
+		let flags = read_u16(data, 2).unwrap();
+		let mut resp = Message::new(id, FLAG_QR | (flags & !RCODE_MASK));
+		if (flags >> 11) & 0x0f == OPCODE_UPDATE >> 11 {
+			if let Answer::Refused = answer {
+				resp.flags |= 5;
+				return resp.to_bytes();
+			}
+			let request_mac = match verify_tsig(data, &tsig_key()) {
+				Some(mac) => mac,
+				None => {
+					resp.flags |= 9;
+					return resp.to_bytes();
+				}
+			};
+			let mut pos = skip_name(data, HEADER_LEN).unwrap() + 4;
+			for _ in 0..read_u16(data, 8).unwrap() {
+				let name = read_name(data, pos);
+				let (rr, next) = read_record(data, pos).unwrap();
+				let value = parse_txt_rdata(&data[rr.rdata]);
+				let mut records = records.lock().unwrap();
+				let values = records.entry(name).or_default();
+				match rr.class {
+					CLASS_IN => values.push(value),
+					CLASS_NONE => values.retain(|v| v != &value),
+					_ => {}
+				}
+				pos = next;
+			}
+			let time = unix_time().unwrap();
+			return match answer {
+				Answer::Signed | Answer::Refused => {
+					resp.sign(&tsig_key(), time, Some(&request_mac)).unwrap().0
+				}
+				Answer::Unsigned => resp.to_bytes(),
+				Answer::WrongMac => {
+					let key = TsigKey::new(KEY_NAME, TsigAlgorithm::HmacSha256, b"other secret");
+					resp.sign(&key, time, Some(&request_mac)).unwrap().0
+				}
+				Answer::Echo => data.to_vec(),
+			};
+		}
+		let name = read_name(data, HEADER_LEN);
+		resp.add_record(&name, TYPE_TXT, CLASS_IN, None).unwrap();
+		resp.qdcount = 1;
+		let values = records
+			.lock()
+			.unwrap()
+			.get(&name)
+			.cloned()
+			.unwrap_or_default();
+		for value in values {
+			resp.add_record(
+				&name,
+				TYPE_TXT,
+				CLASS_IN,
+				Some((60, &txt_rdata(&value).unwrap())),
+			)
+			.unwrap();
+			resp.ancount += 1;
+		}
+		resp.to_bytes()
+	}
+
+	async fn dns_server(answer: Answer) -> (SocketAddr, Records) {
+		let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		let addr = socket.local_addr().unwrap();
+		let records = Records::default();
+		let server_records = records.clone();
+		tokio::spawn(async move {
+			let mut buf = vec![0; MAX_MSG_LEN];
+			loop {
+				let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+				let resp = handle(&buf[..len], &server_records, answer);
+				socket.send_to(&resp, peer).await.unwrap();
+			}
+		});
+		(addr, records)
+	}
+
+	#[test]
+	fn encode_names() {
+		assert_eq!(encode_name("").unwrap(), vec![0]);
+		assert_eq!(encode_name(".").unwrap(), vec![0]);
+		assert_eq!(
+			encode_name("Example.ORG.").unwrap(),
+			b"\x07example\x03org\x00".to_vec()
+		);
+		assert!(encode_name(&"a".repeat(64)).is_err());
+	}
+
+	#[test]
+	fn record_name() {
+		let solver =
+			Rfc2136Solver::new("127.0.0.1:53".parse().unwrap(), "example.org.", tsig_key());
+		let tests = [
+			(
+				Identifier::dns("example.org"),
+				Some("_acme-challenge.example.org"),
+			),
+			(
+				Identifier::dns("www.Example.org"),
+				Some("_acme-challenge.www.example.org"),
+			),
+			(
+				Identifier::dns("*.example.org"),
+				Some("_acme-challenge.example.org"),
+			),
+			(Identifier::dns("example.net"), None),
+			(Identifier::dns("badexample.org"), None),
+			(Identifier::ip("192.0.2.1"), None),
+		];
+		for (id, name) in tests {
+			let res = solver.record_name(&id);
+			assert_eq!(res.ok().as_deref(), name, "{}", id);
+		}
+	}
+
+	#[test]
+	fn debug_redacts_secret() {
+		let solver = Rfc2136Solver::new("127.0.0.1:53".parse().unwrap(), ZONE, tsig_key());
+		let debug = format!("{:?}", solver);
+		assert!(debug.contains("name: \"acme-update\""), "{}", debug);
+		assert!(!debug.contains("secret"), "{}", debug);
+		assert!(!debug.contains("115, 101, 99"), "{}", debug);
+	}
+
+	#[tokio::test]
+	async fn present_and_cleanup() {
+		let (addr, records) = dns_server(Answer::Signed).await;
+		let solver = Rfc2136Solver::new(addr, ZONE, tsig_key())
+			.propagation_interval(Duration::from_millis(10));
+		let id = Identifier::dns("www.example.org");
+		let ka = key_auth();
+		solver.present(&id, &ka).await.unwrap();
+		assert_eq!(
+			records
+				.lock()
+				.unwrap()
+				.get("_acme-challenge.www.example.org"),
+			Some(&vec![ka.dns_txt_value()])
+		);
+		solver.cleanup(&id, &ka).await.unwrap();
+		assert_eq!(
+			records
+				.lock()
+				.unwrap()
+				.get("_acme-challenge.www.example.org"),
+			Some(&vec![])
+		);
+	}
+
+	#[tokio::test]
+	async fn bad_key() {
+		let (addr, records) = dns_server(Answer::Signed).await;
+		let key = TsigKey::new(KEY_NAME, TsigAlgorithm::HmacSha256, b"wrong secret");
+		let solver = Rfc2136Solver::new(addr, ZONE, key);
+		let res = solver
+			.present(&Identifier::dns("www.example.org"), &key_auth())
+			.await;
+		assert!(res.is_err());
+		assert!(res.unwrap_err().to_string().contains("NOTAUTH"));
+		assert!(records.lock().unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn unverified_answer() {
+		let tests = [
+			(Answer::Unsigned, "unsigned response"),
+			(Answer::WrongMac, "invalid TSIG signature"),
+			(Answer::Echo, "not a response"),
+		];
+		for (answer, msg) in tests {
+			let (addr, _) = dns_server(answer).await;
+			let solver = Rfc2136Solver::new(addr, ZONE, tsig_key());
+			let res = solver
+				.present(&Identifier::dns("www.example.org"), &key_auth())
+				.await;
+			assert!(res.is_err());
+			let err = res.unwrap_err().to_string();
+			assert!(err.contains(msg), "{}", err);
+		}
+	}
+
+	#[tokio::test]
+	async fn propagation_timeout() {
+		let (primary, _) = dns_server(Answer::Signed).await;
+		let (secondary, _) = dns_server(Answer::Refused).await;
+		let solver = Rfc2136Solver::new(primary, ZONE, tsig_key())
+			.authoritative_servers(&[primary, secondary])
+			.propagation_timeout(Duration::from_millis(50))
+			.propagation_interval(Duration::from_millis(10));
+		let res = solver
+			.present(&Identifier::dns("www.example.org"), &key_auth())
+			.await;
+		assert!(res.is_err());
+		let err = res.unwrap_err().to_string();
+		assert!(err.contains(&secondary.to_string()), "{}", err);
+		assert!(!err.contains(&primary.to_string()), "{}", err);
+	}
+}
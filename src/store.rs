@@ -0,0 +1,259 @@
+use crate::errors::{KeyError, StoreError};
+use crate::key::AccountKey;
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+#[cfg(feature = "file_store")]
+mod file;
+
+#[cfg(feature = "file_store")]
+pub use file::FileStore;
+
+/// Everything needed to use an existing account again.
+#[derive(Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountState {
+	/// Account private key, PKCS#8 DER encoded.
+	#[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
+	pub private_key: Zeroizing<Vec<u8>>,
+	pub kid: String,
+	pub directory_url: String,
+	pub contacts: Vec<String>,
+}
+
+impl AccountState {
+	pub fn new<T: ToString>(
+		account_key: &AccountKey,
+		kid: &str,
+		directory_url: &str,
+		contacts: &[T],
+	) -> Result<Self, KeyError> {
+		Ok(Self {
+			private_key: Zeroizing::new(account_key.to_pkcs8_der()?),
+			kid: kid.to_string(),
+			directory_url: directory_url.to_string(),
+			contacts: contacts.iter().map(|e| e.to_string()).collect(),
+		})
+	}
+
+	pub fn account_key(&self) -> Result<AccountKey, KeyError> {
+		AccountKey::from_pkcs8_der(&self.private_key)
+	}
+}
+
+impl fmt::Debug for AccountState {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("AccountState")
+			.field("private_key", &"[redacted]")
+			.field("kid", &self.kid)
+			.field("directory_url", &self.directory_url)
+			.field("contacts", &self.contacts)
+			.finish()
+	}
+}
+
+/// Issued certificate chain, and optionally the certificate's private key.
+#[derive(Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateState {
+	pub chain_pem: String,
+	/// Certificate private key, PKCS#8 DER encoded.
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		serialize_with = "to_base64_opt",
+		deserialize_with = "from_base64_opt"
+	)]
+	pub private_key: Option<Zeroizing<Vec<u8>>>,
+}
+
+impl fmt::Debug for CertificateState {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("CertificateState")
+			.field("chain_pem", &self.chain_pem)
+			.field(
+				"private_key",
+				&self.private_key.as_ref().map(|_| "[redacted]"),
+			)
+			.finish()
+	}
+}
+
+/// Persistent storage for accounts and certificates, both identified by a
+/// name chosen by the caller.
+#[async_trait]
+pub trait Store: Send + Sync {
+	async fn save_account(&self, name: &str, account: &AccountState) -> Result<(), StoreError>;
+
+	async fn load_account(&self, name: &str) -> Result<Option<AccountState>, StoreError>;
+
+	async fn save_certificate(
+		&self,
+		name: &str,
+		certificate: &CertificateState,
+	) -> Result<(), StoreError>;
+
+	async fn load_certificate(&self, name: &str) -> Result<Option<CertificateState>, StoreError>;
+}
+
+/// Store keeping everything in memory, mostly useful for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+	accounts: Mutex<HashMap<String, AccountState>>,
+	certificates: Mutex<HashMap<String, CertificateState>>,
+}
+
+impl MemoryStore {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+	async fn save_account(&self, name: &str, account: &AccountState) -> Result<(), StoreError> {
+		self.accounts
+			.lock()
+			.map_err(|e| StoreError::new(e.to_string()))?
+			.insert(name.to_string(), account.clone());
+		Ok(())
+	}
+
+	async fn load_account(&self, name: &str) -> Result<Option<AccountState>, StoreError> {
+		let accounts = self
+			.accounts
+			.lock()
+			.map_err(|e| StoreError::new(e.to_string()))?;
+		Ok(accounts.get(name).cloned())
+	}
+
+	async fn save_certificate(
+		&self,
+		name: &str,
+		certificate: &CertificateState,
+	) -> Result<(), StoreError> {
+		self.certificates
+			.lock()
+			.map_err(|e| StoreError::new(e.to_string()))?
+			.insert(name.to_string(), certificate.clone());
+		Ok(())
+	}
+
+	async fn load_certificate(&self, name: &str) -> Result<Option<CertificateState>, StoreError> {
+		let certificates = self
+			.certificates
+			.lock()
+			.map_err(|e| StoreError::new(e.to_string()))?;
+		Ok(certificates.get(name).cloned())
+	}
+}
+
+fn to_base64<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&Zeroizing::new(URL_SAFE_NO_PAD.encode(data)))
+}
+
+fn to_base64_opt<S: Serializer>(
+	data: &Option<Zeroizing<Vec<u8>>>,
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	match data {
+		Some(data) => to_base64(data, serializer),
+		None => serializer.serialize_none(),
+	}
+}
+
+fn from_base64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Zeroizing<Vec<u8>>, D::Error> {
+	let s = Zeroizing::new(String::deserialize(deserializer)?);
+	URL_SAFE_NO_PAD
+		.decode(s.as_bytes())
+		.map(Zeroizing::new)
+		.map_err(serde::de::Error::custom)
+}
+
+fn from_base64_opt<'de, D: Deserializer<'de>>(
+	deserializer: D,
+) -> Result<Option<Zeroizing<Vec<u8>>>, D::Error> {
+	from_base64(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{AccountState, CertificateState, MemoryStore, Store};
+	use crate::key::{test_keys, AccountKey};
+	use zeroize::Zeroizing;
+
+	pub(super) fn account() -> AccountState {
+		AccountState::new(
+			&AccountKey::from_pem(test_keys::P256_PKCS8).unwrap(),
+			"https://example.com/acme/acct/evOfKhNU60wg",
+			"https://example.com/acme/directory",
+			&["mailto:cert-admin@example.org"],
+		)
+		.unwrap()
+	}
+
+	fn certificate() -> CertificateState {
+		CertificateState {
+			chain_pem: "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n".to_string(),
+			private_key: Some(Zeroizing::new(vec![0x30, 0x2e])),
+		}
+	}
+
+	pub(super) async fn check_store<S: Store>(store: &S) {
+		assert_eq!(store.load_account("default").await.unwrap(), None);
+		store.save_account("default", &account()).await.unwrap();
+		let loaded = store.load_account("default").await.unwrap();
+		assert_eq!(loaded, Some(account()));
+		assert_eq!(
+			loaded.unwrap().account_key().unwrap().jwk().thumbprint(),
+			"ldAQBXxSNclyDuv6EdmgCErq4KwCFIEj06u4IZFU4sY"
+		);
+		let mut updated = account();
+		updated.contacts.clear();
+		store.save_account("default", &updated).await.unwrap();
+		assert_eq!(store.load_account("default").await.unwrap(), Some(updated));
+
+		assert_eq!(store.load_certificate("example.org").await.unwrap(), None);
+		store
+			.save_certificate("example.org", &certificate())
+			.await
+			.unwrap();
+		assert_eq!(
+			store.load_certificate("example.org").await.unwrap(),
+			Some(certificate())
+		);
+		let no_key = CertificateState {
+			private_key: None,
+			..certificate()
+		};
+		store
+			.save_certificate("example.org", &no_key)
+			.await
+			.unwrap();
+		assert_eq!(
+			store.load_certificate("example.org").await.unwrap(),
+			Some(no_key)
+		);
+	}
+
+	#[test]
+	fn debug_redacts_keys() {
+		let debug = format!("{:?}", account());
+		assert!(debug.contains("private_key: \"[redacted]\""));
+		assert!(debug.contains("evOfKhNU60wg"));
+		let debug = format!("{:?}", certificate());
+		assert!(debug.contains("private_key: Some(\"[redacted]\")"));
+		assert!(!debug.contains("48, 46"));
+	}
+
+	#[tokio::test]
+	async fn memory_store() {
+		check_store(&MemoryStore::new()).await;
+	}
+}
@@ -0,0 +1,208 @@
+use super::{AccountState, CertificateState, Store};
+use crate::errors::StoreError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use zeroize::Zeroizing;
+
+const ACCOUNTS_DIR: &str = "accounts";
+const CERTIFICATES_DIR: &str = "certificates";
+
+/// Store writing JSON files in a directory.
+///
+/// Files are only readable by their owner and are written to a temporary
+/// file first, then atomically renamed, so a crash never leaves a partially
+/// written file behind. The file system is accessed from Tokio's blocking
+/// thread pool, hence the store has to be used within a Tokio runtime.
+#[derive(Debug)]
+pub struct FileStore {
+	root: PathBuf,
+	tmp_counter: AtomicUsize,
+}
+
+impl FileStore {
+	pub fn new<P: AsRef<Path>>(root: P) -> Self {
+		Self {
+			root: root.as_ref().to_path_buf(),
+			tmp_counter: AtomicUsize::new(0),
+		}
+	}
+
+	fn path(&self, dir: &str, name: &str) -> Result<PathBuf, StoreError> {
+		let is_valid = !name.is_empty()
+			&& !name.starts_with('.')
+			&& name
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || "-_.@".contains(c));
+		if !is_valid {
+			return Err(StoreError::new(format!("{}: invalid name", name)));
+		}
+		Ok(self.root.join(dir).join(format!("{}.json", name)))
+	}
+
+	async fn write<T: Serialize + Sync>(
+		&self,
+		dir: &str,
+		name: &str,
+		value: &T,
+	) -> Result<(), StoreError> {
+		let path = self.path(dir, name)?;
+		let parent = self.root.join(dir);
+		let tmp_path = parent.join(format!(
+			".{}.{}.{}.tmp",
+			name,
+			std::process::id(),
+			self.tmp_counter.fetch_add(1, Ordering::Relaxed)
+		));
+		let data = Zeroizing::new(serde_json::to_vec_pretty(value)?);
+		blocking(move || {
+			create_dir(&parent)?;
+			let res = write_file(&tmp_path, &data)
+				.and_then(|_| fs::rename(&tmp_path, &path).map_err(StoreError::from));
+			if res.is_err() {
+				let _ = fs::remove_file(&tmp_path);
+			}
+			res?;
+			sync_dir(&parent)
+		})
+		.await
+	}
+
+	async fn read<T: for<'de> Deserialize<'de>>(
+		&self,
+		dir: &str,
+		name: &str,
+	) -> Result<Option<T>, StoreError> {
+		let path = self.path(dir, name)?;
+		let data = blocking(move || match fs::read(&path) {
+			Ok(data) => Ok(Some(Zeroizing::new(data))),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e.into()),
+		})
+		.await?;
+		match data {
+			Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+			None => Ok(None),
+		}
+	}
+}
+
+#[async_trait]
+impl Store for FileStore {
+	async fn save_account(&self, name: &str, account: &AccountState) -> Result<(), StoreError> {
+		self.write(ACCOUNTS_DIR, name, account).await
+	}
+
+	async fn load_account(&self, name: &str) -> Result<Option<AccountState>, StoreError> {
+		self.read(ACCOUNTS_DIR, name).await
+	}
+
+	async fn save_certificate(
+		&self,
+		name: &str,
+		certificate: &CertificateState,
+	) -> Result<(), StoreError> {
+		self.write(CERTIFICATES_DIR, name, certificate).await
+	}
+
+	async fn load_certificate(&self, name: &str) -> Result<Option<CertificateState>, StoreError> {
+		self.read(CERTIFICATES_DIR, name).await
+	}
+}
+
+/// Runs blocking file system operations outside of the async executor.
+async fn blocking<F, T>(f: F) -> Result<T, StoreError>
+where
+	F: FnOnce() -> Result<T, StoreError> + Send + 'static,
+	T: Send + 'static,
+{
+	tokio::task::spawn_blocking(f)
+		.await
+		.map_err(|e| StoreError::new(e.to_string()))?
+}
+
+#[cfg(unix)]
+fn create_dir(path: &Path) -> Result<(), StoreError> {
+	use std::os::unix::fs::DirBuilderExt;
+
+	fs::DirBuilder::new()
+		.recursive(true)
+		.mode(0o700)
+		.create(path)?;
+	Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_dir(path: &Path) -> Result<(), StoreError> {
+	fs::create_dir_all(path)?;
+	Ok(())
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), StoreError> {
+	let mut options = OpenOptions::new();
+	options.write(true).create_new(true);
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::OpenOptionsExt;
+		options.mode(0o600);
+	}
+	let mut file = options.open(path)?;
+	file.write_all(data)?;
+	file.sync_all()?;
+	Ok(())
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<(), StoreError> {
+	File::open(path)?.sync_all()?;
+	Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<(), StoreError> {
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::FileStore;
+	use crate::store::tests::{account, check_store};
+	use crate::store::Store;
+
+	#[tokio::test]
+	async fn file_store() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = FileStore::new(dir.path());
+		check_store(&store).await;
+		let accounts: Vec<_> = std::fs::read_dir(dir.path().join("accounts"))
+			.unwrap()
+			.map(|e| e.unwrap().file_name())
+			.collect();
+		assert_eq!(accounts, vec!["default.json"]);
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			let path = dir.path().join("accounts").join("default.json");
+			let mode = std::fs::metadata(path).unwrap().permissions().mode();
+			assert_eq!(mode & 0o777, 0o600);
+			let mode = std::fs::metadata(dir.path().join("accounts"))
+				.unwrap()
+				.permissions()
+				.mode();
+			assert_eq!(mode & 0o777, 0o700);
+		}
+	}
+
+	#[tokio::test]
+	async fn file_store_invalid_names() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = FileStore::new(dir.path());
+		for name in ["", "../default", "a/b", ".hidden"] {
+			assert!(store.save_account(name, &account()).await.is_err());
+			assert!(store.load_certificate(name).await.is_err());
+		}
+	}
+}
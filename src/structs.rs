@@ -5,24 +5,163 @@ macro_rules! deserialize_from_str {
 			type Err = $crate::errors::DeserializeError;
 
 			fn from_str(data: &str) -> Result<Self, Self::Err> {
-				let res = serde_json::from_str(data).map_err(|e| Self::Err {
-					data_type: $label.to_string(),
-					err_msg: e.to_string(),
-				})?;
-				Ok(res)
+				$crate::structs::from_json_slice(data.as_bytes(), $label)
+			}
+		}
+
+		impl $t {
+			/// Parses a JSON document without copying it into a `String`.
+			pub fn from_slice(data: &[u8]) -> Result<Self, $crate::errors::DeserializeError> {
+				$crate::structs::from_json_slice(data, $label)
+			}
+
+			/// Parses a JSON document from a reader, such as a response body
+			/// being received.
+			pub fn from_reader<R: std::io::Read>(
+				reader: R,
+			) -> Result<Self, $crate::errors::DeserializeError> {
+				$crate::structs::from_json_reader(reader, $label)
 			}
 		}
 	};
 }
 
 mod account;
+mod authorization;
+mod certificate;
+mod challenge;
 mod directory;
 mod error;
+mod identifier;
 mod jwk;
 mod jws;
+mod order;
+mod renewal_info;
+#[cfg(test)]
+mod roundtrip;
+mod timestamp;
+mod url;
+
+use crate::errors::DeserializeError;
+use serde::de::{Deserialize, DeserializeOwned};
 
-pub use account::{AccountManagement, AccountResource, AccountStatus};
+pub use self::url::{Url, UrlRef};
+pub use account::{AccountKeyRollover, AccountManagement, AccountResource, AccountStatus};
+pub use authorization::{Authorization, AuthorizationStatus, NewAuthorization};
+pub use certificate::CertificateChain;
+pub use challenge::{Challenge, ChallengeStatus, ChallengeType, KeyAuthorization};
 pub use directory::{Directory, DirectoryMeta};
 pub use error::Error;
-pub use jwk::Jwk;
-pub use jws::{Jws, JwsProtectedHeader};
+pub use identifier::{Identifier, IdentifierType};
+pub use jwk::{EcdsaCurve, EddsaCurve, Jwk, JwkType, KeyUse, RsaAlgorithm};
+pub use jws::{Jws, JwsAlgorithm, JwsProtectedHeader};
+pub use order::{NewOrder, Order, OrderStatus, OrdersList, OrdersListRef};
+pub use renewal_info::{CertificateId, RenewalInfo, SuggestedWindow};
+pub use timestamp::Timestamp;
+
+/// Parses a JSON document, keeping track of the member being parsed so that
+/// errors can be located.
+pub(crate) fn from_json<T: DeserializeOwned>(
+	data: &str,
+	data_type: &str,
+) -> Result<T, DeserializeError> {
+	from_json_slice(data.as_bytes(), data_type)
+}
+
+/// Same as `from_json`, but the result may borrow from the document.
+pub(crate) fn from_json_slice<'de, T: Deserialize<'de>>(
+	data: &'de [u8],
+	data_type: &str,
+) -> Result<T, DeserializeError> {
+	from_json_slice_checked(data, data_type, true)
+}
+
+/// Same as `from_json_slice`, but members unknown to `T` are rejected unless
+/// `extra_fields` is set, see `Quirks::extra_fields`.
+pub(crate) fn from_json_slice_checked<'de, T: Deserialize<'de>>(
+	data: &'de [u8],
+	data_type: &str,
+	extra_fields: bool,
+) -> Result<T, DeserializeError> {
+	parse_json(
+		serde_json::Deserializer::from_slice(data),
+		Some(data),
+		data_type,
+		extra_fields,
+	)
+}
+
+/// Same as `from_json`, but the document is not available to build an
+/// excerpt around the error.
+pub(crate) fn from_json_reader<T: DeserializeOwned, R: std::io::Read>(
+	reader: R,
+	data_type: &str,
+) -> Result<T, DeserializeError> {
+	from_json_reader_checked(reader, data_type, true)
+}
+
+/// Same as `from_json_reader`, but members unknown to `T` are rejected
+/// unless `extra_fields` is set, see `Quirks::extra_fields`.
+pub(crate) fn from_json_reader_checked<T: DeserializeOwned, R: std::io::Read>(
+	reader: R,
+	data_type: &str,
+	extra_fields: bool,
+) -> Result<T, DeserializeError> {
+	parse_json(
+		serde_json::Deserializer::from_reader(reader),
+		None,
+		data_type,
+		extra_fields,
+	)
+}
+
+fn parse_json<'de, R, T>(
+	mut deserializer: serde_json::Deserializer<R>,
+	data: Option<&[u8]>,
+	data_type: &str,
+	extra_fields: bool,
+) -> Result<T, DeserializeError>
+where
+	R: serde_json::de::Read<'de>,
+	T: Deserialize<'de>,
+{
+	let mut unknown = None;
+	let mut on_ignored = |path: serde_ignored::Path| {
+		if unknown.is_none() {
+			unknown = Some(member_path(&path));
+		}
+	};
+	let ignored = serde_ignored::Deserializer::new(&mut deserializer, &mut on_ignored);
+	let value = serde_path_to_error::deserialize(ignored).map_err(|e| {
+		let path = e.path().to_string();
+		DeserializeError::json(data_type, data, &path, e.into_inner())
+	})?;
+	deserializer
+		.end()
+		.map_err(|e| DeserializeError::json(data_type, data, "", e))?;
+	match unknown {
+		Some(path) if !extra_fields => Err(DeserializeError::new(
+			data_type,
+			format!("unknown member `{}`", path),
+		)),
+		_ => Ok(value),
+	}
+}
+
+/// Formats the path of an ignored member the same way as the path of an
+/// invalid one, such as `challenges[0].url`.
+fn member_path(path: &serde_ignored::Path) -> String {
+	use serde_ignored::Path;
+
+	match path {
+		Path::Root => String::new(),
+		Path::Seq { parent, index } => format!("{}[{}]", member_path(parent), index),
+		Path::Map { parent, key } => match member_path(parent) {
+			parent if parent.is_empty() => key.to_string(),
+			parent => format!("{}.{}", parent, key),
+		},
+		Path::Some { parent }
+		| Path::NewtypeStruct { parent }
+		| Path::NewtypeVariant { parent } => member_path(parent),
+	}
+}
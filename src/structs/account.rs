@@ -1,8 +1,12 @@
+use crate::errors::DeserializeError;
+use crate::quirks::Quirks;
 use crate::structs::jwk::Jwk;
 use crate::structs::jws::Jws;
+use crate::structs::url::Url;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AccountStatus {
 	Valid,
@@ -10,22 +14,44 @@ pub enum AccountStatus {
 	Revoked,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountResource {
 	pub status: AccountStatus,
-	pub contact: Option<Vec<String>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub contact: Option<Vec<Url>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub terms_of_service_agreed: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub external_account_binding: Option<serde_json::Value>,
-	#[cfg(feature = "opt_account_orders")]
-	pub orders: Option<String>,
-	#[cfg(not(feature = "opt_account_orders"))]
-	pub orders: String,
+	/// Mandatory according to RFC 8555, but missing from the objects returned
+	/// by some servers, see `Quirks::optional_account_orders`.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub orders: Option<Url>,
+}
+
+impl FromStr for AccountResource {
+	type Err = DeserializeError;
+
+	/// Parses an account using the default quirks, see `Quirks::parse_account`.
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		Quirks::default().parse_account(data)
+	}
 }
 
-deserialize_from_str!(AccountResource, "account resource");
+impl AccountResource {
+	/// Same as `from_str`, without copying the document into a `String`.
+	pub fn from_slice(data: &[u8]) -> Result<Self, DeserializeError> {
+		Quirks::default().parse_account(data)
+	}
+
+	/// Same as `from_str`, the document being read from a reader.
+	pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self, DeserializeError> {
+		Quirks::default().parse_account_reader(reader)
+	}
+}
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountManagement {
 	#[serde(skip_serializing_if = "Option::is_none")]
@@ -40,6 +66,8 @@ pub struct AccountManagement {
 	external_account_binding: Option<Jws<Jwk>>,
 }
 
+deserialize_from_str!(AccountManagement, "account management request");
+
 impl AccountManagement {
 	pub fn creation_request<T: ToString>(contacts: &[T], tos_agreed: bool) -> Self {
 		Self {
@@ -84,13 +112,15 @@ impl AccountManagement {
 	}
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountKeyRollover {
-	pub account: String,
+	pub account: Url,
 	pub old_key: Jwk,
 }
 
+deserialize_from_str!(AccountKeyRollover, "account key rollover");
+
 #[cfg(test)]
 mod tests {
 	use super::{AccountManagement, AccountResource, AccountStatus};
@@ -141,15 +171,9 @@ mod tests {
 		assert_eq!(ar.status, AccountStatus::Valid);
 		assert_eq!(ar.contact, None);
 		assert_eq!(ar.terms_of_service_agreed, None);
-		#[cfg(feature = "opt_account_orders")]
-		assert_eq!(
-			ar.orders,
-			Some("https://localhost:14000/list-orderz/1".to_string())
-		);
-		#[cfg(not(feature = "opt_account_orders"))]
 		assert_eq!(
-			ar.orders,
-			"https://localhost:14000/list-orderz/1".to_string()
+			ar.orders.as_deref(),
+			Some("https://localhost:14000/list-orderz/1")
 		);
 	}
 
@@ -179,19 +203,12 @@ mod tests {
 		let ar = res.unwrap();
 		assert!(ar.external_account_binding.is_some());
 		assert_eq!(ar.status, AccountStatus::Valid);
+		let contact = ar.contact.unwrap();
+		assert_eq!(contact.len(), 1);
+		assert_eq!(contact[0], "mailto:derp@example.com");
 		assert_eq!(
-			ar.contact,
-			Some(vec!["mailto:derp@example.com".to_string()])
-		);
-		#[cfg(feature = "opt_account_orders")]
-		assert_eq!(
-			ar.orders,
-			Some("https://localhost:14000/list-orderz/1".to_string())
-		);
-		#[cfg(not(feature = "opt_account_orders"))]
-		assert_eq!(
-			ar.orders,
-			"https://localhost:14000/list-orderz/1".to_string()
+			ar.orders.as_deref(),
+			Some("https://localhost:14000/list-orderz/1")
 		);
 	}
 
@@ -0,0 +1,219 @@
+use crate::errors::RequestError;
+use crate::structs::challenge::Challenge;
+use crate::structs::directory::Directory;
+use crate::structs::identifier::{Identifier, IdentifierType};
+use crate::structs::timestamp::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthorizationStatus {
+	Pending,
+	Valid,
+	Invalid,
+	Deactivated,
+	Expired,
+	Revoked,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Authorization {
+	pub identifier: Identifier,
+	pub status: AuthorizationStatus,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub expires: Option<Timestamp>,
+	pub challenges: Vec<Challenge>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub wildcard: Option<bool>,
+}
+
+deserialize_from_str!(Authorization, "authorization");
+
+impl Authorization {
+	/// Whether this authorization can be used to issue a certificate for the
+	/// given identifier, that is if it is valid, has not expired and is for
+	/// this identifier. Wildcard authorizations only cover wildcard
+	/// identifiers.
+	pub fn covers(&self, identifier: &Identifier) -> bool {
+		if self.status != AuthorizationStatus::Valid
+			|| self.identifier.id_type != identifier.id_type
+		{
+			return false;
+		}
+		if let Some(expires) = &self.expires {
+			if expires.system_time() <= SystemTime::now() {
+				return false;
+			}
+		}
+		let value = match identifier.value.strip_prefix("*.") {
+			Some(v) if self.wildcard.unwrap_or(false) => v,
+			None if !self.wildcard.unwrap_or(false) => identifier.value.as_str(),
+			_ => return false,
+		};
+		match identifier.id_type {
+			IdentifierType::Dns => self
+				.identifier
+				.value
+				.trim_end_matches('.')
+				.eq_ignore_ascii_case(value.trim_end_matches('.')),
+			IdentifierType::Ip => self.identifier.value == value,
+		}
+	}
+}
+
+/// Payload of a newAuthz request, used to pre-authorize an identifier
+/// (RFC 8555 section 7.4.1).
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct NewAuthorization {
+	identifier: Identifier,
+}
+
+deserialize_from_str!(NewAuthorization, "new authorization");
+
+impl NewAuthorization {
+	pub fn new(identifier: &Identifier) -> Self {
+		Self {
+			identifier: identifier.clone(),
+		}
+	}
+
+	pub fn identifier(&self) -> &Identifier {
+		&self.identifier
+	}
+
+	/// Checks the request against the directory before it is sent.
+	pub fn validate(&self, directory: &Directory) -> Result<(), RequestError> {
+		if !directory.supports_pre_authorization() {
+			return Err(RequestError::new(
+				"the server does not support pre-authorization",
+			));
+		}
+		if self.identifier.value.starts_with("*.") {
+			return Err(RequestError::new(format!(
+				"{}: wildcard identifiers cannot be pre-authorized",
+				self.identifier
+			)));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Authorization, AuthorizationStatus, NewAuthorization};
+	use crate::structs::{ChallengeType, Directory, Identifier};
+	use std::str::FromStr;
+
+	fn valid_authz(value: &str, expires: &str, wildcard: bool) -> Authorization {
+		let data = format!(
+			r#"{{
+				"status": "valid",
+				"expires": "{}",
+				"identifier": {{"type": "dns", "value": "{}"}},
+				"challenges": [],
+				"wildcard": {}
+			}}"#,
+			expires, value, wildcard
+		);
+		Authorization::from_str(&data).unwrap()
+	}
+
+	#[test]
+	fn deserialize_authorization() {
+		let data = r#"{
+			"status": "pending",
+			"expires": "2016-01-02T14:09:30Z",
+			"identifier": {
+				"type": "dns",
+				"value": "www.example.org"
+			},
+			"challenges": [
+				{
+					"url": "https://example.com/acme/chall/prV_B7yEyA4",
+					"type": "http-01",
+					"status": "pending",
+					"token": "DGyRejmCefe7v4NfDGDKfA"
+				},
+				{
+					"url": "https://example.com/acme/chall/Rg5dV14Gh1Q",
+					"type": "dns-01",
+					"status": "pending",
+					"token": "DGyRejmCefe7v4NfDGDKfA"
+				}
+			],
+			"wildcard": false
+		}"#;
+		let res = Authorization::from_str(data);
+		assert!(res.is_ok(), "deserialization failed");
+		let authz = res.unwrap();
+		assert_eq!(authz.status, AuthorizationStatus::Pending);
+		assert_eq!(authz.identifier, Identifier::dns("www.example.org"));
+		assert_eq!(
+			authz.expires.as_ref().map(|t| t.as_str()),
+			Some("2016-01-02T14:09:30Z")
+		);
+		assert_eq!(authz.challenges.len(), 2);
+		assert_eq!(authz.challenges[0].challenge_type, ChallengeType::Http01);
+		assert_eq!(authz.challenges[1].challenge_type, ChallengeType::Dns01);
+		assert_eq!(authz.wildcard, Some(false));
+	}
+
+	#[test]
+	fn deserialize_authorization_no_challenges() {
+		let data = r#"{
+			"status": "valid",
+			"identifier": {
+				"type": "dns",
+				"value": "www.example.org"
+			}
+		}"#;
+		let res = Authorization::from_str(data);
+		assert!(res.is_err());
+	}
+
+	#[test]
+	fn authorization_covers() {
+		let authz = valid_authz("www.example.org", "2999-01-01T00:00:00Z", false);
+		assert!(authz.covers(&Identifier::dns("www.example.org")));
+		assert!(authz.covers(&Identifier::dns("WWW.Example.org.")));
+		assert!(!authz.covers(&Identifier::dns("example.org")));
+		assert!(!authz.covers(&Identifier::dns("*.www.example.org")));
+		assert!(!authz.covers(&Identifier::ip("www.example.org")));
+		let authz = valid_authz("example.org", "2999-01-01T00:00:00Z", true);
+		assert!(authz.covers(&Identifier::dns("*.example.org")));
+		assert!(!authz.covers(&Identifier::dns("example.org")));
+		let authz = valid_authz("example.org", "2016-01-02T14:09:30Z", false);
+		assert!(!authz.covers(&Identifier::dns("example.org")));
+		let mut authz = valid_authz("example.org", "2999-01-01T00:00:00Z", false);
+		authz.status = AuthorizationStatus::Pending;
+		assert!(!authz.covers(&Identifier::dns("example.org")));
+	}
+
+	#[test]
+	fn new_authorization() {
+		let with_authz = Directory::from_str(
+			r#"{
+				"keyChange": "https://example.org/acme/key-change",
+				"newAccount": "https://example.org/acme/new-acct",
+				"newAuthz": "https://example.org/acme/new-authz",
+				"newNonce": "https://example.org/acme/new-nonce",
+				"newOrder": "https://example.org/acme/new-order",
+				"revokeCert": "https://example.org/acme/revoke-cert"
+			}"#,
+		)
+		.unwrap();
+		let mut without_authz = with_authz.clone();
+		without_authz.new_authz = None;
+		let req = NewAuthorization::new(&Identifier::dns("example.org"));
+		assert_eq!(
+			serde_json::to_string(&req).unwrap(),
+			r#"{"identifier":{"type":"dns","value":"example.org"}}"#
+		);
+		assert!(req.validate(&with_authz).is_ok());
+		assert!(req.validate(&without_authz).is_err());
+		let req = NewAuthorization::new(&Identifier::dns("*.example.org"));
+		assert!(req.validate(&with_authz).is_err());
+	}
+}
@@ -0,0 +1,228 @@
+use crate::structs::error::Error;
+use crate::structs::jwk::Jwk;
+use crate::structs::timestamp::Timestamp;
+use crate::structs::url::Url;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+pub enum ChallengeType {
+	#[serde(rename = "http-01")]
+	Http01,
+	#[serde(rename = "dns-01")]
+	Dns01,
+	#[serde(rename = "tls-alpn-01")]
+	TlsAlpn01,
+	#[serde(other)]
+	Unknown,
+}
+
+impl fmt::Display for ChallengeType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			ChallengeType::Http01 => "http-01",
+			ChallengeType::Dns01 => "dns-01",
+			ChallengeType::TlsAlpn01 => "tls-alpn-01",
+			ChallengeType::Unknown => "unknown",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeStatus {
+	Pending,
+	Processing,
+	Valid,
+	Invalid,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Challenge {
+	#[serde(rename = "type")]
+	pub challenge_type: ChallengeType,
+	pub url: Url,
+	pub status: ChallengeStatus,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub validated: Option<Timestamp>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<Error>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub token: Option<String>,
+}
+
+deserialize_from_str!(Challenge, "challenge");
+
+impl Challenge {
+	/// Derives the key authorization of this challenge for the given account key.
+	///
+	/// Returns `None` if the challenge does not have any token.
+	pub fn key_authorization(&self, account_key: &Jwk) -> Option<KeyAuthorization> {
+		self.token
+			.as_ref()
+			.map(|token| KeyAuthorization::new(token, account_key))
+	}
+}
+
+/// Key authorization as defined in RFC 8555, section 8.1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyAuthorization {
+	token: String,
+	value: String,
+}
+
+impl KeyAuthorization {
+	pub fn new(token: &str, account_key: &Jwk) -> Self {
+		Self {
+			token: token.to_string(),
+			value: format!("{}.{}", token, account_key.thumbprint()),
+		}
+	}
+
+	pub fn token(&self) -> &str {
+		&self.token
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.value
+	}
+
+	/// SHA-256 digest of the key authorization, as used by tls-alpn-01.
+	pub fn digest(&self) -> [u8; 32] {
+		crate::crypto::sha256(self.value.as_bytes())
+	}
+
+	/// Content of the TXT record to provision for dns-01.
+	pub fn dns_txt_value(&self) -> String {
+		URL_SAFE_NO_PAD.encode(self.digest())
+	}
+}
+
+impl fmt::Display for KeyAuthorization {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Challenge, ChallengeStatus, ChallengeType, KeyAuthorization};
+	use crate::key::{test_keys, AccountKey};
+	use crate::structs::Jwk;
+	use std::str::FromStr;
+
+	fn account_key() -> Jwk {
+		AccountKey::from_pem(test_keys::P256_PKCS8).unwrap().jwk()
+	}
+
+	#[test]
+	fn deserialize_challenge_type() {
+		let tests = [
+			(r#" "http-01" "#, ChallengeType::Http01),
+			(r#" "dns-01" "#, ChallengeType::Dns01),
+			(r#" "tls-alpn-01" "#, ChallengeType::TlsAlpn01),
+			(r#" "dns-account-01" "#, ChallengeType::Unknown),
+			(r#" "HTTP-01" "#, ChallengeType::Unknown),
+		];
+		for (type_str, type_ref) in tests {
+			let res = serde_json::from_str::<ChallengeType>(type_str);
+			assert!(res.is_ok(), "deserialization of `{}` failed", type_str);
+			assert_eq!(res.unwrap(), type_ref);
+		}
+	}
+
+	#[test]
+	fn deserialize_challenge() {
+		let data = r#"{
+			"type": "http-01",
+			"url": "https://example.com/acme/chall/prV_B7yEyA4",
+			"status": "pending",
+			"token": "LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0"
+		}"#;
+		let res = Challenge::from_str(data);
+		assert!(res.is_ok(), "deserialization failed");
+		let c = res.unwrap();
+		assert_eq!(c.challenge_type, ChallengeType::Http01);
+		assert_eq!(c.url, "https://example.com/acme/chall/prV_B7yEyA4");
+		assert_eq!(c.status, ChallengeStatus::Pending);
+		assert_eq!(
+			c.token,
+			Some("LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0".to_string())
+		);
+		assert!(c.validated.is_none());
+		assert!(c.error.is_none());
+	}
+
+	#[test]
+	fn deserialize_challenge_invalid() {
+		let data = r#"{
+			"type": "dns-01",
+			"url": "https://example.com/acme/chall/Rg5dV14Gh1Q",
+			"status": "invalid",
+			"token": "evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA",
+			"error": {
+				"type": "urn:ietf:params:acme:error:dns",
+				"detail": "No TXT record found"
+			}
+		}"#;
+		let res = Challenge::from_str(data);
+		assert!(res.is_ok(), "deserialization failed");
+		let c = res.unwrap();
+		assert_eq!(c.challenge_type, ChallengeType::Dns01);
+		assert_eq!(c.status, ChallengeStatus::Invalid);
+		assert!(c.error.is_some());
+	}
+
+	#[test]
+	fn challenge_key_authorization() {
+		let jwk = account_key();
+		let data = r#"{
+			"type": "http-01",
+			"url": "https://example.com/acme/chall/prV_B7yEyA4",
+			"status": "pending",
+			"token": "LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0"
+		}"#;
+		let c = Challenge::from_str(data).unwrap();
+		let ka = c.key_authorization(&jwk);
+		assert!(ka.is_some());
+		let ka = ka.unwrap();
+		assert_eq!(ka.token(), "LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0");
+		assert_eq!(
+			ka.as_str(),
+			format!(
+				"LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0.{}",
+				jwk.thumbprint()
+			)
+		);
+		assert_eq!(ka, KeyAuthorization::new(ka.token(), &jwk));
+	}
+
+	#[test]
+	fn challenge_key_authorization_no_token() {
+		let data = r#"{
+			"type": "dns-account-01",
+			"url": "https://example.com/acme/chall/prV_B7yEyA4",
+			"status": "pending"
+		}"#;
+		let c = Challenge::from_str(data).unwrap();
+		assert!(c.key_authorization(&account_key()).is_none());
+	}
+
+	#[test]
+	fn key_authorization_dns_txt_value() {
+		let jwk = account_key();
+		let ka = KeyAuthorization::new("evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA", &jwk);
+		assert_eq!(
+			ka.as_str(),
+			"evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA.ldAQBXxSNclyDuv6EdmgCErq4KwCFIEj06u4IZFU4sY"
+		);
+		assert_eq!(
+			ka.dns_txt_value(),
+			"9P5iQG-WK-2DXx6kwO4I-FfQ_7NCfFmizOUBD6r9YrU"
+		);
+	}
+}
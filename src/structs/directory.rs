@@ -1,26 +1,82 @@
-use serde::Deserialize;
+use crate::structs::url::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Directory {
-	pub new_nonce: String,
-	pub new_account: String,
-	pub new_order: String,
-	pub new_authz: Option<String>,
-	pub revoke_cert: String,
-	pub key_change: String,
+	pub new_nonce: Url,
+	pub new_account: Url,
+	pub new_order: Url,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub new_authz: Option<Url>,
+	pub revoke_cert: Url,
+	pub key_change: Url,
+	/// ACME Renewal Information endpoint (RFC 9773).
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub renewal_info: Option<Url>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub meta: Option<DirectoryMeta>,
+	/// Members not defined by the supported specifications.
+	#[serde(flatten)]
+	pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 deserialize_from_str!(Directory, "directory");
 
-#[derive(Clone, Debug, Deserialize)]
+impl Directory {
+	/// Whether the CA requires an external account binding on account
+	/// creation.
+	pub fn requires_external_account(&self) -> bool {
+		self.meta
+			.as_ref()
+			.and_then(|m| m.external_account_required)
+			.unwrap_or(false)
+	}
+
+	/// Whether the CA allows authorizations to be created before an order
+	/// (RFC 8555 section 7.4.1).
+	pub fn supports_pre_authorization(&self) -> bool {
+		self.new_authz.is_some()
+	}
+
+	/// Whether the CA provides ACME Renewal Information (RFC 9773).
+	pub fn supports_renewal_info(&self) -> bool {
+		self.renewal_info.is_some()
+	}
+
+	pub fn terms_of_service(&self) -> Option<&str> {
+		self.meta.as_ref()?.terms_of_service.as_deref()
+	}
+
+	/// Whether the CA advertises the given certificate profile.
+	pub fn supports_profile(&self, name: &str) -> bool {
+		self.meta
+			.as_ref()
+			.and_then(|m| m.profiles.as_ref())
+			.map(|p| p.contains_key(name))
+			.unwrap_or(false)
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DirectoryMeta {
-	pub terms_of_service: Option<String>,
-	pub website: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub terms_of_service: Option<Url>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub website: Option<Url>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub caa_identities: Option<Vec<String>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub external_account_required: Option<bool>,
+	/// Certificate profiles offered by the CA, by name, with a human-readable
+	/// description (draft-aaron-acme-profiles).
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub profiles: Option<BTreeMap<String, String>>,
+	/// Members not defined by the supported specifications.
+	#[serde(flatten)]
+	pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 #[cfg(test)]
@@ -45,8 +101,8 @@ mod tests {
 		assert_eq!(parsed_dir.new_account, "https://example.org/acme/new-acct");
 		assert_eq!(parsed_dir.new_order, "https://example.org/acme/new-order");
 		assert_eq!(
-			parsed_dir.new_authz,
-			Some("https://example.org/acme/new-authz".to_string())
+			parsed_dir.new_authz.as_deref(),
+			Some("https://example.org/acme/new-authz")
 		);
 		assert_eq!(
 			parsed_dir.revoke_cert,
@@ -94,16 +150,16 @@ mod tests {
 			\"newOrder\": \"https://example.org/acme/new-order\",
 			\"revokeCert\": \"https://example.org/acme/revoke-cert\"
 		}";
-		let parsed_dir = Directory::from_str(&data);
+		let parsed_dir = Directory::from_str(data);
 		assert!(parsed_dir.is_ok());
 		let parsed_dir = parsed_dir.unwrap();
 		assert!(parsed_dir.meta.is_some());
 		let meta = parsed_dir.meta.unwrap();
 		assert_eq!(
-			meta.terms_of_service,
-			Some("https://example.org/documents/tos.pdf".to_string())
+			meta.terms_of_service.as_deref(),
+			Some("https://example.org/documents/tos.pdf")
 		);
-		assert_eq!(meta.website, Some("https://example.org/".to_string()));
+		assert_eq!(meta.website.as_deref(), Some("https://example.org/"));
 		assert!(meta.caa_identities.is_some());
 		let caa_identities = meta.caa_identities.unwrap();
 		assert_eq!(caa_identities.len(), 1);
@@ -123,15 +179,15 @@ mod tests {
 			\"newOrder\": \"https://example.org/acme/new-order\",
 			\"revokeCert\": \"https://example.org/acme/revoke-cert\"
 		}";
-		let parsed_dir = Directory::from_str(&data);
+		let parsed_dir = Directory::from_str(data);
 		assert!(parsed_dir.is_ok());
 		let parsed_dir = parsed_dir.unwrap();
 		assert_eq!(parsed_dir.new_nonce, "https://example.org/acme/new-nonce");
 		assert_eq!(parsed_dir.new_account, "https://example.org/acme/new-acct");
 		assert_eq!(parsed_dir.new_order, "https://example.org/acme/new-order");
 		assert_eq!(
-			parsed_dir.new_authz,
-			Some("https://example.org/acme/new-authz".to_string())
+			parsed_dir.new_authz.as_deref(),
+			Some("https://example.org/acme/new-authz")
 		);
 		assert_eq!(
 			parsed_dir.revoke_cert,
@@ -139,5 +195,73 @@ mod tests {
 		);
 		assert_eq!(parsed_dir.key_change, "https://example.org/acme/key-change");
 		assert!(parsed_dir.meta.is_none());
+		assert_eq!(parsed_dir.extra.len(), 2);
+		assert_eq!(parsed_dir.extra.get("foo"), Some(&"bar".into()));
+		assert_eq!(parsed_dir.extra.get("baz"), Some(&"quz".into()));
+	}
+
+	#[test]
+	fn test_directory_extensions() {
+		let data = "{
+			\"keyChange\": \"https://example.org/acme/key-change\",
+			\"meta\": {
+				\"termsOfService\": \"https://example.org/documents/tos.pdf\",
+				\"externalAccountRequired\": true,
+				\"profiles\": {
+					\"classic\": \"The same profile you're accustomed to\",
+					\"tlsserver\": \"https://example.org/docs/tls-server-profile\"
+				},
+				\"x-rate-limits\": {\"orders\": 300}
+			},
+			\"newAccount\": \"https://example.org/acme/new-acct\",
+			\"newNonce\": \"https://example.org/acme/new-nonce\",
+			\"newOrder\": \"https://example.org/acme/new-order\",
+			\"renewalInfo\": \"https://example.org/acme/renewal-info\",
+			\"revokeCert\": \"https://example.org/acme/revoke-cert\"
+		}";
+		let parsed_dir = Directory::from_str(data);
+		assert!(parsed_dir.is_ok());
+		let parsed_dir = parsed_dir.unwrap();
+		assert_eq!(
+			parsed_dir.renewal_info.as_deref(),
+			Some("https://example.org/acme/renewal-info")
+		);
+		assert!(parsed_dir.extra.is_empty());
+		assert!(parsed_dir.requires_external_account());
+		assert!(!parsed_dir.supports_pre_authorization());
+		assert!(parsed_dir.supports_renewal_info());
+		assert_eq!(
+			parsed_dir.terms_of_service(),
+			Some("https://example.org/documents/tos.pdf")
+		);
+		assert!(parsed_dir.supports_profile("tlsserver"));
+		assert!(!parsed_dir.supports_profile("shortlived"));
+		let meta = parsed_dir.meta.unwrap();
+		let profiles = meta.profiles.unwrap();
+		assert_eq!(profiles.len(), 2);
+		assert_eq!(
+			profiles.get("classic"),
+			Some(&"The same profile you're accustomed to".to_string())
+		);
+		assert_eq!(meta.extra.len(), 1);
+		assert_eq!(meta.extra["x-rate-limits"]["orders"], 300);
+	}
+
+	#[test]
+	fn test_directory_helpers_no_meta() {
+		let data = "{
+			\"keyChange\": \"https://example.org/acme/key-change\",
+			\"newAccount\": \"https://example.org/acme/new-acct\",
+			\"newAuthz\": \"https://example.org/acme/new-authz\",
+			\"newNonce\": \"https://example.org/acme/new-nonce\",
+			\"newOrder\": \"https://example.org/acme/new-order\",
+			\"revokeCert\": \"https://example.org/acme/revoke-cert\"
+		}";
+		let parsed_dir = Directory::from_str(data).unwrap();
+		assert!(!parsed_dir.requires_external_account());
+		assert!(parsed_dir.supports_pre_authorization());
+		assert!(!parsed_dir.supports_renewal_info());
+		assert!(parsed_dir.terms_of_service().is_none());
+		assert!(!parsed_dir.supports_profile("classic"));
 	}
 }
@@ -1,17 +1,59 @@
-use serde::Deserialize;
+use crate::structs::identifier::Identifier;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Deserialize)]
+/// Problem document (RFC 7807) returned by the server, see RFC 8555 section
+/// 6.7.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Error {
 	#[serde(rename = "type")]
 	error_type: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	detail: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	subproblems: Option<Vec<Subproblem>>,
 }
 
 deserialize_from_str!(Error, "error");
 
+impl Error {
+	/// Builds a problem document, for instance
+	/// `urn:ietf:params:acme:error:malformed`.
+	pub fn new(error_type: &str, detail: Option<&str>) -> Self {
+		Self {
+			error_type: error_type.to_string(),
+			detail: detail.map(str::to_string),
+			subproblems: None,
+		}
+	}
+
+	/// Adds a subproblem, which may be about a specific identifier.
+	pub fn subproblem(
+		mut self,
+		error_type: &str,
+		detail: Option<&str>,
+		identifier: Option<&Identifier>,
+	) -> Self {
+		self.subproblems
+			.get_or_insert_with(Vec::new)
+			.push(Subproblem {
+				error_type: error_type.to_string(),
+				detail: detail.map(str::to_string),
+				identifier: identifier.cloned(),
+			});
+		self
+	}
+
+	pub fn error_type(&self) -> &str {
+		&self.error_type
+	}
+
+	pub fn detail(&self) -> Option<&str> {
+		self.detail.as_deref()
+	}
+}
+
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		writeln!(
@@ -34,13 +76,15 @@ impl fmt::Display for Error {
 	}
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct Subproblem {
 	#[serde(rename = "type")]
 	error_type: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	detail: Option<String>,
-	identifier: Option<String>, // TODO: replace by the real type
+	#[serde(skip_serializing_if = "Option::is_none")]
+	identifier: Option<Identifier>,
 }
 
 impl fmt::Display for Subproblem {
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdentifierType {
+	Dns,
+	Ip,
+}
+
+impl fmt::Display for IdentifierType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			IdentifierType::Dns => "dns",
+			IdentifierType::Ip => "ip",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Identifier {
+	#[serde(rename = "type")]
+	pub id_type: IdentifierType,
+	pub value: String,
+}
+
+impl Identifier {
+	pub fn dns(value: &str) -> Self {
+		Self {
+			id_type: IdentifierType::Dns,
+			value: value.to_string(),
+		}
+	}
+
+	pub fn ip(value: &str) -> Self {
+		Self {
+			id_type: IdentifierType::Ip,
+			value: value.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for Identifier {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}", self.id_type, self.value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Identifier, IdentifierType};
+
+	#[test]
+	fn deserialize_identifier() {
+		let tests = [
+			(
+				r#"{"type":"dns","value":"example.org"}"#,
+				Some(Identifier::dns("example.org")),
+			),
+			(
+				r#"{"type":"ip","value":"192.0.2.1"}"#,
+				Some(Identifier::ip("192.0.2.1")),
+			),
+			(r#"{"type":"DNS","value":"example.org"}"#, None),
+			(r#"{"type":"email","value":"derp@example.org"}"#, None),
+			(r#"{"value":"example.org"}"#, None),
+		];
+		for (id_str, id) in tests {
+			let res = serde_json::from_str::<Identifier>(id_str);
+			match id {
+				Some(id_ref) => {
+					assert!(res.is_ok(), "deserialization of `{}` failed", id_str);
+					assert_eq!(res.unwrap(), id_ref);
+				}
+				None => {
+					assert!(res.is_err(), "`{}` is incorrectly considered valid", id_str);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn serialize_identifier() {
+		let id = Identifier {
+			id_type: IdentifierType::Dns,
+			value: "example.org".to_string(),
+		};
+		let res = serde_json::to_string(&id);
+		assert!(res.is_ok(), "serialization failed");
+		assert_eq!(res.unwrap(), r#"{"type":"dns","value":"example.org"}"#);
+	}
+}
@@ -1,52 +1,340 @@
-use serde::Serialize;
+use crate::structs::jws::JwsAlgorithm;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::de::{Error as _, IntoDeserializer};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 
-#[derive(Clone, Debug, Serialize)]
-#[serde(untagged)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum JwkType {
+	#[serde(rename = "RSA")]
+	Rsa,
+	#[serde(rename = "EC")]
+	Ec,
+	#[serde(rename = "OKP")]
+	Okp,
+}
+
+impl fmt::Display for JwkType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			JwkType::Rsa => "RSA",
+			JwkType::Ec => "EC",
+			JwkType::Okp => "OKP",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyUse {
+	Sig,
+	Enc,
+}
+
+impl fmt::Display for KeyUse {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			KeyUse::Sig => "sig",
+			KeyUse::Enc => "enc",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+/// JWS algorithms that can be used with an RSA key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum RsaAlgorithm {
+	#[serde(rename = "RS256")]
+	Rs256,
+	#[serde(rename = "RS384")]
+	Rs384,
+	#[serde(rename = "RS512")]
+	Rs512,
+	#[serde(rename = "PS256")]
+	Ps256,
+	#[serde(rename = "PS384")]
+	Ps384,
+	#[serde(rename = "PS512")]
+	Ps512,
+}
+
+impl From<RsaAlgorithm> for JwsAlgorithm {
+	fn from(alg: RsaAlgorithm) -> Self {
+		match alg {
+			RsaAlgorithm::Rs256 => JwsAlgorithm::Rs256,
+			RsaAlgorithm::Rs384 => JwsAlgorithm::Rs384,
+			RsaAlgorithm::Rs512 => JwsAlgorithm::Rs512,
+			RsaAlgorithm::Ps256 => JwsAlgorithm::Ps256,
+			RsaAlgorithm::Ps384 => JwsAlgorithm::Ps384,
+			RsaAlgorithm::Ps512 => JwsAlgorithm::Ps512,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum EcdsaCurve {
+	#[serde(rename = "P-256")]
+	P256,
+	#[serde(rename = "P-384")]
+	P384,
+	#[serde(rename = "P-521")]
+	P521,
+}
+
+impl EcdsaCurve {
+	/// The only JWS algorithm that may be used with this curve.
+	pub fn algorithm(&self) -> JwsAlgorithm {
+		match self {
+			EcdsaCurve::P256 => JwsAlgorithm::Es256,
+			EcdsaCurve::P384 => JwsAlgorithm::Es384,
+			EcdsaCurve::P521 => JwsAlgorithm::Es512,
+		}
+	}
+}
+
+impl fmt::Display for EcdsaCurve {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			EcdsaCurve::P256 => "P-256",
+			EcdsaCurve::P384 => "P-384",
+			EcdsaCurve::P521 => "P-521",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum EddsaCurve {
+	Ed25519,
+	Ed448,
+}
+
+impl fmt::Display for EddsaCurve {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			EddsaCurve::Ed25519 => "Ed25519",
+			EddsaCurve::Ed448 => "Ed448",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+/// Public key in the JWK format (RFC 7517).
+///
+/// The `kty` member is implied by the variant. For elliptic curves, the `alg`
+/// member is implied by the curve and therefore is only either included or
+/// omitted.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Jwk {
 	Rsa {
-		#[serde(skip_serializing_if = "Option::is_none")]
-		alg: Option<String>,
-		kty: String,
-		#[serde(skip_serializing_if = "Option::is_none")]
-		#[serde(rename = "use")]
-		key_use: Option<String>,
+		alg: Option<RsaAlgorithm>,
+		key_use: Option<KeyUse>,
 		e: String,
 		n: String,
 	},
 	Ecdsa {
-		#[serde(skip_serializing_if = "Option::is_none")]
-		alg: Option<String>,
-		crv: String,
-		kty: String,
-		#[serde(skip_serializing_if = "Option::is_none")]
-		#[serde(rename = "use")]
-		key_use: Option<String>,
+		alg: bool,
+		crv: EcdsaCurve,
+		key_use: Option<KeyUse>,
 		x: String,
 		y: String,
 	},
 	Eddsa {
-		#[serde(skip_serializing_if = "Option::is_none")]
-		alg: Option<String>,
-		crv: String,
-		kty: String,
-		#[serde(skip_serializing_if = "Option::is_none")]
-		#[serde(rename = "use")]
-		key_use: Option<String>,
+		alg: bool,
+		crv: EddsaCurve,
+		key_use: Option<KeyUse>,
 		x: String,
 	},
 }
 
+deserialize_from_str!(Jwk, "JWK");
+
+impl Jwk {
+	pub fn key_type(&self) -> JwkType {
+		match self {
+			Jwk::Rsa { .. } => JwkType::Rsa,
+			Jwk::Ecdsa { .. } => JwkType::Ec,
+			Jwk::Eddsa { .. } => JwkType::Okp,
+		}
+	}
+
+	/// JWS algorithm to use with this key. RSA keys without any `alg` member
+	/// default to RS256.
+	pub fn algorithm(&self) -> JwsAlgorithm {
+		match self {
+			Jwk::Rsa { alg, .. } => alg.unwrap_or(RsaAlgorithm::Rs256).into(),
+			Jwk::Ecdsa { crv, .. } => crv.algorithm(),
+			Jwk::Eddsa { .. } => JwsAlgorithm::EdDsa,
+		}
+	}
+
+	/// Computes the RFC 7638 thumbprint of the key using SHA-256.
+	pub fn thumbprint(&self) -> String {
+		// The members are inserted in lexicographic order so the output is
+		// canonical even if serde_json preserves the insertion order.
+		let canonical = match self {
+			Jwk::Rsa { e, n, .. } => serde_json::json!({
+				"e": e,
+				"kty": self.key_type(),
+				"n": n,
+			}),
+			Jwk::Ecdsa { crv, x, y, .. } => serde_json::json!({
+				"crv": crv,
+				"kty": self.key_type(),
+				"x": x,
+				"y": y,
+			}),
+			Jwk::Eddsa { crv, x, .. } => serde_json::json!({
+				"crv": crv,
+				"kty": self.key_type(),
+				"x": x,
+			}),
+		};
+		let digest = crate::crypto::sha256(canonical.to_string().as_bytes());
+		URL_SAFE_NO_PAD.encode(digest)
+	}
+}
+
+impl Serialize for Jwk {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut map = serializer.serialize_map(None)?;
+		match self {
+			Jwk::Rsa { alg, key_use, e, n } => {
+				if let Some(alg) = alg {
+					map.serialize_entry("alg", alg)?;
+				}
+				map.serialize_entry("kty", &self.key_type())?;
+				if let Some(key_use) = key_use {
+					map.serialize_entry("use", key_use)?;
+				}
+				map.serialize_entry("e", e)?;
+				map.serialize_entry("n", n)?;
+			}
+			Jwk::Ecdsa {
+				alg,
+				crv,
+				key_use,
+				x,
+				y,
+			} => {
+				if *alg {
+					map.serialize_entry("alg", &self.algorithm())?;
+				}
+				map.serialize_entry("crv", crv)?;
+				map.serialize_entry("kty", &self.key_type())?;
+				if let Some(key_use) = key_use {
+					map.serialize_entry("use", key_use)?;
+				}
+				map.serialize_entry("x", x)?;
+				map.serialize_entry("y", y)?;
+			}
+			Jwk::Eddsa {
+				alg,
+				crv,
+				key_use,
+				x,
+			} => {
+				if *alg {
+					map.serialize_entry("alg", &self.algorithm())?;
+				}
+				map.serialize_entry("crv", crv)?;
+				map.serialize_entry("kty", &self.key_type())?;
+				if let Some(key_use) = key_use {
+					map.serialize_entry("use", key_use)?;
+				}
+				map.serialize_entry("x", x)?;
+			}
+		}
+		map.end()
+	}
+}
+
+impl<'de> Deserialize<'de> for Jwk {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		struct RawJwk {
+			kty: JwkType,
+			alg: Option<JwsAlgorithm>,
+			#[serde(rename = "use")]
+			key_use: Option<KeyUse>,
+			crv: Option<String>,
+			e: Option<String>,
+			n: Option<String>,
+			x: Option<String>,
+			y: Option<String>,
+		}
+
+		let raw = RawJwk::deserialize(deserializer)?;
+		let member =
+			|value: Option<String>, name| value.ok_or_else(|| D::Error::missing_field(name));
+		let invalid_alg = |alg: JwsAlgorithm| {
+			D::Error::custom(format!("{}: invalid algorithm for a {} key", alg, raw.kty))
+		};
+		let jwk = match raw.kty {
+			JwkType::Rsa => {
+				let alg = match raw.alg {
+					Some(JwsAlgorithm::Rs256) => Some(RsaAlgorithm::Rs256),
+					Some(JwsAlgorithm::Rs384) => Some(RsaAlgorithm::Rs384),
+					Some(JwsAlgorithm::Rs512) => Some(RsaAlgorithm::Rs512),
+					Some(JwsAlgorithm::Ps256) => Some(RsaAlgorithm::Ps256),
+					Some(JwsAlgorithm::Ps384) => Some(RsaAlgorithm::Ps384),
+					Some(JwsAlgorithm::Ps512) => Some(RsaAlgorithm::Ps512),
+					Some(alg) => return Err(invalid_alg(alg)),
+					None => None,
+				};
+				Jwk::Rsa {
+					alg,
+					key_use: raw.key_use,
+					e: member(raw.e, "e")?,
+					n: member(raw.n, "n")?,
+				}
+			}
+			JwkType::Ec => {
+				let crv = EcdsaCurve::deserialize(member(raw.crv, "crv")?.into_deserializer())?;
+				match raw.alg {
+					Some(alg) if alg != crv.algorithm() => return Err(invalid_alg(alg)),
+					_ => {}
+				}
+				Jwk::Ecdsa {
+					alg: raw.alg.is_some(),
+					crv,
+					key_use: raw.key_use,
+					x: member(raw.x, "x")?,
+					y: member(raw.y, "y")?,
+				}
+			}
+			JwkType::Okp => {
+				let crv = EddsaCurve::deserialize(member(raw.crv, "crv")?.into_deserializer())?;
+				match raw.alg {
+					Some(alg) if alg != JwsAlgorithm::EdDsa => return Err(invalid_alg(alg)),
+					_ => {}
+				}
+				Jwk::Eddsa {
+					alg: raw.alg.is_some(),
+					crv,
+					key_use: raw.key_use,
+					x: member(raw.x, "x")?,
+				}
+			}
+		};
+		Ok(jwk)
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::Jwk;
+	use super::{EcdsaCurve, EddsaCurve, Jwk, JwkType, KeyUse, RsaAlgorithm};
+	use crate::structs::JwsAlgorithm;
 
 	#[test]
 	fn serialize_rsa() {
 		let ref_s = "{\"alg\":\"RS256\",\"kty\":\"RSA\",\"use\":\"sig\",\"e\":\"AQAB\",\"n\":\"jfjEAtwZv7IdEu2YvS2Y95a-y1tw_HvNFIqtuVc0On6X4UsSSH1QEVOAKgkAVJOEsMWcumnUNe9etOfbCpO9d6E4NEw8tOY2b1bMpGHGZPV-Yu1Ik3QxgvXU7MGg0T2vQMd-migVsNQpLIol47TUWFydpGYoBcFWnogWcaBIy4H9B_2hnyp8Z-zwoG3gEY24FFtfw3Y-tnQB4MY1IPW3dqMnZ2-AUqw8KV9TH0LhnnRhuwL47rXYOXolJG4IgOZeereyCqaTLasZ_EY_ZZ7wYqAsrt7GkwWnziRVNg3uGlQyaHo1pO7V0YAQ2oEerMjpycNPLYGZggm_V7zHqxDHAQ\"}";
 		let jwk = Jwk::Rsa {
-			alg: Some("RS256".to_string()),
-			kty: "RSA".to_string(),
-			key_use: Some("sig".to_string()),
+			alg: Some(RsaAlgorithm::Rs256),
+			key_use: Some(KeyUse::Sig),
 			e: "AQAB".to_string(),
 			n: "jfjEAtwZv7IdEu2YvS2Y95a-y1tw_HvNFIqtuVc0On6X4UsSSH1QEVOAKgkAVJOEsMWcumnUNe9etOfbCpO9d6E4NEw8tOY2b1bMpGHGZPV-Yu1Ik3QxgvXU7MGg0T2vQMd-migVsNQpLIol47TUWFydpGYoBcFWnogWcaBIy4H9B_2hnyp8Z-zwoG3gEY24FFtfw3Y-tnQB4MY1IPW3dqMnZ2-AUqw8KV9TH0LhnnRhuwL47rXYOXolJG4IgOZeereyCqaTLasZ_EY_ZZ7wYqAsrt7GkwWnziRVNg3uGlQyaHo1pO7V0YAQ2oEerMjpycNPLYGZggm_V7zHqxDHAQ".to_string(),
 		};
@@ -61,7 +349,6 @@ mod tests {
 		let ref_s = "{\"kty\":\"RSA\",\"e\":\"AQAB\",\"n\":\"hgUtxyPag2X4YmazM26LZa80J_VcfNqE6VhOPj7UuCh1wkugy5sbVxC9tufjXwmJ0YyJphGGp3VNCg6UqMTQwyXwj-1EtVafmm2UJt5cqfN2sR6fjtxUy2OxQH0XrV2PeLB4nDipJPZtnDt0hKY95FSXOXrtViSdr6aznzQOho1ApTBJsGRN37cRzzZNpECBzDeLKpj6OHAraLZdUTlIByMTMa9JhJmUPlerjGqd665mjeBEnIaP9-CeF9_Pq0-s7HaHq87vx1nfOXiAMGJ4uONL0qU6pe2ZXxLQ7uwynl6EdoKf8bHrah3nn3PUNfxoiJ33oCo81JWiKKkWi2ygpmxn5FuMMun-3vmEG_n2xhTXuFOKrbOX-rM7fALBC-zmgzGe1Cb1eRcoePr-0AgOEeSNJgPUmwqF1dv7WB6XWR7UmRxn1tM6qiAuAeXIDdNbbbkQkhFEvjDVhKwyR9kesgau0KZKonTlDT7k4w-GhsG6Am-iIl61JbzqapA-lBnGqxfxEgsKbJpSsHujKXJnXlT1PM4skuXwlKB9ZHJdPjXhNGSHw7JdeUPXmIez3w6FOGF7ur_ilTNIRvknAQSdFU9LJr2yS6QucTUqo6PSwlM-0D5nIaKaplooLvBTybk9q1miHQnLoOHBhHVOzj3gPCJU75R9fZCgyafhu_a2XD8\"}";
 		let jwk = Jwk::Rsa {
 			alg: None,
-			kty: "RSA".to_string(),
 			key_use: None,
 			e: "AQAB".to_string(),
 			n: "hgUtxyPag2X4YmazM26LZa80J_VcfNqE6VhOPj7UuCh1wkugy5sbVxC9tufjXwmJ0YyJphGGp3VNCg6UqMTQwyXwj-1EtVafmm2UJt5cqfN2sR6fjtxUy2OxQH0XrV2PeLB4nDipJPZtnDt0hKY95FSXOXrtViSdr6aznzQOho1ApTBJsGRN37cRzzZNpECBzDeLKpj6OHAraLZdUTlIByMTMa9JhJmUPlerjGqd665mjeBEnIaP9-CeF9_Pq0-s7HaHq87vx1nfOXiAMGJ4uONL0qU6pe2ZXxLQ7uwynl6EdoKf8bHrah3nn3PUNfxoiJ33oCo81JWiKKkWi2ygpmxn5FuMMun-3vmEG_n2xhTXuFOKrbOX-rM7fALBC-zmgzGe1Cb1eRcoePr-0AgOEeSNJgPUmwqF1dv7WB6XWR7UmRxn1tM6qiAuAeXIDdNbbbkQkhFEvjDVhKwyR9kesgau0KZKonTlDT7k4w-GhsG6Am-iIl61JbzqapA-lBnGqxfxEgsKbJpSsHujKXJnXlT1PM4skuXwlKB9ZHJdPjXhNGSHw7JdeUPXmIez3w6FOGF7ur_ilTNIRvknAQSdFU9LJr2yS6QucTUqo6PSwlM-0D5nIaKaplooLvBTybk9q1miHQnLoOHBhHVOzj3gPCJU75R9fZCgyafhu_a2XD8".to_string(),
@@ -76,10 +363,9 @@ mod tests {
 	fn serialize_ecdsa() {
 		let ref_s = "{\"alg\":\"ES384\",\"crv\":\"P-384\",\"kty\":\"EC\",\"use\":\"sig\",\"x\":\"O6XNy1zaY2CSZrWsuxOlXuK0xxmILGC3tXWRgmEyoimjZx5loc5lsXEXg0WYHTLa\",\"y\":\"4Dv8OtS5tx9x3l4l_fuLjZxj3aBZNF8SibKaQkRWHiDdbVRNYe9jKC3zhfRX3b-S\"}";
 		let jwk = Jwk::Ecdsa {
-			alg: Some("ES384".to_string()),
-			crv: "P-384".to_string(),
-			kty: "EC".to_string(),
-			key_use: Some("sig".to_string()),
+			alg: true,
+			crv: EcdsaCurve::P384,
+			key_use: Some(KeyUse::Sig),
 			x: "O6XNy1zaY2CSZrWsuxOlXuK0xxmILGC3tXWRgmEyoimjZx5loc5lsXEXg0WYHTLa".to_string(),
 			y: "4Dv8OtS5tx9x3l4l_fuLjZxj3aBZNF8SibKaQkRWHiDdbVRNYe9jKC3zhfRX3b-S".to_string(),
 		};
@@ -93,9 +379,8 @@ mod tests {
 	fn serialize_ecdsa_thumbprint() {
 		let ref_s = "{\"crv\":\"P-256\",\"kty\":\"EC\",\"x\":\"ilsljbd6og8KH4DvHSYMv-E8EyQsVbiJY2-nVzoJINc\",\"y\":\"aDiZYC1DiILIub_MNmFtNbDtPbe68FsCnUIRNnDKpcI\"}";
 		let jwk = Jwk::Ecdsa {
-			alg: None,
-			crv: "P-256".to_string(),
-			kty: "EC".to_string(),
+			alg: false,
+			crv: EcdsaCurve::P256,
 			key_use: None,
 			x: "ilsljbd6og8KH4DvHSYMv-E8EyQsVbiJY2-nVzoJINc".to_string(),
 			y: "aDiZYC1DiILIub_MNmFtNbDtPbe68FsCnUIRNnDKpcI".to_string(),
@@ -110,10 +395,9 @@ mod tests {
 	fn serialize_eddsa() {
 		let ref_s = "{\"alg\":\"EdDSA\",\"crv\":\"Ed25519\",\"kty\":\"OKP\",\"use\":\"sig\",\"x\":\"nITdIc8eoqRsy9pHvlhEHwAKku0jA1j0gSR_f6BfyjA\"}";
 		let jwk = Jwk::Eddsa {
-			alg: Some("EdDSA".to_string()),
-			crv: "Ed25519".to_string(),
-			kty: "OKP".to_string(),
-			key_use: Some("sig".to_string()),
+			alg: true,
+			crv: EddsaCurve::Ed25519,
+			key_use: Some(KeyUse::Sig),
 			x: "nITdIc8eoqRsy9pHvlhEHwAKku0jA1j0gSR_f6BfyjA".to_string(),
 		};
 		let res = serde_json::to_string(&jwk);
@@ -126,9 +410,8 @@ mod tests {
 	fn serialize_eddsa_thumbprint() {
 		let ref_s = "{\"crv\":\"Ed448\",\"kty\":\"OKP\",\"x\":\"-4VPMWQCo8Ykyir7omFtQD2Lznko1A3QjA9-wCwjNC49PeUFzBgp5b-GNCMH-0RhffZkx9Ce14kA\"}";
 		let jwk = Jwk::Eddsa {
-			alg: None,
-			crv: "Ed448".to_string(),
-			kty: "OKP".to_string(),
+			alg: false,
+			crv: EddsaCurve::Ed448,
 			key_use: None,
 			x: "-4VPMWQCo8Ykyir7omFtQD2Lznko1A3QjA9-wCwjNC49PeUFzBgp5b-GNCMH-0RhffZkx9Ce14kA"
 				.to_string(),
@@ -138,4 +421,98 @@ mod tests {
 		let s = res.unwrap();
 		assert_eq!(s, ref_s);
 	}
+
+	#[test]
+	fn rfc7638_thumbprint() {
+		let jwk = Jwk::Rsa {
+			alg: Some(RsaAlgorithm::Rs256),
+			key_use: None,
+			e: "AQAB".to_string(),
+			n: "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw".to_string(),
+		};
+		assert_eq!(
+			jwk.thumbprint(),
+			"NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+		);
+	}
+
+	#[test]
+	fn key_type_and_algorithm() {
+		let tests = [
+			(
+				Jwk::Rsa {
+					alg: None,
+					key_use: None,
+					e: "AQAB".to_string(),
+					n: "0vx7".to_string(),
+				},
+				JwkType::Rsa,
+				JwsAlgorithm::Rs256,
+			),
+			(
+				Jwk::Rsa {
+					alg: Some(RsaAlgorithm::Ps384),
+					key_use: None,
+					e: "AQAB".to_string(),
+					n: "0vx7".to_string(),
+				},
+				JwkType::Rsa,
+				JwsAlgorithm::Ps384,
+			),
+			(
+				Jwk::Ecdsa {
+					alg: false,
+					crv: EcdsaCurve::P521,
+					key_use: None,
+					x: "AdHH".to_string(),
+					y: "AUPb".to_string(),
+				},
+				JwkType::Ec,
+				JwsAlgorithm::Es512,
+			),
+			(
+				Jwk::Eddsa {
+					alg: false,
+					crv: EddsaCurve::Ed25519,
+					key_use: None,
+					x: "nITd".to_string(),
+				},
+				JwkType::Okp,
+				JwsAlgorithm::EdDsa,
+			),
+		];
+		for (jwk, kty, alg) in tests {
+			assert_eq!(jwk.key_type(), kty);
+			assert_eq!(jwk.algorithm(), alg);
+		}
+	}
+
+	#[test]
+	fn deserialize() {
+		let valid = [
+			r#"{"alg":"RS256","kty":"RSA","use":"sig","e":"AQAB","n":"0vx7"}"#,
+			r#"{"kty":"RSA","e":"AQAB","n":"0vx7"}"#,
+			r#"{"alg":"ES384","crv":"P-384","kty":"EC","use":"sig","x":"O6XN","y":"4Dv8"}"#,
+			r#"{"crv":"P-256","kty":"EC","x":"ilsl","y":"aDiZ"}"#,
+			r#"{"alg":"EdDSA","crv":"Ed25519","kty":"OKP","use":"sig","x":"nITd"}"#,
+			r#"{"crv":"Ed448","kty":"OKP","x":"-4VP"}"#,
+		];
+		for s in valid {
+			let res = serde_json::from_str::<Jwk>(s);
+			assert!(res.is_ok(), "{}: {:?}", s, res);
+			assert_eq!(serde_json::to_string(&res.unwrap()).unwrap(), s);
+		}
+		let invalid = [
+			r#"{"alg":"ES256","kty":"RSA","e":"AQAB","n":"0vx7"}"#,
+			r#"{"kty":"RSA","e":"AQAB"}"#,
+			r#"{"alg":"ES384","crv":"P-256","kty":"EC","x":"ilsl","y":"aDiZ"}"#,
+			r#"{"crv":"P-192","kty":"EC","x":"ilsl","y":"aDiZ"}"#,
+			r#"{"kty":"EC","x":"ilsl","y":"aDiZ"}"#,
+			r#"{"alg":"ES256","crv":"Ed25519","kty":"OKP","x":"nITd"}"#,
+			r#"{"crv":"Ed25519","kty":"oct","x":"nITd"}"#,
+		];
+		for s in invalid {
+			assert!(serde_json::from_str::<Jwk>(s).is_err(), "{}", s);
+		}
+	}
 }
@@ -1,13 +1,88 @@
-use serde::Serialize;
+#[cfg(any(feature = "crypto_openssl", feature = "crypto_rustcrypto"))]
+use crate::crypto::CryptoBackend;
+use crate::crypto::{b64, b64_decode, DefaultBackend};
+use crate::errors::{DeserializeError, KeyError};
+use crate::signer::{sign_raw, JwsSigner};
+use crate::structs::from_json;
+use crate::structs::jwk::Jwk;
+use crate::structs::url::Url;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum JwsAlgorithm {
+	#[serde(rename = "RS256")]
+	Rs256,
+	#[serde(rename = "RS384")]
+	Rs384,
+	#[serde(rename = "RS512")]
+	Rs512,
+	#[serde(rename = "PS256")]
+	Ps256,
+	#[serde(rename = "PS384")]
+	Ps384,
+	#[serde(rename = "PS512")]
+	Ps512,
+	#[serde(rename = "ES256")]
+	Es256,
+	#[serde(rename = "ES384")]
+	Es384,
+	#[serde(rename = "ES512")]
+	Es512,
+	#[serde(rename = "EdDSA")]
+	EdDsa,
+	#[serde(rename = "HS256")]
+	Hs256,
+	#[serde(rename = "HS384")]
+	Hs384,
+	#[serde(rename = "HS512")]
+	Hs512,
+}
+
+impl fmt::Display for JwsAlgorithm {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			JwsAlgorithm::Rs256 => "RS256",
+			JwsAlgorithm::Rs384 => "RS384",
+			JwsAlgorithm::Rs512 => "RS512",
+			JwsAlgorithm::Ps256 => "PS256",
+			JwsAlgorithm::Ps384 => "PS384",
+			JwsAlgorithm::Ps512 => "PS512",
+			JwsAlgorithm::Es256 => "ES256",
+			JwsAlgorithm::Es384 => "ES384",
+			JwsAlgorithm::Es512 => "ES512",
+			JwsAlgorithm::EdDsa => "EdDSA",
+			JwsAlgorithm::Hs256 => "HS256",
+			JwsAlgorithm::Hs384 => "HS384",
+			JwsAlgorithm::Hs512 => "HS512",
+		};
+		write!(f, "{}", s)
+	}
+}
+
+/// JWS using the flattened JSON serialization (RFC 7515), as required by
+/// RFC 8555 section 6.2.
+///
+/// A deserialized JWS keeps its original encoding, so it is serialized
+/// again as is. Its signature is not verified.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Jws<T: Clone> {
 	protected: JwsProtectedHeader,
 	payload: T,
-	signature: Option<String>,
+	signature: Option<JwsSignature>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct JwsSignature {
+	protected: String,
+	payload: String,
+	signature: String,
 }
 
-impl<T: Clone> Jws<T> {
+impl<T: Clone + Serialize> Jws<T> {
 	pub fn new(protected: &JwsProtectedHeader, payload: &T) -> Self {
 		Self {
 			protected: protected.clone(),
@@ -16,21 +91,272 @@ impl<T: Clone> Jws<T> {
 		}
 	}
 
-	pub fn sign(&mut self) {
-		// TODO
+	/// Signs the JWS using an account key or any other `JwsSigner`.
+	///
+	/// The algorithm of the protected header has to match the signer's.
+	pub async fn sign<S: JwsSigner + ?Sized>(&mut self, signer: &S) -> Result<(), KeyError> {
+		let alg = signer.algorithm();
+		if alg != self.protected.alg {
+			return Err(KeyError::new(format!(
+				"{}: signer algorithm does not match the protected header ({})",
+				alg, self.protected.alg
+			)));
+		}
+		let (protected, payload) = self.encode()?;
+		let signing_input = format!("{}.{}", protected, payload);
+		let signature = sign_raw(signer, signing_input.as_bytes()).await?;
+		self.set_signature(protected, payload, &signature);
+		Ok(())
+	}
+
+	/// Signs the JWS using HMAC-SHA256, as used for external account binding.
+	pub fn sign_hmac(&mut self, key: &[u8]) -> Result<(), KeyError> {
+		if self.protected.alg != JwsAlgorithm::Hs256 {
+			return Err(KeyError::new(format!(
+				"{}: unsupported MAC algorithm",
+				self.protected.alg
+			)));
+		}
+		let (protected, payload) = self.encode()?;
+		let signing_input = format!("{}.{}", protected, payload);
+		let signature = DefaultBackend::hmac_sha256(key, signing_input.as_bytes());
+		self.set_signature(protected, payload, &signature);
+		Ok(())
+	}
+
+	pub fn is_signed(&self) -> bool {
+		self.signature.is_some()
+	}
+
+	pub fn protected(&self) -> &JwsProtectedHeader {
+		&self.protected
+	}
+
+	pub fn payload(&self) -> &T {
+		&self.payload
+	}
+
+	fn encode(&self) -> Result<(String, String), KeyError> {
+		let protected =
+			serde_json::to_vec(&self.protected).map_err(|e| KeyError::new(e.to_string()))?;
+		let payload =
+			serde_json::to_vec(&self.payload).map_err(|e| KeyError::new(e.to_string()))?;
+		Ok((b64(&protected), b64(&payload)))
+	}
+
+	fn set_signature(&mut self, protected: String, payload: String, signature: &[u8]) {
+		self.signature = Some(JwsSignature {
+			protected,
+			payload,
+			signature: b64(signature),
+		});
+	}
+}
+
+impl<T: Clone> Serialize for Jws<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let sig = self
+			.signature
+			.as_ref()
+			.ok_or_else(|| S::Error::custom("the JWS has not been signed"))?;
+		let mut map = serializer.serialize_map(Some(3))?;
+		map.serialize_entry("protected", &sig.protected)?;
+		map.serialize_entry("payload", &sig.payload)?;
+		map.serialize_entry("signature", &sig.signature)?;
+		map.end()
 	}
 }
 
-#[derive(Clone, Debug, Serialize)]
+impl<'de, T: Clone + DeserializeOwned> Deserialize<'de> for Jws<T> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		struct RawJws {
+			protected: String,
+			payload: String,
+			signature: String,
+		}
+
+		let raw = RawJws::deserialize(deserializer)?;
+		let decode = |name: &str, data: &str| {
+			b64_decode(data).map_err(|e| D::Error::custom(format!("{}: {}", name, e.err_msg)))
+		};
+		let protected = serde_json::from_slice(&decode("protected", &raw.protected)?)
+			.map_err(|e| D::Error::custom(format!("protected: {}", e)))?;
+		let payload = serde_json::from_slice(&decode("payload", &raw.payload)?)
+			.map_err(|e| D::Error::custom(format!("payload: {}", e)))?;
+		decode("signature", &raw.signature)?;
+		Ok(Self {
+			protected,
+			payload,
+			signature: Some(JwsSignature {
+				protected: raw.protected,
+				payload: raw.payload,
+				signature: raw.signature,
+			}),
+		})
+	}
+}
+
+impl<T: Clone + DeserializeOwned> FromStr for Jws<T> {
+	type Err = DeserializeError;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		from_json(data, "JWS")
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct JwsProtectedHeader {
-	alg: String,
+	alg: JwsAlgorithm,
 	#[serde(skip_serializing_if = "Option::is_none")]
-	jwk: Option<serde_json::Value>,
+	jwk: Option<Jwk>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	kid: Option<String>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	nonce: Option<String>,
-	url: String,
+	url: Url,
+}
+
+deserialize_from_str!(JwsProtectedHeader, "JWS protected header");
+
+impl JwsProtectedHeader {
+	/// Header embedding the public key, used for account creation and
+	/// revocation requests signed with the certificate key.
+	pub fn new_jwk(jwk: &Jwk, nonce: Option<&str>, url: &Url) -> Self {
+		Self {
+			alg: jwk.algorithm(),
+			jwk: Some(jwk.clone()),
+			kid: None,
+			nonce: nonce.map(str::to_string),
+			url: url.clone(),
+		}
+	}
+
+	/// Header referencing the account URL, used for every other request.
+	pub fn new_kid(alg: JwsAlgorithm, kid: &Url, nonce: Option<&str>, url: &Url) -> Self {
+		Self {
+			alg,
+			jwk: None,
+			kid: Some(kid.to_string()),
+			nonce: nonce.map(str::to_string),
+			url: url.clone(),
+		}
+	}
+
+	/// Header of an external account binding, referencing the key identifier
+	/// given by the CA, which is not a URL. It never carries a nonce.
+	pub fn new_external_account(alg: JwsAlgorithm, key_id: &str, url: &Url) -> Self {
+		Self {
+			alg,
+			jwk: None,
+			kid: Some(key_id.to_string()),
+			nonce: None,
+			url: url.clone(),
+		}
+	}
+
+	pub fn algorithm(&self) -> JwsAlgorithm {
+		self.alg
+	}
 }
 
-impl JwsProtectedHeader {}
+#[cfg(test)]
+mod tests {
+	use super::{Jws, JwsAlgorithm, JwsProtectedHeader};
+	#[cfg(any(feature = "crypto_openssl", feature = "crypto_rustcrypto"))]
+	use crate::crypto::CryptoBackend;
+	use crate::crypto::{b64_decode, DefaultBackend};
+	use crate::key::{test_keys, AccountKey};
+	use crate::structs::Url;
+	use std::str::FromStr;
+
+	fn url(url: &str) -> Url {
+		Url::from_str(url).unwrap()
+	}
+
+	fn split(jws: &str) -> (String, String, String) {
+		let value: serde_json::Value = serde_json::from_str(jws).unwrap();
+		let get = |name: &str| value.get(name).unwrap().as_str().unwrap().to_string();
+		(get("protected"), get("payload"), get("signature"))
+	}
+
+	#[tokio::test]
+	async fn sign_jwk() {
+		for pem in [
+			test_keys::ED25519_PKCS8,
+			test_keys::P256_PKCS8,
+			test_keys::P384_SEC1,
+			test_keys::P521_PKCS8,
+			test_keys::RSA2048_PKCS8,
+		] {
+			let key = AccountKey::from_pem(pem).unwrap();
+			let header = JwsProtectedHeader::new_jwk(
+				&key.jwk(),
+				Some("nonce"),
+				&url("https://example.com/acme/new-account"),
+			);
+			let mut jws = Jws::new(&header, &serde_json::json!({"termsOfServiceAgreed": true}));
+			assert!(!jws.is_signed());
+			assert!(serde_json::to_string(&jws).is_err());
+			let res = jws.sign(&key).await;
+			assert!(res.is_ok(), "{}: {:?}", key.key_type(), res);
+			let (protected, payload, signature) = split(&serde_json::to_string(&jws).unwrap());
+			let header: serde_json::Value =
+				serde_json::from_slice(&b64_decode(&protected).unwrap()).unwrap();
+			assert_eq!(header["alg"], key.algorithm().to_string());
+			assert_eq!(header["jwk"], serde_json::to_value(key.jwk()).unwrap());
+			assert_eq!(header["nonce"], "nonce");
+			assert_eq!(header["url"], "https://example.com/acme/new-account");
+			assert!(header.get("kid").is_none());
+			assert_eq!(payload, "eyJ0ZXJtc09mU2VydmljZUFncmVlZCI6dHJ1ZX0");
+			let signing_input = format!("{}.{}", protected, payload);
+			let res = DefaultBackend::verify(
+				&key.jwk(),
+				signing_input.as_bytes(),
+				&b64_decode(&signature).unwrap(),
+			);
+			assert!(res.is_ok(), "{}: {:?}", key.key_type(), res);
+		}
+	}
+
+	#[tokio::test]
+	async fn sign_algorithm_mismatch() {
+		let key = AccountKey::from_pem(test_keys::P256_PKCS8).unwrap();
+		let header = JwsProtectedHeader::new_kid(
+			JwsAlgorithm::Es384,
+			&url("https://example.com/acme/acct/1"),
+			Some("nonce"),
+			&url("https://example.com/acme/new-order"),
+		);
+		let mut jws = Jws::new(&header, &"payload");
+		assert!(jws.sign(&key).await.is_err());
+		assert!(!jws.is_signed());
+	}
+
+	#[test]
+	fn sign_hmac() {
+		let key = AccountKey::from_pem(test_keys::ED25519_PKCS8).unwrap();
+		let header = JwsProtectedHeader::new_external_account(
+			JwsAlgorithm::Hs256,
+			"kid-1",
+			&url("https://example.com/acme/new-account"),
+		);
+		let mut eab = Jws::new(&header, &key.jwk());
+		assert!(eab.sign_hmac(b"secret key used for testing").is_ok());
+		let (protected, payload, signature) = split(&serde_json::to_string(&eab).unwrap());
+		assert_eq!(
+			protected,
+			"eyJhbGciOiJIUzI1NiIsImtpZCI6ImtpZC0xIiwidXJsIjoiaHR0cHM6Ly9leGFtcGxlLmNvbS9hY21lL25ldy1hY2NvdW50In0"
+		);
+		let signing_input = format!("{}.{}", protected, payload);
+		let expected =
+			DefaultBackend::hmac_sha256(b"secret key used for testing", signing_input.as_bytes());
+		assert_eq!(b64_decode(&signature).unwrap(), expected);
+		let header = JwsProtectedHeader::new_jwk(
+			&key.jwk(),
+			None,
+			&url("https://example.com/acme/new-account"),
+		);
+		assert!(Jws::new(&header, &key.jwk()).sign_hmac(b"key").is_err());
+	}
+}
@@ -0,0 +1,337 @@
+use crate::errors::{DeserializeError, RequestError};
+use crate::structs::directory::Directory;
+use crate::structs::error::Error;
+use crate::structs::from_json_slice;
+use crate::structs::identifier::Identifier;
+use crate::structs::renewal_info::CertificateId;
+use crate::structs::timestamp::Timestamp;
+use crate::structs::url::{Url, UrlRef};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+	Pending,
+	Ready,
+	Processing,
+	Valid,
+	Invalid,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Order {
+	pub status: OrderStatus,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub expires: Option<Timestamp>,
+	pub identifiers: Vec<Identifier>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub not_before: Option<Timestamp>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub not_after: Option<Timestamp>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<Error>,
+	pub authorizations: Vec<Url>,
+	pub finalize: Url,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub certificate: Option<Url>,
+	/// ARI identifier of the replaced certificate (RFC 9773).
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub replaces: Option<CertificateId>,
+	/// Certificate profile selected for this order.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub profile: Option<String>,
+}
+
+deserialize_from_str!(Order, "order");
+
+/// List of orders created by an account (RFC 8555 section 7.1.2.1).
+///
+/// Large lists are split in pages linked by `Link` headers with the `next`
+/// relation type, see `AccountOrders`.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct OrdersList {
+	pub orders: Vec<Url>,
+}
+
+deserialize_from_str!(OrdersList, "orders list");
+
+/// Borrowed variant of `OrdersList`, whose URLs point into the parsed
+/// document, for accounts with a large number of orders.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct OrdersListRef<'a> {
+	#[serde(borrow)]
+	pub orders: Vec<UrlRef<'a>>,
+}
+
+impl<'a> OrdersListRef<'a> {
+	pub fn from_slice(data: &'a [u8]) -> Result<Self, DeserializeError> {
+		from_json_slice(data, "orders list")
+	}
+}
+
+/// Payload of a newOrder request (RFC 8555 section 7.4).
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewOrder {
+	identifiers: Vec<Identifier>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	not_before: Option<Timestamp>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	not_after: Option<Timestamp>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	replaces: Option<CertificateId>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	profile: Option<String>,
+}
+
+deserialize_from_str!(NewOrder, "new order");
+
+impl NewOrder {
+	pub fn new(identifiers: &[Identifier]) -> Self {
+		Self {
+			identifiers: identifiers.to_vec(),
+			not_before: None,
+			not_after: None,
+			replaces: None,
+			profile: None,
+		}
+	}
+
+	/// Requested start of the validity period, either a `Timestamp` or a
+	/// `SystemTime`.
+	pub fn not_before<T: Into<Timestamp>>(mut self, not_before: T) -> Self {
+		self.not_before = Some(not_before.into());
+		self
+	}
+
+	/// Requested end of the validity period, either a `Timestamp` or a
+	/// `SystemTime`.
+	pub fn not_after<T: Into<Timestamp>>(mut self, not_after: T) -> Self {
+		self.not_after = Some(not_after.into());
+		self
+	}
+
+	/// Certificate replaced by the one requested, as defined by RFC 9773
+	/// section 5.
+	pub fn replaces(mut self, certificate: &CertificateId) -> Self {
+		self.replaces = Some(certificate.clone());
+		self
+	}
+
+	/// Certificate profile, which must be advertised by the directory.
+	pub fn profile(mut self, profile: &str) -> Self {
+		self.profile = Some(profile.to_string());
+		self
+	}
+
+	pub fn identifiers(&self) -> &[Identifier] {
+		&self.identifiers
+	}
+
+	/// Checks the request against the directory before it is sent.
+	pub fn validate(&self, directory: &Directory) -> Result<(), RequestError> {
+		if self.identifiers.is_empty() {
+			return Err(RequestError::new(
+				"an order requires at least one identifier",
+			));
+		}
+		if let Some(profile) = &self.profile {
+			if !directory.supports_profile(profile) {
+				return Err(RequestError::new(format!(
+					"{}: profile not advertised by the directory",
+					profile
+				)));
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{NewOrder, Order, OrderStatus, OrdersList, OrdersListRef};
+	use crate::structs::{CertificateId, Directory, Identifier, Timestamp};
+	use std::str::FromStr;
+	use std::time::{Duration, SystemTime};
+
+	const DIRECTORY: &str = r#"{
+		"keyChange": "https://example.org/acme/key-change",
+		"meta": {
+			"profiles": {
+				"classic": "The same profile you're accustomed to",
+				"shortlived": "A short-lived certificate profile"
+			}
+		},
+		"newAccount": "https://example.org/acme/new-acct",
+		"newNonce": "https://example.org/acme/new-nonce",
+		"newOrder": "https://example.org/acme/new-order",
+		"revokeCert": "https://example.org/acme/revoke-cert"
+	}"#;
+
+	#[test]
+	fn new_order() {
+		let order = NewOrder::new(&[Identifier::dns("example.org"), Identifier::ip("192.0.2.1")]);
+		assert_eq!(order.identifiers().len(), 2);
+		assert_eq!(
+			serde_json::to_string(&order).unwrap(),
+			r#"{"identifiers":[{"type":"dns","value":"example.org"},{"type":"ip","value":"192.0.2.1"}]}"#
+		);
+	}
+
+	#[test]
+	fn new_order_all_fields() {
+		let replaced = CertificateId::new(&[0x69, 0x88], &[0x00, 0x87, 0x65, 0x43, 0x21]);
+		let not_before = Timestamp::from_str("2025-01-01T00:00:00Z").unwrap();
+		let not_after = SystemTime::UNIX_EPOCH + Duration::from_secs(1_736_294_400);
+		let order = NewOrder::new(&[Identifier::dns("example.org")])
+			.not_before(not_before)
+			.not_after(not_after)
+			.replaces(&replaced);
+		assert_eq!(
+			serde_json::to_string(&order).unwrap(),
+			r#"{"identifiers":[{"type":"dns","value":"example.org"}],"notBefore":"2025-01-01T00:00:00Z","notAfter":"2025-01-08T00:00:00Z","replaces":"aYg.AIdlQyE"}"#
+		);
+	}
+
+	#[test]
+	fn new_order_profile() {
+		let directory = Directory::from_str(DIRECTORY).unwrap();
+		let order = NewOrder::new(&[Identifier::dns("example.org")]).profile("shortlived");
+		assert!(order.validate(&directory).is_ok());
+		assert_eq!(
+			serde_json::to_string(&order).unwrap(),
+			r#"{"identifiers":[{"type":"dns","value":"example.org"}],"profile":"shortlived"}"#
+		);
+		let order = NewOrder::new(&[Identifier::dns("example.org")]).profile("tlsserver");
+		assert!(order.validate(&directory).is_err());
+		let order = NewOrder::new(&[Identifier::dns("example.org")]);
+		assert!(order.validate(&directory).is_ok());
+		assert!(NewOrder::new(&[]).validate(&directory).is_err());
+	}
+
+	#[test]
+	fn new_order_profile_not_supported() {
+		let directory = Directory::from_str(
+			r#"{
+				"keyChange": "https://example.org/acme/key-change",
+				"newAccount": "https://example.org/acme/new-acct",
+				"newNonce": "https://example.org/acme/new-nonce",
+				"newOrder": "https://example.org/acme/new-order",
+				"revokeCert": "https://example.org/acme/revoke-cert"
+			}"#,
+		)
+		.unwrap();
+		let order = NewOrder::new(&[Identifier::dns("example.org")]).profile("classic");
+		assert!(order.validate(&directory).is_err());
+	}
+
+	#[test]
+	fn deserialize_order() {
+		let data = r#"{
+			"status": "valid",
+			"expires": "2016-01-20T14:09:07.99Z",
+			"identifiers": [
+				{ "type": "dns", "value": "www.example.org" },
+				{ "type": "dns", "value": "example.org" }
+			],
+			"notBefore": "2016-01-01T00:00:00Z",
+			"notAfter": "2016-01-08T00:00:00Z",
+			"authorizations": [
+				"https://example.com/acme/authz/PAniVnsZcis",
+				"https://example.com/acme/authz/r4HqLzrSrpI"
+			],
+			"finalize": "https://example.com/acme/order/TOlocE8rfgo/finalize",
+			"certificate": "https://example.com/acme/cert/mAt3xBGaobw",
+			"profile": "classic"
+		}"#;
+		let order = Order::from_str(data);
+		assert!(order.is_ok());
+		let order = order.unwrap();
+		assert_eq!(order.status, OrderStatus::Valid);
+		assert_eq!(order.identifiers.len(), 2);
+		assert_eq!(order.authorizations.len(), 2);
+		assert_eq!(
+			order.finalize,
+			"https://example.com/acme/order/TOlocE8rfgo/finalize"
+		);
+		assert_eq!(
+			order.certificate.as_deref(),
+			Some("https://example.com/acme/cert/mAt3xBGaobw")
+		);
+		assert_eq!(order.profile, Some("classic".to_string()));
+		assert!(order.replaces.is_none());
+		assert!(order.error.is_none());
+	}
+
+	#[test]
+	fn deserialize_order_invalid() {
+		let data = r#"{
+			"status": "invalid",
+			"identifiers": [{ "type": "dns", "value": "example.org" }],
+			"authorizations": ["https://example.com/acme/authz/PAniVnsZcis"],
+			"finalize": "https://example.com/acme/order/TOlocE8rfgo/finalize",
+			"error": {
+				"type": "urn:ietf:params:acme:error:unauthorized",
+				"detail": "Some of the identifiers requested were rejected"
+			},
+			"replaces": "aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE"
+		}"#;
+		let order = Order::from_str(data);
+		assert!(order.is_ok());
+		let order = order.unwrap();
+		assert_eq!(order.status, OrderStatus::Invalid);
+		assert!(order.error.is_some());
+		assert_eq!(
+			order.replaces.unwrap().to_string(),
+			"aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE"
+		);
+		assert!(order.profile.is_none());
+		assert!(Order::from_str(r#"{"status": "pending"}"#).is_err());
+	}
+
+	#[test]
+	fn deserialize_orders_list() {
+		let data = r#"{
+			"orders": [
+				"https://example.com/acme/order/TOlocE8rfgo",
+				"https://example.com/acme/order/4E16bbL5iSw"
+			]
+		}"#;
+		let list = OrdersList::from_str(data);
+		assert!(list.is_ok());
+		let list = list.unwrap();
+		assert_eq!(list.orders.len(), 2);
+		assert_eq!(list.orders[1], "https://example.com/acme/order/4E16bbL5iSw");
+		assert!(OrdersList::from_str(r#"{"orders": "invalid"}"#).is_err());
+	}
+
+	#[test]
+	fn deserialize_orders_list_bytes() {
+		let data = br#"{
+			"orders": [
+				"https://example.com/acme/order/TOlocE8rfgo",
+				"https://example.com/acme/order/4E16bbL5iSw"
+			]
+		}"#;
+		let list = OrdersList::from_slice(data).unwrap();
+		assert_eq!(OrdersList::from_reader(&data[..]).unwrap(), list);
+		let borrowed = OrdersListRef::from_slice(data).unwrap();
+		assert_eq!(borrowed.orders.len(), 2);
+		assert_eq!(
+			borrowed.orders[1],
+			"https://example.com/acme/order/4E16bbL5iSw"
+		);
+		assert_eq!(
+			serde_json::to_string(&borrowed).unwrap(),
+			serde_json::to_string(&list).unwrap()
+		);
+		let err = OrdersList::from_reader(&br#"{"orders": ["order/1"]}"#[..]).unwrap_err();
+		assert_eq!(err.path(), Some("orders[0]"));
+		assert!(err.snippet().is_none());
+		let err = OrdersListRef::from_slice(br#"{"orders": ["order/1"]}"#).unwrap_err();
+		assert_eq!(err.data_type(), "orders list");
+		assert_eq!(err.path(), Some("orders[0]"));
+		assert!(OrdersList::from_slice(b"{\"orders\": [\"\xff\"]}").is_err());
+	}
+}
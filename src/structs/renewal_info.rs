@@ -0,0 +1,345 @@
+use crate::crypto::{b64, b64_decode, random_u64};
+use crate::errors::{DeserializeError, RenewalError};
+use crate::structs::timestamp::Timestamp;
+use crate::structs::url::Url;
+use sec1::der::asn1::AnyRef;
+use sec1::der::{Decode, Reader, SliceReader, Tag, TagNumber, Tagged};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+// DER encoding of the id-ce-authorityKeyIdentifier OID (2.5.29.35).
+const AKI_OID: &[u8] = &[0x55, 0x1d, 0x23];
+
+/// Unique identifier of a certificate, as defined by RFC 9773 section 4.1.
+///
+/// It is used to request renewal information and in the `replaces` field of
+/// new orders.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CertificateId {
+	key_identifier: Vec<u8>,
+	serial: Vec<u8>,
+}
+
+impl CertificateId {
+	/// Builds the identifier from the key identifier of the Authority Key
+	/// Identifier extension and the content octets of the DER encoded serial
+	/// number, including any leading zero.
+	pub fn new(key_identifier: &[u8], serial: &[u8]) -> Self {
+		Self {
+			key_identifier: key_identifier.to_vec(),
+			serial: serial.to_vec(),
+		}
+	}
+
+	/// Extracts the identifier from a DER encoded certificate.
+	pub fn from_der(certificate: &[u8]) -> Result<Self, DeserializeError> {
+		let err = |msg: String| DeserializeError::new("certificate", msg);
+		let (serial, extensions) =
+			parse_certificate(certificate).map_err(|e| err(e.to_string()))?;
+		let key_identifier = extensions
+			.map(find_key_identifier)
+			.transpose()
+			.map_err(|e| err(e.to_string()))?
+			.flatten()
+			.ok_or_else(|| err("no authority key identifier".to_string()))?;
+		Ok(Self::new(key_identifier, serial))
+	}
+
+	/// Extracts the identifier from the first certificate of a PEM encoded
+	/// chain.
+	pub fn from_pem(chain: &str) -> Result<Self, DeserializeError> {
+		let begin = "-----BEGIN CERTIFICATE-----";
+		let end = "-----END CERTIFICATE-----";
+		let err = |msg: String| DeserializeError::new("certificate", msg);
+		let start = chain
+			.find(begin)
+			.ok_or_else(|| err("no PEM certificate found".to_string()))?;
+		let stop = chain[start..]
+			.find(end)
+			.ok_or_else(|| err("unterminated PEM certificate".to_string()))?;
+		let pem = &chain[start..start + stop + end.len()];
+		let (_, der) =
+			sec1::der::pem::decode_vec(pem.as_bytes()).map_err(|e| err(e.to_string()))?;
+		Self::from_der(&der)
+	}
+
+	/// URL of the renewal information resource, given the `renewalInfo` URL
+	/// of the directory.
+	pub fn renewal_info_url(&self, renewal_info: &Url) -> Url {
+		let url = format!("{}/{}", renewal_info.trim_end_matches('/'), self);
+		Url::from_str(&url).expect("appending a path segment keeps the URL valid")
+	}
+}
+
+impl fmt::Display for CertificateId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}.{}", b64(&self.key_identifier), b64(&self.serial))
+	}
+}
+
+impl FromStr for CertificateId {
+	type Err = DeserializeError;
+
+	/// Parses the textual form of the identifier, as used in URLs.
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		let err = |msg: String| DeserializeError::new("certificate identifier", msg);
+		let (key_identifier, serial) = data
+			.split_once('.')
+			.ok_or_else(|| err(format!("{}: missing separator", data)))?;
+		let key_identifier = b64_decode(key_identifier).map_err(|e| err(e.err_msg))?;
+		let serial = b64_decode(serial).map_err(|e| err(e.err_msg))?;
+		if key_identifier.is_empty() || serial.is_empty() {
+			return Err(err(format!("{}: empty component", data)));
+		}
+		Ok(Self::new(&key_identifier, &serial))
+	}
+}
+
+impl Serialize for CertificateId {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+
+impl<'de> Deserialize<'de> for CertificateId {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let data = String::deserialize(deserializer)?;
+		Self::from_str(&data).map_err(|e| D::Error::custom(e.err_msg))
+	}
+}
+
+/// Returns the content of the serial number and of the extensions.
+fn parse_certificate(der: &[u8]) -> sec1::der::Result<(&[u8], Option<&[u8]>)> {
+	let mut reader = SliceReader::new(der)?;
+	let res = reader.sequence(|cert| {
+		let res = cert.sequence(|tbs| {
+			if tbs.peek_tag()? == context_specific(0) {
+				AnyRef::decode(tbs)?;
+			}
+			let serial = AnyRef::decode(tbs)?;
+			serial.tag().assert_eq(Tag::Integer)?;
+			// signature, issuer, validity, subject and subjectPublicKeyInfo
+			for _ in 0..5 {
+				AnyRef::decode(tbs)?;
+			}
+			let mut extensions = None;
+			while !tbs.is_finished() {
+				let field = AnyRef::decode(tbs)?;
+				if field.tag() == context_specific(3) {
+					extensions = Some(field.value());
+				}
+			}
+			Ok((serial.value(), extensions))
+		})?;
+		// signatureAlgorithm and signatureValue
+		AnyRef::decode(cert)?;
+		AnyRef::decode(cert)?;
+		Ok(res)
+	})?;
+	reader.finish(res)
+}
+
+fn find_key_identifier(extensions: &[u8]) -> sec1::der::Result<Option<&[u8]>> {
+	let mut reader = SliceReader::new(extensions)?;
+	let res = reader.sequence(|list| {
+		let mut key_identifier = None;
+		while !list.is_finished() {
+			let (oid, value) = list.sequence(|ext| {
+				let oid = AnyRef::decode(ext)?;
+				oid.tag().assert_eq(Tag::ObjectIdentifier)?;
+				let mut value = AnyRef::decode(ext)?;
+				if value.tag() == Tag::Boolean {
+					value = AnyRef::decode(ext)?;
+				}
+				value.tag().assert_eq(Tag::OctetString)?;
+				Ok((oid.value(), value.value()))
+			})?;
+			if oid == AKI_OID {
+				let mut aki = SliceReader::new(value)?;
+				let id = aki.sequence(|aki| {
+					let mut id = None;
+					while !aki.is_finished() {
+						let field = AnyRef::decode(aki)?;
+						if field.tag() == context_specific_primitive(0) {
+							id = Some(field.value());
+						}
+					}
+					Ok(id)
+				})?;
+				key_identifier = aki.finish(id)?;
+			}
+		}
+		Ok(key_identifier)
+	})?;
+	reader.finish(res)
+}
+
+fn context_specific(number: u8) -> Tag {
+	Tag::ContextSpecific {
+		constructed: true,
+		number: TagNumber::new(number),
+	}
+}
+
+fn context_specific_primitive(number: u8) -> Tag {
+	Tag::ContextSpecific {
+		constructed: false,
+		number: TagNumber::new(number),
+	}
+}
+
+/// ACME Renewal Information (RFC 9773 section 4.2).
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenewalInfo {
+	pub suggested_window: SuggestedWindow,
+	#[serde(rename = "explanationURL", skip_serializing_if = "Option::is_none")]
+	pub explanation_url: Option<Url>,
+}
+
+deserialize_from_str!(RenewalInfo, "renewal information");
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct SuggestedWindow {
+	pub start: Timestamp,
+	pub end: Timestamp,
+}
+
+impl SuggestedWindow {
+	/// Picks an instant uniformly at random within the window, as advised by
+	/// RFC 9773 section 4.2 in order to spread the load on the CA.
+	pub fn random_instant(&self) -> Result<SystemTime, RenewalError> {
+		let start = self.start.system_time();
+		let len = self.end.system_time().duration_since(start).map_err(|_| {
+			RenewalError::new("the end of the suggested window is before its start")
+		})?;
+		let random = random_u64()?;
+		// Whole milliseconds are more than enough here.
+		let len_ms = len.as_millis().max(1) as u64;
+		let offset = std::time::Duration::from_millis(random % len_ms);
+		Ok(start + offset.min(len))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{CertificateId, RenewalInfo};
+	use crate::structs::Url;
+	use crate::time::parse_rfc3339;
+	use std::str::FromStr;
+
+	// Certificate with the key identifier and serial number used in the
+	// example of RFC 9773 section 4.1.
+	const CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBVTCB/KADAgECAgUAh2VDITAKBggqhkjOPQQDAjASMRAwDgYDVQQDDAdUZXN0
+IENBMB4XDTI1MDEwMTAwMDAwMFoXDTI1MDQwMTAwMDAwMFowFjEUMBIGA1UEAwwL
+ZXhhbXBsZS5vcmcwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQS4fSL5EZBkUlO
+rZFrocCHm9It7vsscfErXlhr8CtdFK/aa3Yj80ypOkLVncV1K3dLRXCNEDL7ESg0
+eyNmfc6yozswOTAfBgNVHSMEGDAWgBRpiFtrh0ZAQeGze4R7oK4s3gHI1DAWBgNV
+HREEDzANggtleGFtcGxlLm9yZzAKBggqhkjOPQQDAgNIADBFAiEAhBQ1+c9LqJcj
+zNDEGGVYfVv7A2KFVYyIOYZz6HkHEPwCIBRrft3WMLZi0lw2W9R3m3DbxgKqA3Lw
+wNUlv0TliCkx
+-----END CERTIFICATE-----
+";
+
+	const CERTIFICATE_NO_AKI: &str = "-----BEGIN CERTIFICATE-----
+MIIBFzCBv6ADAgECAgEqMAoGCCqGSM49BAMCMBYxFDASBgNVBAMMC2V4YW1wbGUu
+b3JnMB4XDTI1MDEwMTAwMDAwMFoXDTI1MDQwMTAwMDAwMFowFjEUMBIGA1UEAwwL
+ZXhhbXBsZS5vcmcwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQS4fSL5EZBkUlO
+rZFrocCHm9It7vsscfErXlhr8CtdFK/aa3Yj80ypOkLVncV1K3dLRXCNEDL7ESg0
+eyNmfc6yMAoGCCqGSM49BAMCA0cAMEQCIHMeXF3ZEyoMmwy/MY8oTFb8xlh8Qiy6
+DIZW2ms2PZI7AiAkeIp1MstldrZvcICFhnsNPyXwCu8zCgn0r4OvWDqjfQ==
+-----END CERTIFICATE-----
+";
+
+	#[test]
+	fn certificate_id() {
+		let id = CertificateId::new(
+			&[
+				0x69, 0x88, 0x5b, 0x6b, 0x87, 0x46, 0x40, 0x41, 0xe1, 0xb3, 0x7b, 0x84, 0x7b, 0xa0,
+				0xae, 0x2c, 0xde, 0x01, 0xc8, 0xd4,
+			],
+			&[0x00, 0x87, 0x65, 0x43, 0x21],
+		);
+		assert_eq!(id.to_string(), "aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE");
+		assert_eq!(
+			serde_json::to_string(&id).unwrap(),
+			"\"aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE\""
+		);
+		assert_eq!(
+			id.renewal_info_url(&Url::from_str("https://example.com/acme/renewal-info/").unwrap()),
+			"https://example.com/acme/renewal-info/aYhba4dGQEHhs3uEe6CuLN4ByNQ.AIdlQyE"
+		);
+		let chain = format!("{}{}", CERTIFICATE, CERTIFICATE_NO_AKI);
+		let parsed = CertificateId::from_pem(&chain);
+		assert!(parsed.is_ok(), "{:?}", parsed);
+		assert_eq!(parsed.unwrap(), id);
+	}
+
+	#[test]
+	fn certificate_id_invalid() {
+		assert!(CertificateId::from_pem(CERTIFICATE_NO_AKI).is_err());
+		assert!(CertificateId::from_pem("not a certificate").is_err());
+		assert!(CertificateId::from_der(b"not a certificate").is_err());
+		let (_, mut der) = sec1::der::pem::decode_vec(CERTIFICATE.as_bytes()).unwrap();
+		assert!(CertificateId::from_der(&der[..der.len() - 1]).is_err());
+		der.push(0);
+		assert!(CertificateId::from_der(&der).is_err());
+	}
+
+	#[test]
+	fn renewal_info() {
+		let data = "{
+			\"suggestedWindow\": {
+				\"start\": \"2025-01-02T04:00:00Z\",
+				\"end\": \"2025-01-03T04:00:00Z\"
+			},
+			\"explanationURL\": \"https://acme.example.com/docs/ari\"
+		}";
+		let ri = RenewalInfo::from_str(data);
+		assert!(ri.is_ok());
+		let ri = ri.unwrap();
+		assert_eq!(ri.suggested_window.start, "2025-01-02T04:00:00Z");
+		assert_eq!(ri.suggested_window.end, "2025-01-03T04:00:00Z");
+		assert_eq!(
+			ri.explanation_url.as_deref(),
+			Some("https://acme.example.com/docs/ari")
+		);
+		let start = ri.suggested_window.start.system_time();
+		let end = ri.suggested_window.end.system_time();
+		for _ in 0..100 {
+			let instant = ri.suggested_window.random_instant().unwrap();
+			assert!(instant >= start && instant <= end);
+		}
+	}
+
+	#[test]
+	fn renewal_info_invalid_window() {
+		let data = "{
+			\"suggestedWindow\": {
+				\"start\": \"2025-01-03T04:00:00Z\",
+				\"end\": \"2025-01-02T04:00:00Z\"
+			}
+		}";
+		let ri = RenewalInfo::from_str(data).unwrap();
+		assert!(ri.explanation_url.is_none());
+		assert!(ri.suggested_window.random_instant().is_err());
+		let data =
+			"{\"suggestedWindow\": {\"start\": \"tomorrow\", \"end\": \"2025-01-02T04:00:00Z\"}}";
+		assert!(RenewalInfo::from_str(data).is_err());
+		assert!(RenewalInfo::from_str("{\"explanationURL\": \"https://example.com\"}").is_err());
+	}
+
+	#[test]
+	fn renewal_info_instant_window() {
+		let data = "{\"suggestedWindow\": {\"start\": \"2025-01-02T04:00:00Z\", \"end\": \"2025-01-02T04:00:00Z\"}}";
+		let ri = RenewalInfo::from_str(data).unwrap();
+		assert_eq!(
+			ri.suggested_window.random_instant().unwrap(),
+			parse_rfc3339("2025-01-02T04:00:00Z").unwrap()
+		);
+	}
+}
@@ -0,0 +1,439 @@
+//! Checks that every resource and request type is serialized in a way that
+//! is parsed back to the same value, and serialized again to the same bytes.
+
+use crate::structs::*;
+use proptest::collection::{btree_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+fn check<T>(value: &T) -> Result<(), TestCaseError>
+where
+	T: Debug + FromStr + PartialEq + Serialize,
+	T::Err: Debug,
+{
+	let data = serde_json::to_string(value).unwrap();
+	let parsed = T::from_str(&data);
+	prop_assert!(parsed.is_ok(), "{}: {:?}", data, parsed.err());
+	let parsed = parsed.unwrap();
+	prop_assert_eq!(&parsed, value);
+	prop_assert_eq!(serde_json::to_string(&parsed).unwrap(), data);
+	Ok(())
+}
+
+fn text() -> impl Strategy<Value = String> {
+	"\\PC{0,12}"
+}
+
+fn url() -> impl Strategy<Value = Url> {
+	"https://example\\.org/acme/[a-z0-9/]{1,12}".prop_map(|url| Url::from_str(&url).unwrap())
+}
+
+fn timestamp() -> impl Strategy<Value = Timestamp> {
+	(0..4_102_444_800_u64)
+		.prop_map(|secs| Timestamp::from(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)))
+}
+
+fn b64_text() -> impl Strategy<Value = String> {
+	"[A-Za-z0-9_-]{1,43}"
+}
+
+fn json_value() -> impl Strategy<Value = Value> {
+	prop_oneof![
+		any::<bool>().prop_map(Value::from),
+		any::<i64>().prop_map(Value::from),
+		text().prop_map(Value::from),
+		vec(text(), 0..3).prop_map(Value::from),
+	]
+}
+
+/// Members not defined by the specifications, which cannot collide with the
+/// defined ones.
+fn extra() -> impl Strategy<Value = BTreeMap<String, Value>> {
+	btree_map("x[a-zA-Z]{0,8}", json_value(), 0..3)
+}
+
+fn identifier() -> impl Strategy<Value = Identifier> {
+	prop_oneof![
+		"(\\*\\.)?[a-z0-9-]{1,12}\\.[a-z]{2,6}".prop_map(|v| Identifier::dns(&v)),
+		any::<std::net::IpAddr>().prop_map(|ip| Identifier::ip(&ip.to_string())),
+	]
+}
+
+fn error() -> impl Strategy<Value = Error> {
+	let subproblem = (
+		"[a-zA-Z:]{1,24}",
+		option::of(text()),
+		option::of(identifier()),
+	);
+	("[a-zA-Z:]{1,24}", option::of(text()), vec(subproblem, 0..3)).prop_map(
+		|(error_type, detail, subproblems)| {
+			subproblems.iter().fold(
+				Error::new(&error_type, detail.as_deref()),
+				|error, (error_type, detail, identifier)| {
+					error.subproblem(error_type, detail.as_deref(), identifier.as_ref())
+				},
+			)
+		},
+	)
+}
+
+fn challenge() -> impl Strategy<Value = Challenge> {
+	let challenge_type = prop_oneof![
+		Just(ChallengeType::Http01),
+		Just(ChallengeType::Dns01),
+		Just(ChallengeType::TlsAlpn01),
+		Just(ChallengeType::Unknown),
+	];
+	let status = prop_oneof![
+		Just(ChallengeStatus::Pending),
+		Just(ChallengeStatus::Processing),
+		Just(ChallengeStatus::Valid),
+		Just(ChallengeStatus::Invalid),
+	];
+	(
+		challenge_type,
+		url(),
+		status,
+		option::of(timestamp()),
+		option::of(error()),
+		option::of(b64_text()),
+	)
+		.prop_map(
+			|(challenge_type, url, status, validated, error, token)| Challenge {
+				challenge_type,
+				url,
+				status,
+				validated,
+				error,
+				token,
+			},
+		)
+}
+
+fn authorization() -> impl Strategy<Value = Authorization> {
+	let status = prop_oneof![
+		Just(AuthorizationStatus::Pending),
+		Just(AuthorizationStatus::Valid),
+		Just(AuthorizationStatus::Invalid),
+		Just(AuthorizationStatus::Deactivated),
+		Just(AuthorizationStatus::Expired),
+		Just(AuthorizationStatus::Revoked),
+	];
+	(
+		identifier(),
+		status,
+		option::of(timestamp()),
+		vec(challenge(), 0..4),
+		option::of(any::<bool>()),
+	)
+		.prop_map(
+			|(identifier, status, expires, challenges, wildcard)| Authorization {
+				identifier,
+				status,
+				expires,
+				challenges,
+				wildcard,
+			},
+		)
+}
+
+fn order() -> impl Strategy<Value = Order> {
+	let status = prop_oneof![
+		Just(OrderStatus::Pending),
+		Just(OrderStatus::Ready),
+		Just(OrderStatus::Processing),
+		Just(OrderStatus::Valid),
+		Just(OrderStatus::Invalid),
+	];
+	(
+		(status, option::of(timestamp()), vec(identifier(), 1..4)),
+		(option::of(timestamp()), option::of(timestamp())),
+		(
+			option::of(error()),
+			vec(url(), 1..4),
+			url(),
+			option::of(url()),
+		),
+		(option::of(certificate_id()), option::of("[a-z]{1,12}")),
+	)
+		.prop_map(
+			|(
+				(status, expires, identifiers),
+				(not_before, not_after),
+				(error, authorizations, finalize, certificate),
+				(replaces, profile),
+			)| Order {
+				status,
+				expires,
+				identifiers,
+				not_before,
+				not_after,
+				error,
+				authorizations,
+				finalize,
+				certificate,
+				replaces,
+				profile,
+			},
+		)
+}
+
+fn certificate_id() -> impl Strategy<Value = CertificateId> {
+	(vec(any::<u8>(), 1..32), vec(any::<u8>(), 1..20))
+		.prop_map(|(key_identifier, serial)| CertificateId::new(&key_identifier, &serial))
+}
+
+fn new_order() -> impl Strategy<Value = NewOrder> {
+	(
+		vec(identifier(), 1..4),
+		option::of(timestamp()),
+		option::of(timestamp()),
+		option::of(certificate_id()),
+		option::of("[a-z]{1,12}"),
+	)
+		.prop_map(|(identifiers, not_before, not_after, replaces, profile)| {
+			let mut new_order = NewOrder::new(&identifiers);
+			if let Some(not_before) = not_before {
+				new_order = new_order.not_before(not_before);
+			}
+			if let Some(not_after) = not_after {
+				new_order = new_order.not_after(not_after);
+			}
+			if let Some(replaces) = replaces {
+				new_order = new_order.replaces(&replaces);
+			}
+			if let Some(profile) = profile {
+				new_order = new_order.profile(&profile);
+			}
+			new_order
+		})
+}
+
+fn directory() -> impl Strategy<Value = Directory> {
+	let meta = (
+		(option::of(url()), option::of(url())),
+		(
+			option::of(vec("[a-z.]{1,12}", 0..3)),
+			option::of(any::<bool>()),
+		),
+		(option::of(btree_map("[a-z]{1,8}", text(), 0..3)), extra()),
+	)
+		.prop_map(
+			|(
+				(terms_of_service, website),
+				(caa_identities, external_account_required),
+				(profiles, extra),
+			)| DirectoryMeta {
+				terms_of_service,
+				website,
+				caa_identities,
+				external_account_required,
+				profiles,
+				extra,
+			},
+		);
+	(
+		(url(), url(), url(), option::of(url())),
+		(url(), url(), option::of(url())),
+		(option::of(meta), extra()),
+	)
+		.prop_map(
+			|(
+				(new_nonce, new_account, new_order, new_authz),
+				(revoke_cert, key_change, renewal_info),
+				(meta, extra),
+			)| Directory {
+				new_nonce,
+				new_account,
+				new_order,
+				new_authz,
+				revoke_cert,
+				key_change,
+				renewal_info,
+				meta,
+				extra,
+			},
+		)
+}
+
+fn jwk() -> impl Strategy<Value = Jwk> {
+	let key_use = option::of(prop_oneof![Just(KeyUse::Sig), Just(KeyUse::Enc)]);
+	let rsa_alg = option::of(prop_oneof![
+		Just(RsaAlgorithm::Rs256),
+		Just(RsaAlgorithm::Rs384),
+		Just(RsaAlgorithm::Rs512),
+		Just(RsaAlgorithm::Ps256),
+		Just(RsaAlgorithm::Ps384),
+		Just(RsaAlgorithm::Ps512),
+	]);
+	let ecdsa_crv = prop_oneof![
+		Just(EcdsaCurve::P256),
+		Just(EcdsaCurve::P384),
+		Just(EcdsaCurve::P521),
+	];
+	let eddsa_crv = prop_oneof![Just(EddsaCurve::Ed25519), Just(EddsaCurve::Ed448)];
+	prop_oneof![
+		(rsa_alg, key_use.clone(), b64_text(), b64_text())
+			.prop_map(|(alg, key_use, e, n)| Jwk::Rsa { alg, key_use, e, n }),
+		(
+			any::<bool>(),
+			ecdsa_crv,
+			key_use.clone(),
+			b64_text(),
+			b64_text()
+		)
+			.prop_map(|(alg, crv, key_use, x, y)| Jwk::Ecdsa {
+				alg,
+				crv,
+				key_use,
+				x,
+				y
+			}),
+		(any::<bool>(), eddsa_crv, key_use, b64_text()).prop_map(|(alg, crv, key_use, x)| {
+			Jwk::Eddsa {
+				alg,
+				crv,
+				key_use,
+				x,
+			}
+		}),
+	]
+}
+
+fn protected_header() -> impl Strategy<Value = JwsProtectedHeader> {
+	prop_oneof![
+		(jwk(), option::of(b64_text()), url()).prop_map(|(jwk, nonce, url)| {
+			JwsProtectedHeader::new_jwk(&jwk, nonce.as_deref(), &url)
+		}),
+		(url(), option::of(b64_text()), url()).prop_map(|(kid, nonce, url)| {
+			JwsProtectedHeader::new_kid(JwsAlgorithm::Es256, &kid, nonce.as_deref(), &url)
+		}),
+	]
+}
+
+/// External account binding, signed with a random MAC key.
+fn external_account_binding() -> impl Strategy<Value = Jws<Jwk>> {
+	(b64_text(), url(), jwk(), vec(any::<u8>(), 16..32)).prop_map(|(kid, url, jwk, key)| {
+		let header = JwsProtectedHeader::new_external_account(JwsAlgorithm::Hs256, &kid, &url);
+		let mut jws = Jws::new(&header, &jwk);
+		jws.sign_hmac(&key).unwrap();
+		jws
+	})
+}
+
+fn account_resource() -> impl Strategy<Value = AccountResource> {
+	let status = prop_oneof![
+		Just(AccountStatus::Valid),
+		Just(AccountStatus::Deactivated),
+		Just(AccountStatus::Revoked),
+	];
+	let binding = external_account_binding().prop_map(|jws| serde_json::to_value(jws).unwrap());
+	(
+		status,
+		option::of(vec(
+			"mailto:[a-z]{1,8}@example\\.org".prop_map(|c| Url::from_str(&c).unwrap()),
+			0..3,
+		)),
+		option::of(any::<bool>()),
+		option::of(binding),
+		option::of(url()),
+	)
+		.prop_map(
+			|(status, contact, terms_of_service_agreed, external_account_binding, orders)| {
+				AccountResource {
+					status,
+					contact,
+					terms_of_service_agreed,
+					external_account_binding,
+					orders,
+				}
+			},
+		)
+}
+
+fn account_management() -> impl Strategy<Value = AccountManagement> {
+	let contacts = vec("mailto:[a-z]{1,8}@example\\.org", 0..3);
+	prop_oneof![
+		(contacts.clone(), any::<bool>())
+			.prop_map(|(c, tos)| AccountManagement::creation_request(&c, tos)),
+		(contacts.clone(), any::<bool>(), external_account_binding())
+			.prop_map(|(c, tos, eab)| AccountManagement::external_creation_request(&c, tos, &eab)),
+		contacts.prop_map(|c| AccountManagement::contact_update_request(&c)),
+		Just(AccountManagement::lookup_request()),
+		Just(AccountManagement::deactivation_request()),
+	]
+}
+
+proptest! {
+	#[test]
+	fn resources(
+		directory in directory(),
+		account in account_resource(),
+		order in order(),
+		orders in vec(url(), 0..4),
+		authorization in authorization(),
+		challenge in challenge(),
+		error in error(),
+	) {
+		// Accounts without any orders URL are only parsed if allowed by the
+		// default quirks.
+		if account.orders.is_some() || cfg!(feature = "opt_account_orders") {
+			check(&account)?;
+		}
+		check(&directory)?;
+		check(&order)?;
+		check(&OrdersList { orders })?;
+		check(&authorization)?;
+		check(&challenge)?;
+		check(&error)?;
+	}
+
+	#[test]
+	fn renewal_info(
+		start in timestamp(),
+		end in timestamp(),
+		explanation_url in option::of(url()),
+		certificate_id in certificate_id(),
+	) {
+		check(&RenewalInfo {
+			suggested_window: SuggestedWindow { start, end },
+			explanation_url,
+		})?;
+		let data = serde_json::to_string(&certificate_id).unwrap();
+		prop_assert_eq!(serde_json::from_str::<CertificateId>(&data).unwrap(), certificate_id);
+	}
+
+	#[test]
+	fn requests(
+		new_order in new_order(),
+		identifier in identifier(),
+		account in account_management(),
+		account_url in url(),
+		old_key in jwk(),
+	) {
+		check(&new_order)?;
+		check(&NewAuthorization::new(&identifier))?;
+		check(&account)?;
+		check(&AccountKeyRollover {
+			account: account_url,
+			old_key,
+		})?;
+	}
+
+	#[test]
+	fn jose(
+		jwk in jwk(),
+		header in protected_header(),
+		jws in external_account_binding(),
+	) {
+		check(&jwk)?;
+		check(&header)?;
+		check(&jws)?;
+	}
+}
@@ -0,0 +1,177 @@
+use crate::errors::DeserializeError;
+use crate::time::{format_rfc3339, parse_rfc3339};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// RFC 3339 date-time, as used by ACME for expiration and validity dates.
+///
+/// The date-time is checked when parsed and kept as sent by the server, so
+/// that serializing it gives back the same string. Timestamps are ordered by
+/// the instant they represent. With the `time` feature, they convert to and
+/// from `time::OffsetDateTime`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Timestamp {
+	raw: String,
+	time: SystemTime,
+}
+
+impl Timestamp {
+	pub fn as_str(&self) -> &str {
+		&self.raw
+	}
+
+	pub fn system_time(&self) -> SystemTime {
+		self.time
+	}
+
+	#[cfg(feature = "time")]
+	pub fn to_offset_date_time(&self) -> ::time::OffsetDateTime {
+		self.time.into()
+	}
+
+	fn parse(raw: &str) -> Option<Self> {
+		parse_rfc3339(raw).map(|time| Self {
+			raw: raw.to_string(),
+			time,
+		})
+	}
+}
+
+impl From<SystemTime> for Timestamp {
+	/// Formats the instant in UTC, without fractional seconds.
+	fn from(time: SystemTime) -> Self {
+		let raw = format_rfc3339(time);
+		let time = parse_rfc3339(&raw).unwrap_or(time);
+		Self { raw, time }
+	}
+}
+
+#[cfg(feature = "time")]
+impl From<::time::OffsetDateTime> for Timestamp {
+	fn from(time: ::time::OffsetDateTime) -> Self {
+		SystemTime::from(time).into()
+	}
+}
+
+#[cfg(feature = "time")]
+impl From<Timestamp> for ::time::OffsetDateTime {
+	fn from(timestamp: Timestamp) -> Self {
+		timestamp.to_offset_date_time()
+	}
+}
+
+impl FromStr for Timestamp {
+	type Err = DeserializeError;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		Self::parse(data).ok_or_else(|| {
+			DeserializeError::new("timestamp", format!("{}: not a RFC 3339 date-time", data))
+		})
+	}
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let data = String::deserialize(deserializer)?;
+		Self::parse(&data).ok_or_else(|| {
+			serde::de::Error::custom(format!(
+				"invalid timestamp `{}`: not a RFC 3339 date-time",
+				data
+			))
+		})
+	}
+}
+
+impl Serialize for Timestamp {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(&self.raw)
+	}
+}
+
+impl fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.raw)
+	}
+}
+
+impl PartialEq<str> for Timestamp {
+	fn eq(&self, other: &str) -> bool {
+		self.raw == other
+	}
+}
+
+impl PartialEq<&str> for Timestamp {
+	fn eq(&self, other: &&str) -> bool {
+		self.raw == *other
+	}
+}
+
+impl PartialOrd for Timestamp {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Timestamp {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.time
+			.cmp(&other.time)
+			.then_with(|| self.raw.cmp(&other.raw))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Timestamp;
+	use std::str::FromStr;
+	use std::time::{Duration, SystemTime};
+
+	#[test]
+	fn parse() {
+		let ts = Timestamp::from_str("2016-01-20T14:09:07.99Z").unwrap();
+		assert_eq!(ts, "2016-01-20T14:09:07.99Z");
+		assert_eq!(
+			ts.system_time(),
+			SystemTime::UNIX_EPOCH + Duration::new(1_453_298_947, 990_000_000)
+		);
+		assert_eq!(
+			serde_json::to_string(&ts).unwrap(),
+			"\"2016-01-20T14:09:07.99Z\""
+		);
+		let offset = Timestamp::from_str("2016-01-20T16:09:07.99+02:00").unwrap();
+		assert_ne!(ts, offset);
+		assert_eq!(ts.system_time(), offset.system_time());
+		assert!(Timestamp::from_str("2016-01-20T14:09:08Z").unwrap() > offset);
+		assert!(Timestamp::from_str("2016-01-20 14:09").is_err());
+		let err = serde_json::from_str::<Timestamp>("\"tomorrow\"").unwrap_err();
+		assert!(err
+			.to_string()
+			.starts_with("invalid timestamp `tomorrow`: not a RFC 3339 date-time"));
+	}
+
+	#[test]
+	fn from_system_time() {
+		let time = SystemTime::UNIX_EPOCH + Duration::new(1_709_208_000, 500);
+		let ts = Timestamp::from(time);
+		assert_eq!(ts.as_str(), "2024-02-29T12:00:00Z");
+		assert_eq!(ts.system_time(), time - Duration::from_nanos(500));
+	}
+
+	#[cfg(feature = "time")]
+	#[test]
+	fn offset_date_time() {
+		let ts = Timestamp::from_str("2024-02-29T14:00:00+02:00").unwrap();
+		let time = ts.to_offset_date_time();
+		assert_eq!(time.unix_timestamp(), 1_709_208_000);
+		assert_eq!(Timestamp::from(time).as_str(), "2024-02-29T12:00:00Z");
+	}
+}
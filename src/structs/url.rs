@@ -0,0 +1,334 @@
+use crate::errors::DeserializeError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Absolute URL, as used by ACME to link resources together.
+///
+/// The URL is checked when parsed and kept as sent by the server, so that
+/// serializing it gives back the same string. It dereferences to `str`. With
+/// the `url` feature, it is also parsed into a `url::Url`, see `Url::as_url`.
+#[derive(Clone)]
+pub struct Url {
+	raw: String,
+	#[cfg(feature = "url")]
+	parsed: ::url::Url,
+}
+
+impl Url {
+	pub fn as_str(&self) -> &str {
+		&self.raw
+	}
+
+	#[cfg(feature = "url")]
+	pub fn as_url(&self) -> &::url::Url {
+		&self.parsed
+	}
+
+	fn parse(raw: &str) -> Result<Self, String> {
+		check_url(raw)?;
+		Ok(Self {
+			raw: raw.to_string(),
+			#[cfg(feature = "url")]
+			parsed: ::url::Url::parse(raw).map_err(|e| e.to_string())?,
+		})
+	}
+}
+
+/// Checks the URL is absolute and does not contain any whitespace or control
+/// character, which `url::Url` would otherwise silently percent-encode.
+fn check_url(raw: &str) -> Result<(), String> {
+	if raw.chars().any(|c| c.is_whitespace() || c.is_control()) {
+		return Err("invalid character".to_string());
+	}
+	let (scheme, rest) = raw
+		.split_once(':')
+		.ok_or_else(|| "relative URL without a base".to_string())?;
+	let mut chars = scheme.chars();
+	if !chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+		|| !chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+	{
+		return Err("relative URL without a base".to_string());
+	}
+	if let Some(rest) = rest.strip_prefix("//") {
+		let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+		let host = authority.rsplit('@').next().unwrap_or_default();
+		if host.is_empty() || host.starts_with(':') {
+			return Err("empty host".to_string());
+		}
+	} else if rest.is_empty() {
+		return Err("empty URL".to_string());
+	}
+	Ok(())
+}
+
+impl FromStr for Url {
+	type Err = DeserializeError;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		Self::parse(data).map_err(|e| DeserializeError::new("URL", format!("{}: {}", data, e)))
+	}
+}
+
+impl<'de> Deserialize<'de> for Url {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let data = String::deserialize(deserializer)?;
+		Self::parse(&data)
+			.map_err(|e| serde::de::Error::custom(format!("invalid URL `{}`: {}", data, e)))
+	}
+}
+
+impl Serialize for Url {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(&self.raw)
+	}
+}
+
+impl Deref for Url {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		&self.raw
+	}
+}
+
+impl AsRef<str> for Url {
+	fn as_ref(&self) -> &str {
+		&self.raw
+	}
+}
+
+impl fmt::Debug for Url {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Url").field(&self.raw).finish()
+	}
+}
+
+impl fmt::Display for Url {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.raw)
+	}
+}
+
+impl PartialEq for Url {
+	fn eq(&self, other: &Self) -> bool {
+		self.raw == other.raw
+	}
+}
+
+impl Eq for Url {}
+
+impl PartialEq<str> for Url {
+	fn eq(&self, other: &str) -> bool {
+		self.raw == other
+	}
+}
+
+impl PartialEq<&str> for Url {
+	fn eq(&self, other: &&str) -> bool {
+		self.raw == *other
+	}
+}
+
+impl PartialEq<String> for Url {
+	fn eq(&self, other: &String) -> bool {
+		self.raw == *other
+	}
+}
+
+impl PartialOrd for Url {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Url {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.raw.cmp(&other.raw)
+	}
+}
+
+impl Hash for Url {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.raw.hash(state);
+	}
+}
+
+#[cfg(feature = "url")]
+impl From<::url::Url> for Url {
+	fn from(url: ::url::Url) -> Self {
+		Self {
+			raw: url.to_string(),
+			parsed: url,
+		}
+	}
+}
+
+#[cfg(feature = "url")]
+impl From<Url> for ::url::Url {
+	fn from(url: Url) -> Self {
+		url.parsed
+	}
+}
+
+/// Borrowed variant of `Url`, pointing into the parsed document unless the
+/// URL contains escaped characters.
+///
+/// Only the checks done on every `Url` are performed, the `url::Url` is not
+/// parsed, see `UrlRef::to_url`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct UrlRef<'a>(Cow<'a, str>);
+
+impl UrlRef<'_> {
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+
+	pub fn to_url(&self) -> Result<Url, DeserializeError> {
+		Url::from_str(&self.0)
+	}
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for UrlRef<'a> {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct Visitor;
+
+		impl<'de> serde::de::Visitor<'de> for Visitor {
+			type Value = Cow<'de, str>;
+
+			fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				write!(f, "a URL")
+			}
+
+			fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+				Ok(Cow::Borrowed(v))
+			}
+
+			fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+				Ok(Cow::Owned(v.to_string()))
+			}
+
+			fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+				Ok(Cow::Owned(v))
+			}
+		}
+
+		let data = deserializer.deserialize_str(Visitor)?;
+		check_url(&data)
+			.map_err(|e| serde::de::Error::custom(format!("invalid URL `{}`: {}", data, e)))?;
+		Ok(Self(data))
+	}
+}
+
+impl Serialize for UrlRef<'_> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(&self.0)
+	}
+}
+
+impl Deref for UrlRef<'_> {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		&self.0
+	}
+}
+
+impl fmt::Display for UrlRef<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+impl PartialEq<&str> for UrlRef<'_> {
+	fn eq(&self, other: &&str) -> bool {
+		self.0 == *other
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Url, UrlRef};
+	use std::borrow::Cow;
+	use std::str::FromStr;
+
+	#[test]
+	fn parse() {
+		for data in [
+			"https://example.org/acme/new-order",
+			"https://example.org",
+			"https://user@example.org:8443/acme?a=b#c",
+			"http://[::1]:14000/dir",
+			"mailto:admin@example.org",
+		] {
+			let url = Url::from_str(data);
+			assert!(url.is_ok(), "{}: {:?}", data, url);
+			let url = url.unwrap();
+			assert_eq!(url, data);
+			assert_eq!(url.to_string(), data);
+			assert_eq!(
+				serde_json::to_string(&url).unwrap(),
+				format!("\"{}\"", data)
+			);
+		}
+		for data in [
+			"",
+			"/acme/new-order",
+			"example.org/acme",
+			"1https://example.org",
+			"https://",
+			"https://:443/acme",
+			"https://example.org/new order",
+		] {
+			assert!(Url::from_str(data).is_err(), "{}", data);
+		}
+	}
+
+	#[test]
+	fn deserialize() {
+		let url: Url = serde_json::from_str("\"https://example.org/acme\"").unwrap();
+		assert_eq!(url.as_str(), "https://example.org/acme");
+		assert!(url.starts_with("https://"));
+		let err = serde_json::from_str::<Url>("\"acme/new-order\"").unwrap_err();
+		assert!(err
+			.to_string()
+			.starts_with("invalid URL `acme/new-order`: relative URL without a base"));
+	}
+
+	#[test]
+	fn borrowed() {
+		let data = r#"["https://example.org/acme", "https:\/\/example.org\/acme"]"#;
+		let urls: Vec<UrlRef> = serde_json::from_str(data).unwrap();
+		assert!(matches!(urls[0].0, Cow::Borrowed(_)));
+		assert!(matches!(urls[1].0, Cow::Owned(_)));
+		assert_eq!(urls[0], urls[1]);
+		assert_eq!(urls[0].to_url().unwrap(), "https://example.org/acme");
+		assert!(serde_json::from_str::<Vec<UrlRef>>(r#"["acme"]"#).is_err());
+	}
+
+	#[cfg(feature = "url")]
+	#[test]
+	fn as_url() {
+		let url = Url::from_str("https://example.org:8443/acme").unwrap();
+		assert_eq!(url.as_url().host_str(), Some("example.org"));
+		assert_eq!(url.as_url().port(), Some(8443));
+		let url = Url::from(::url::Url::parse("https://example.org").unwrap());
+		assert_eq!(url, "https://example.org/");
+	}
+}
@@ -0,0 +1,1867 @@
+use crate::crypto::{b64, b64_decode, CryptoBackend, DefaultBackend};
+use crate::structs::{
+	AccountResource, AccountStatus, Authorization, AuthorizationStatus, Challenge, ChallengeStatus,
+	ChallengeType, Directory, DirectoryMeta, Error, Identifier, IdentifierType, Jwk, JwsAlgorithm,
+	KeyAuthorization, Order, OrderStatus, OrdersList, Timestamp, Url,
+};
+use crate::time::civil_date;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::header::{
+	HeaderName, HeaderValue, CACHE_CONTROL, CONTENT_TYPE, LINK, LOCATION, RETRY_AFTER,
+};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rcgen::{
+	date_time_ymd, BasicConstraints, CertificateParams, CertificateSigningRequestParams, DnType,
+	IsCa, Issuer, KeyPair, KeyUsagePurpose, SanType, SerialNumber,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+const AUTHORIZATION_LIFETIME: Duration = Duration::from_secs(7 * 86_400);
+const CERTIFICATE_LIFETIME: Duration = Duration::from_secs(90 * 86_400);
+const VALIDATION_TIMEOUT: Duration = Duration::from_secs(10);
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Number of most recently issued nonces accepted by the server.
+const MAX_NONCES: usize = 1024;
+
+type SharedState = Arc<Mutex<State>>;
+
+/// Settings of a `TestServer`.
+#[derive(Clone, Debug)]
+pub struct TestServerConfig {
+	auto_validate: bool,
+	bad_nonce_rate: f64,
+	http01_port: Option<u16>,
+	orders_per_page: usize,
+}
+
+impl Default for TestServerConfig {
+	fn default() -> Self {
+		Self {
+			auto_validate: false,
+			bad_nonce_rate: 0.0,
+			http01_port: None,
+			orders_per_page: 10,
+		}
+	}
+}
+
+impl TestServerConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Challenges are considered valid as soon as the client asks for their
+	/// validation, without checking anything.
+	pub fn auto_validate(mut self, auto_validate: bool) -> Self {
+		self.auto_validate = auto_validate;
+		self
+	}
+
+	/// Proportion of signed requests rejected with a `badNonce` error even
+	/// though their nonce is valid, between 0 and 1.
+	pub fn bad_nonce_rate(mut self, rate: f64) -> Self {
+		self.bad_nonce_rate = rate.clamp(0.0, 1.0);
+		self
+	}
+
+	/// Port on which http-01 challenges are validated. Every identifier
+	/// resolves to the loopback address.
+	pub fn http01_port(mut self, port: u16) -> Self {
+		self.http01_port = Some(port);
+		self
+	}
+
+	/// Maximum number of orders per page of the orders lists.
+	pub fn orders_per_page(mut self, orders_per_page: usize) -> Self {
+		self.orders_per_page = orders_per_page.max(1);
+		self
+	}
+}
+
+/// Failure a `TestServer` can be scripted to produce, in order to test how
+/// clients handle the error paths of RFC 8555.
+///
+/// Faults are queued using `TestServer::inject` and each of them only
+/// affects the first request it applies to. Injecting the same fault several
+/// times therefore affects as many requests.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TestServerFault {
+	/// The next signed request is rejected with a `badNonce` error, even if
+	/// its nonce is valid.
+	BadNonce,
+	/// The next signed request is rejected with a `rateLimited` error and a
+	/// `Retry-After` header, in seconds.
+	RateLimited { retry_after: u64 },
+	/// The next finalized order stays in the `processing` state during the
+	/// given number of requests fetching it, which are answered with a
+	/// `Retry-After` header, in seconds.
+	ProcessingOrder { polls: u32, retry_after: u64 },
+	/// The validation of the next challenge fails, which invalidates its
+	/// authorization. The error of the challenge has subproblems.
+	InvalidAuthorization,
+	/// The next downloaded certificate chain only contains the end-entity
+	/// certificate.
+	TruncatedChain,
+}
+
+/// In-process ACME server, meant to test ACME clients without any network
+/// access, like Pebble does.
+///
+/// The server runs on the current Tokio runtime until it is dropped and
+/// keeps everything in memory. It speaks plain HTTP and implements the
+/// directory, nonce, account, order, authorization, challenge and
+/// certificate resources of RFC 8555. Key changes and revocations are not
+/// implemented.
+///
+/// Unless auto-validation is enabled, only http-01 challenges can be
+/// validated, using the port given by `TestServerConfig::http01_port`.
+/// Certificates are issued by a CA generated on startup. Failures can be
+/// injected using `inject`.
+pub struct TestServer {
+	local_addr: SocketAddr,
+	root_certificate: String,
+	state: SharedState,
+	task: JoinHandle<()>,
+}
+
+impl TestServer {
+	/// Starts a server listening on the given address, which should be a
+	/// loopback one.
+	pub async fn bind(addr: SocketAddr, config: TestServerConfig) -> io::Result<Self> {
+		let listener = TcpListener::bind(addr).await?;
+		let local_addr = listener.local_addr()?;
+		let (issuer, root_certificate) =
+			generate_ca().map_err(|e| io::Error::other(e.to_string()))?;
+		let state = State {
+			config,
+			base_url: format!("http://{}", local_addr),
+			issuer,
+			root_certificate: root_certificate.clone(),
+			nonces: Nonces::default(),
+			next_id: 1,
+			accounts: HashMap::new(),
+			orders: HashMap::new(),
+			authorizations: HashMap::new(),
+			challenges: HashMap::new(),
+			certificates: HashMap::new(),
+			faults: Vec::new(),
+		};
+		let state = Arc::new(Mutex::new(state));
+		let task = tokio::spawn(serve(listener, state.clone()));
+		Ok(Self {
+			local_addr,
+			root_certificate,
+			state,
+			task,
+		})
+	}
+
+	/// Address the server is listening on, which is useful when binding to
+	/// port 0.
+	pub fn local_addr(&self) -> SocketAddr {
+		self.local_addr
+	}
+
+	pub fn directory_url(&self) -> String {
+		format!("http://{}/directory", self.local_addr)
+	}
+
+	/// PEM encoded certificate of the CA issuing the certificates.
+	pub fn root_certificate(&self) -> &str {
+		&self.root_certificate
+	}
+
+	/// Queues a failure, which will affect the next request it applies to.
+	pub fn inject(&self, fault: TestServerFault) {
+		self.state
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.faults
+			.push(fault);
+	}
+}
+
+impl Drop for TestServer {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+fn generate_ca() -> Result<(Issuer<'static, KeyPair>, String), rcgen::Error> {
+	let key = KeyPair::generate()?;
+	let mut params = CertificateParams::default();
+	params
+		.distinguished_name
+		.push(DnType::CommonName, "acme-proto test CA");
+	params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+	params.key_usages = vec![
+		KeyUsagePurpose::DigitalSignature,
+		KeyUsagePurpose::KeyCertSign,
+		KeyUsagePurpose::CrlSign,
+	];
+	let cert = params.self_signed(&key)?;
+	Ok((Issuer::new(params, key), cert.pem()))
+}
+
+async fn serve(listener: TcpListener, state: SharedState) {
+	loop {
+		let stream = match listener.accept().await {
+			Ok((stream, _)) => stream,
+			Err(_) => {
+				// Errors such as EMFILE persist for a while, do not spin on them.
+				tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+				continue;
+			}
+		};
+		let state = state.clone();
+		tokio::spawn(async move {
+			let service = service_fn(move |req| handle_request(req, state.clone()));
+			let _ = http1::Builder::new()
+				.serve_connection(TokioIo::new(stream), service)
+				.await;
+		});
+	}
+}
+
+async fn handle_request(
+	req: Request<Incoming>,
+	state: SharedState,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+	let method = req.method().clone();
+	let path = req
+		.uri()
+		.path_and_query()
+		.map(|p| p.as_str().to_string())
+		.unwrap_or_default();
+	let body = match req.into_body().collect().await {
+		Ok(body) => body.to_bytes(),
+		Err(_) => return Ok(Reply::empty(StatusCode::BAD_REQUEST).into_response()),
+	};
+	let reply = {
+		let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+		let reply = match method {
+			Method::GET | Method::HEAD => state.handle_get(&method, &path),
+			Method::POST => state.handle_post(&path, &body),
+			_ => Err(Problem::new(
+				StatusCode::METHOD_NOT_ALLOWED,
+				"malformed",
+				"method not allowed",
+			)),
+		};
+		let index = format!("<{}/directory>;rel=\"index\"", state.base_url);
+		reply
+			.unwrap_or_else(Reply::from)
+			.header(HeaderName::from_static("replay-nonce"), &state.new_nonce())
+			.header(LINK, &index)
+	};
+	let mut reply = reply;
+	if let Some(validation) = reply.validation.take() {
+		tokio::spawn(validate_http01(state, validation));
+	}
+	if method == Method::HEAD {
+		reply.body.clear();
+	}
+	Ok(reply.into_response())
+}
+
+/// Response to a request.
+struct Reply {
+	status: StatusCode,
+	headers: Vec<(HeaderName, String)>,
+	body: Vec<u8>,
+	validation: Option<Http01Validation>,
+}
+
+impl Reply {
+	fn empty(status: StatusCode) -> Self {
+		Self {
+			status,
+			headers: Vec::new(),
+			body: Vec::new(),
+			validation: None,
+		}
+	}
+
+	fn json<T: Serialize>(status: StatusCode, value: &T) -> Self {
+		Self {
+			body: serde_json::to_vec(value).expect("protocol objects always serialize"),
+			..Self::empty(status)
+		}
+		.header(CONTENT_TYPE, "application/json")
+	}
+
+	fn header(mut self, name: HeaderName, value: &str) -> Self {
+		self.headers.push((name, value.to_string()));
+		self
+	}
+
+	fn into_response(self) -> Response<Full<Bytes>> {
+		let mut resp = Response::new(Full::new(Bytes::from(self.body)));
+		*resp.status_mut() = self.status;
+		for (name, value) in self.headers {
+			if let Ok(value) = HeaderValue::from_str(&value) {
+				resp.headers_mut().append(name, value);
+			}
+		}
+		resp
+	}
+}
+
+/// Problem document (RFC 7807) sent with the given HTTP status.
+#[derive(Debug)]
+struct Problem {
+	status: StatusCode,
+	error: Error,
+}
+
+impl Problem {
+	fn new<T: ToString>(status: StatusCode, error_type: &str, detail: T) -> Self {
+		Self {
+			status,
+			error: acme_error(error_type, detail),
+		}
+	}
+
+	fn malformed<T: ToString>(detail: T) -> Self {
+		Self::new(StatusCode::BAD_REQUEST, "malformed", detail)
+	}
+
+	fn unauthorized<T: ToString>(detail: T) -> Self {
+		Self::new(StatusCode::FORBIDDEN, "unauthorized", detail)
+	}
+
+	fn not_found() -> Self {
+		Self::new(StatusCode::NOT_FOUND, "malformed", "resource not found")
+	}
+}
+
+impl From<Problem> for Reply {
+	fn from(problem: Problem) -> Self {
+		let mut reply = Reply::json(problem.status, &problem.error);
+		reply.headers.clear();
+		reply.header(CONTENT_TYPE, "application/problem+json")
+	}
+}
+
+/// Verified JWS sent by a client.
+struct SignedRequest {
+	account: Option<u64>,
+	jwk: Jwk,
+	/// `None` for POST-as-GET requests.
+	payload: Option<Value>,
+}
+
+impl SignedRequest {
+	fn account(&self) -> Result<u64, Problem> {
+		self.account
+			.ok_or_else(|| Problem::malformed("the request must be signed by an account"))
+	}
+
+	fn check_owner(&self, owner: u64) -> Result<(), Problem> {
+		if self.account != Some(owner) {
+			return Err(Problem::unauthorized("resource owned by another account"));
+		}
+		Ok(())
+	}
+}
+
+struct AccountRecord {
+	jwk: Jwk,
+	contact: Vec<Url>,
+	deactivated: bool,
+	orders: Vec<u64>,
+}
+
+struct OrderRecord {
+	account: u64,
+	status: OrderStatus,
+	expires: SystemTime,
+	identifiers: Vec<Identifier>,
+	authorizations: Vec<u64>,
+	certificate: Option<u64>,
+	/// Remaining requests answered while the order is processing.
+	processing_polls: u32,
+	retry_after: u64,
+}
+
+struct AuthorizationRecord {
+	account: u64,
+	identifier: Identifier,
+	wildcard: bool,
+	status: AuthorizationStatus,
+	expires: SystemTime,
+	challenges: Vec<u64>,
+}
+
+struct ChallengeRecord {
+	authorization: u64,
+	challenge_type: ChallengeType,
+	token: String,
+	status: ChallengeStatus,
+	validated: Option<SystemTime>,
+	error: Option<Error>,
+}
+
+/// http-01 validation running in the background.
+struct Http01Validation {
+	challenge: u64,
+	port: u16,
+	host: String,
+	token: String,
+	key_authorization: String,
+}
+
+struct State {
+	config: TestServerConfig,
+	base_url: String,
+	issuer: Issuer<'static, KeyPair>,
+	root_certificate: String,
+	nonces: Nonces,
+	next_id: u64,
+	accounts: HashMap<u64, AccountRecord>,
+	orders: HashMap<u64, OrderRecord>,
+	authorizations: HashMap<u64, AuthorizationRecord>,
+	challenges: HashMap<u64, ChallengeRecord>,
+	certificates: HashMap<u64, (u64, String)>,
+	faults: Vec<TestServerFault>,
+}
+
+impl State {
+	fn handle_get(&mut self, method: &Method, path: &str) -> Result<Reply, Problem> {
+		match path {
+			"/directory" => Ok(Reply::json(StatusCode::OK, &self.directory())),
+			"/new-nonce" if method == Method::HEAD => {
+				Ok(Reply::empty(StatusCode::OK).header(CACHE_CONTROL, "no-store"))
+			}
+			"/new-nonce" => {
+				Ok(Reply::empty(StatusCode::NO_CONTENT).header(CACHE_CONTROL, "no-store"))
+			}
+			_ => Err(Problem::new(
+				StatusCode::METHOD_NOT_ALLOWED,
+				"malformed",
+				"resources have to be fetched using POST-as-GET requests",
+			)),
+		}
+	}
+
+	fn handle_post(&mut self, path: &str, body: &[u8]) -> Result<Reply, Problem> {
+		let url = format!("{}{}", self.base_url, path);
+		let (route, query) = match path.split_once('?') {
+			Some((route, query)) => (route, Some(query)),
+			None => (path, None),
+		};
+		let segments: Vec<&str> = route.trim_start_matches('/').split('/').collect();
+		let is_new_account = segments == ["new-account"];
+		let req = self.verify_jws(&url, body, is_new_account)?;
+		if let Some(TestServerFault::RateLimited { retry_after }) =
+			self.take_fault(|f| matches!(f, TestServerFault::RateLimited { .. }))
+		{
+			let problem = Problem::new(
+				StatusCode::TOO_MANY_REQUESTS,
+				"rateLimited",
+				"too many requests",
+			);
+			return Ok(Reply::from(problem).header(RETRY_AFTER, &retry_after.to_string()));
+		}
+		let id = |s: &str| s.parse::<u64>().map_err(|_| Problem::not_found());
+		let reply = match segments.as_slice() {
+			["new-account"] => self.new_account(req),
+			["new-order"] => self.new_order(req),
+			["new-authz"] => self.new_authorization(req),
+			["account", account] => self.account(id(account)?, req),
+			["account", account, "orders"] => self.orders_list(id(account)?, query, req),
+			["order", order] => self.order(id(order)?, req),
+			["order", order, "finalize"] => self.finalize(id(order)?, req),
+			["authz", authz] => self.authorization(id(authz)?, req),
+			["challenge", challenge] => self.challenge(id(challenge)?, req),
+			["certificate", certificate] => self.certificate(id(certificate)?, req),
+			["key-change"] | ["revoke-cert"] => Err(Problem::new(
+				StatusCode::NOT_IMPLEMENTED,
+				"serverInternal",
+				"not implemented by the test server",
+			)),
+			_ => Err(Problem::not_found()),
+		}?;
+		Ok(reply.header(CACHE_CONTROL, "no-store"))
+	}
+
+	fn verify_jws(
+		&mut self,
+		url: &str,
+		body: &[u8],
+		is_new_account: bool,
+	) -> Result<SignedRequest, Problem> {
+		#[derive(Deserialize)]
+		struct FlattenedJws {
+			protected: String,
+			payload: String,
+			signature: String,
+		}
+
+		#[derive(Deserialize)]
+		struct ProtectedHeader {
+			alg: JwsAlgorithm,
+			jwk: Option<Jwk>,
+			kid: Option<String>,
+			nonce: Option<String>,
+			url: String,
+		}
+
+		let jws: FlattenedJws = serde_json::from_slice(body)
+			.map_err(|e| Problem::malformed(format!("invalid JWS: {}", e)))?;
+		let header: ProtectedHeader = b64_decode(&jws.protected)
+			.map_err(|e| e.to_string())
+			.and_then(|h| serde_json::from_slice(&h).map_err(|e| e.to_string()))
+			.map_err(|e| Problem::malformed(format!("invalid protected header: {}", e)))?;
+		let injected =
+			self.take_fault(|f| *f == TestServerFault::BadNonce)
+				.is_some() || random_ratio() < self.config.bad_nonce_rate;
+		match &header.nonce {
+			Some(nonce) if self.nonces.redeem(nonce) && !injected => {}
+			_ => {
+				return Err(Problem::new(
+					StatusCode::BAD_REQUEST,
+					"badNonce",
+					"missing, invalid or already used nonce",
+				))
+			}
+		}
+		if header.url != url {
+			return Err(Problem::unauthorized(format!(
+				"{}: the URL of the protected header does not match the request",
+				header.url
+			)));
+		}
+		let (account, jwk) = match (header.jwk, header.kid) {
+			(Some(jwk), None) if is_new_account => (None, jwk),
+			(None, Some(kid)) if !is_new_account => {
+				let id = kid
+					.strip_prefix(&format!("{}/account/", self.base_url))
+					.and_then(|id| id.parse::<u64>().ok());
+				let account = id.and_then(|id| self.accounts.get(&id)).ok_or_else(|| {
+					Problem::new(
+						StatusCode::BAD_REQUEST,
+						"accountDoesNotExist",
+						format!("{}: unknown account", kid),
+					)
+				})?;
+				if account.deactivated {
+					return Err(Problem::unauthorized("the account has been deactivated"));
+				}
+				(id, account.jwk.clone())
+			}
+			_ if is_new_account => {
+				return Err(Problem::malformed(
+					"the protected header must contain a jwk",
+				))
+			}
+			_ => {
+				return Err(Problem::malformed(
+					"the protected header must contain a kid",
+				))
+			}
+		};
+		if header.alg != jwk.algorithm() {
+			return Err(Problem::new(
+				StatusCode::BAD_REQUEST,
+				"badSignatureAlgorithm",
+				format!("{}: unsupported algorithm for this key", header.alg),
+			));
+		}
+		let signing_input = format!("{}.{}", jws.protected, jws.payload);
+		b64_decode(&jws.signature)
+			.and_then(|s| DefaultBackend::verify(&jwk, signing_input.as_bytes(), &s))
+			.map_err(|e| Problem::malformed(format!("invalid signature: {}", e)))?;
+		let payload = if jws.payload.is_empty() {
+			None
+		} else {
+			let payload = b64_decode(&jws.payload)
+				.map_err(|e| e.to_string())
+				.and_then(|p| serde_json::from_slice(&p).map_err(|e| e.to_string()))
+				.map_err(|e| Problem::malformed(format!("invalid payload: {}", e)))?;
+			Some(payload)
+		};
+		Ok(SignedRequest {
+			account,
+			jwk,
+			payload,
+		})
+	}
+
+	fn new_nonce(&mut self) -> String {
+		self.nonces.issue()
+	}
+
+	fn new_id(&mut self) -> u64 {
+		self.next_id += 1;
+		self.next_id - 1
+	}
+
+	fn url(&self, resource: &str, id: u64) -> Url {
+		server_url(&format!("{}/{}/{}", self.base_url, resource, id))
+	}
+
+	fn directory(&self) -> Directory {
+		let endpoint = |name: &str| server_url(&format!("{}/{}", self.base_url, name));
+		Directory {
+			new_nonce: endpoint("new-nonce"),
+			new_account: endpoint("new-account"),
+			new_order: endpoint("new-order"),
+			new_authz: Some(endpoint("new-authz")),
+			revoke_cert: endpoint("revoke-cert"),
+			key_change: endpoint("key-change"),
+			renewal_info: None,
+			meta: Some(DirectoryMeta {
+				terms_of_service: None,
+				website: None,
+				caa_identities: None,
+				external_account_required: Some(false),
+				profiles: None,
+				extra: BTreeMap::new(),
+			}),
+			extra: BTreeMap::new(),
+		}
+	}
+
+	fn new_account(&mut self, req: SignedRequest) -> Result<Reply, Problem> {
+		let payload = req
+			.payload
+			.ok_or_else(|| Problem::malformed("missing payload"))?;
+		let thumbprint = req.jwk.thumbprint();
+		let existing = self
+			.accounts
+			.iter()
+			.find(|(_, a)| a.jwk.thumbprint() == thumbprint)
+			.map(|(id, _)| *id);
+		if let Some(id) = existing {
+			return Ok(self.account_reply(id, StatusCode::OK));
+		}
+		if payload["onlyReturnExisting"] == Value::Bool(true) {
+			return Err(Problem::new(
+				StatusCode::BAD_REQUEST,
+				"accountDoesNotExist",
+				"no account exists for this key",
+			));
+		}
+		let contact = contacts(&payload)?.unwrap_or_default();
+		let id = self.new_id();
+		self.accounts.insert(
+			id,
+			AccountRecord {
+				jwk: req.jwk,
+				contact,
+				deactivated: false,
+				orders: Vec::new(),
+			},
+		);
+		Ok(self.account_reply(id, StatusCode::CREATED))
+	}
+
+	fn account(&mut self, id: u64, req: SignedRequest) -> Result<Reply, Problem> {
+		req.check_owner(id)?;
+		if let Some(payload) = &req.payload {
+			let contact = contacts(payload)?;
+			let deactivate = match payload.get("status") {
+				Some(Value::String(status)) if status == "deactivated" => true,
+				Some(_) => return Err(Problem::malformed("invalid account status")),
+				None => false,
+			};
+			let account = self.accounts.get_mut(&id).ok_or_else(Problem::not_found)?;
+			if let Some(contact) = contact {
+				account.contact = contact;
+			}
+			account.deactivated |= deactivate;
+		}
+		Ok(self.account_reply(id, StatusCode::OK))
+	}
+
+	fn account_reply(&self, id: u64, status: StatusCode) -> Reply {
+		let account = &self.accounts[&id];
+		let value = AccountResource {
+			status: match account.deactivated {
+				true => AccountStatus::Deactivated,
+				false => AccountStatus::Valid,
+			},
+			contact: Some(account.contact.clone()),
+			terms_of_service_agreed: None,
+			external_account_binding: None,
+			orders: Some(server_url(&format!("{}/orders", self.url("account", id)))),
+		};
+		Reply::json(status, &value).header(LOCATION, &self.url("account", id))
+	}
+
+	fn orders_list(
+		&mut self,
+		id: u64,
+		query: Option<&str>,
+		req: SignedRequest,
+	) -> Result<Reply, Problem> {
+		req.check_owner(id)?;
+		let cursor = query
+			.and_then(|q| q.strip_prefix("cursor="))
+			.and_then(|c| c.parse::<usize>().ok())
+			.unwrap_or(0);
+		let per_page = self.config.orders_per_page;
+		let account = self.accounts.get(&id).ok_or_else(Problem::not_found)?;
+		let orders = OrdersList {
+			orders: account
+				.orders
+				.iter()
+				.skip(cursor)
+				.take(per_page)
+				.map(|o| self.url("order", *o))
+				.collect(),
+		};
+		let mut reply = Reply::json(StatusCode::OK, &orders);
+		if cursor + per_page < account.orders.len() {
+			let next = format!(
+				"<{}/orders?cursor={}>;rel=\"next\"",
+				self.url("account", id),
+				cursor + per_page
+			);
+			reply = reply.header(LINK, &next);
+		}
+		Ok(reply)
+	}
+
+	fn new_order(&mut self, req: SignedRequest) -> Result<Reply, Problem> {
+		let account = req.account()?;
+		let payload = req
+			.payload
+			.ok_or_else(|| Problem::malformed("missing payload"))?;
+		if payload.get("profile").is_some() {
+			return Err(Problem::new(
+				StatusCode::BAD_REQUEST,
+				"invalidProfile",
+				"certificate profiles are not supported",
+			));
+		}
+		let identifiers: Vec<Identifier> =
+			serde_json::from_value(payload["identifiers"].clone())
+				.map_err(|e| Problem::malformed(format!("invalid identifiers: {}", e)))?;
+		if identifiers.is_empty() {
+			return Err(Problem::malformed("no identifier"));
+		}
+		for identifier in &identifiers {
+			check_identifier(identifier, true)?;
+		}
+		let authorizations = identifiers
+			.iter()
+			.map(|i| self.find_or_create_authorization(account, i))
+			.collect();
+		let id = self.new_id();
+		self.orders.insert(
+			id,
+			OrderRecord {
+				account,
+				status: OrderStatus::Pending,
+				expires: SystemTime::now() + AUTHORIZATION_LIFETIME,
+				identifiers,
+				authorizations,
+				certificate: None,
+				processing_polls: 0,
+				retry_after: 0,
+			},
+		);
+		if let Some(account) = self.accounts.get_mut(&account) {
+			account.orders.push(id);
+		}
+		Ok(self.order_reply(id, StatusCode::CREATED))
+	}
+
+	fn order(&mut self, id: u64, req: SignedRequest) -> Result<Reply, Problem> {
+		let order = self.orders.get_mut(&id).ok_or_else(Problem::not_found)?;
+		req.check_owner(order.account)?;
+		if order.status == OrderStatus::Processing {
+			match order.processing_polls {
+				0 => order.status = OrderStatus::Valid,
+				_ => order.processing_polls -= 1,
+			}
+		}
+		Ok(self.order_reply(id, StatusCode::OK))
+	}
+
+	fn order_reply(&mut self, id: u64, status: StatusCode) -> Reply {
+		self.update_order(id);
+		let order = &self.orders[&id];
+		let value = Order {
+			status: order.status.clone(),
+			expires: Some(Timestamp::from(order.expires)),
+			identifiers: order.identifiers.clone(),
+			not_before: None,
+			not_after: None,
+			error: match order.status {
+				OrderStatus::Invalid => Some(self.order_error(order)),
+				_ => None,
+			},
+			authorizations: order
+				.authorizations
+				.iter()
+				.map(|a| self.url("authz", *a))
+				.collect(),
+			finalize: server_url(&format!("{}/finalize", self.url("order", id))),
+			certificate: match (&order.status, order.certificate) {
+				(OrderStatus::Valid, Some(certificate)) => {
+					Some(self.url("certificate", certificate))
+				}
+				_ => None,
+			},
+			replaces: None,
+			profile: None,
+		};
+		let reply = Reply::json(status, &value).header(LOCATION, &self.url("order", id));
+		match order.status {
+			OrderStatus::Processing => reply.header(RETRY_AFTER, &order.retry_after.to_string()),
+			_ => reply,
+		}
+	}
+
+	/// Error of an invalid order, with a subproblem per invalid
+	/// authorization.
+	fn order_error(&self, order: &OrderRecord) -> Error {
+		order
+			.authorizations
+			.iter()
+			.filter_map(|a| self.authorizations.get(a))
+			.filter(|a| a.status != AuthorizationStatus::Valid)
+			.fold(
+				acme_error("unauthorized", "an authorization is invalid"),
+				|error, a| {
+					error.subproblem(
+						&acme_error_type("unauthorized"),
+						Some("invalid authorization"),
+						Some(&a.identifier),
+					)
+				},
+			)
+	}
+
+	/// Order status derived from its authorizations.
+	fn update_order(&mut self, id: u64) {
+		let order = match self.orders.get(&id) {
+			Some(order) if order.status == OrderStatus::Pending => order,
+			_ => return,
+		};
+		let statuses: Vec<&AuthorizationStatus> = order
+			.authorizations
+			.iter()
+			.filter_map(|a| self.authorizations.get(a))
+			.map(|a| &a.status)
+			.collect();
+		let status = if statuses.iter().all(|s| **s == AuthorizationStatus::Valid) {
+			OrderStatus::Ready
+		} else if statuses
+			.iter()
+			.any(|s| **s != AuthorizationStatus::Valid && **s != AuthorizationStatus::Pending)
+		{
+			OrderStatus::Invalid
+		} else {
+			OrderStatus::Pending
+		};
+		if let Some(order) = self.orders.get_mut(&id) {
+			order.status = status;
+		}
+	}
+
+	fn finalize(&mut self, id: u64, req: SignedRequest) -> Result<Reply, Problem> {
+		let order = self.orders.get(&id).ok_or_else(Problem::not_found)?;
+		req.check_owner(order.account)?;
+		self.update_order(id);
+		let order = &self.orders[&id];
+		if order.status != OrderStatus::Ready {
+			return Err(Problem::new(
+				StatusCode::FORBIDDEN,
+				"orderNotReady",
+				format!("the order is {}", json!(order.status)),
+			));
+		}
+		let csr = req
+			.payload
+			.as_ref()
+			.and_then(|p| p["csr"].as_str())
+			.ok_or_else(|| Problem::malformed("missing CSR"))?;
+		let bad_csr =
+			|e: &dyn ToString| Problem::new(StatusCode::BAD_REQUEST, "badCSR", e.to_string());
+		let der = b64_decode(csr).map_err(|e| bad_csr(&e))?;
+		let mut csr =
+			CertificateSigningRequestParams::from_der(&der.into()).map_err(|e| bad_csr(&e))?;
+		let mut names = Vec::new();
+		for san in &csr.params.subject_alt_names {
+			match san {
+				SanType::DnsName(name) => names.push(Identifier::dns(name.as_str())),
+				SanType::IpAddress(ip) => names.push(Identifier::ip(&ip.to_string())),
+				_ => return Err(bad_csr(&"unsupported subject alternative name")),
+			}
+		}
+		if normalize(&names) != normalize(&order.identifiers) {
+			return Err(bad_csr(
+				&"the CSR does not match the identifiers of the order",
+			));
+		}
+		let now = SystemTime::now();
+		let date = |t: SystemTime| {
+			let (year, month, day) = civil_date(t);
+			date_time_ymd(year as i32, month as u8, day as u8)
+		};
+		let mut serial = [0; 16];
+		DefaultBackend::fill_random(&mut serial).map_err(|e| bad_csr(&e))?;
+		serial[0] = serial[0] & 0x7f | 0x01;
+		csr.params.serial_number = Some(SerialNumber::from_slice(&serial));
+		csr.params.not_before = date(now);
+		csr.params.not_after = date(now + CERTIFICATE_LIFETIME);
+		csr.params.is_ca = IsCa::ExplicitNoCa;
+		csr.params.use_authority_key_identifier_extension = true;
+		let certificate = csr.signed_by(&self.issuer).map_err(|e| {
+			Problem::new(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				"serverInternal",
+				e.to_string(),
+			)
+		})?;
+		let chain = format!("{}{}", certificate.pem(), self.root_certificate);
+		let account = order.account;
+		let cert_id = self.new_id();
+		self.certificates.insert(cert_id, (account, chain));
+		let processing = self.take_fault(|f| matches!(f, TestServerFault::ProcessingOrder { .. }));
+		if let Some(order) = self.orders.get_mut(&id) {
+			order.status = OrderStatus::Valid;
+			order.certificate = Some(cert_id);
+			if let Some(TestServerFault::ProcessingOrder { polls, retry_after }) = processing {
+				order.status = OrderStatus::Processing;
+				order.processing_polls = polls;
+				order.retry_after = retry_after;
+			}
+		}
+		Ok(self.order_reply(id, StatusCode::OK))
+	}
+
+	fn certificate(&mut self, id: u64, req: SignedRequest) -> Result<Reply, Problem> {
+		let (owner, chain) = self.certificates.get(&id).ok_or_else(Problem::not_found)?;
+		req.check_owner(*owner)?;
+		let mut chain = chain.clone();
+		if self
+			.take_fault(|f| *f == TestServerFault::TruncatedChain)
+			.is_some()
+		{
+			let end = "-----END CERTIFICATE-----\n";
+			if let Some(pos) = chain.find(end) {
+				chain.truncate(pos + end.len());
+			}
+		}
+		Ok(Reply {
+			body: chain.into_bytes(),
+			..Reply::empty(StatusCode::OK)
+		}
+		.header(CONTENT_TYPE, "application/pem-certificate-chain"))
+	}
+
+	fn new_authorization(&mut self, req: SignedRequest) -> Result<Reply, Problem> {
+		let account = req.account()?;
+		let payload = req
+			.payload
+			.ok_or_else(|| Problem::malformed("missing payload"))?;
+		let identifier: Identifier = serde_json::from_value(payload["identifier"].clone())
+			.map_err(|e| Problem::malformed(format!("invalid identifier: {}", e)))?;
+		check_identifier(&identifier, false)?;
+		let id = self.create_authorization(account, &identifier);
+		Ok(self
+			.authorization_reply(id, StatusCode::CREATED)
+			.header(LOCATION, &self.url("authz", id)))
+	}
+
+	fn find_or_create_authorization(&mut self, account: u64, identifier: &Identifier) -> u64 {
+		let (value, wildcard) = split_wildcard(identifier);
+		let now = SystemTime::now();
+		let existing = self.authorizations.iter().find(|(_, a)| {
+			a.account == account
+				&& a.identifier.id_type == identifier.id_type
+				&& a.identifier.value.eq_ignore_ascii_case(value)
+				&& a.wildcard == wildcard
+				&& a.expires > now
+				&& matches!(
+					a.status,
+					AuthorizationStatus::Pending | AuthorizationStatus::Valid
+				)
+		});
+		match existing {
+			Some((id, _)) => *id,
+			None => self.create_authorization(account, identifier),
+		}
+	}
+
+	fn create_authorization(&mut self, account: u64, identifier: &Identifier) -> u64 {
+		let (value, wildcard) = split_wildcard(identifier);
+		let challenge_types: &[ChallengeType] = match identifier.id_type {
+			IdentifierType::Dns if wildcard => &[ChallengeType::Dns01],
+			IdentifierType::Dns => &[
+				ChallengeType::Http01,
+				ChallengeType::Dns01,
+				ChallengeType::TlsAlpn01,
+			],
+			IdentifierType::Ip => &[ChallengeType::Http01, ChallengeType::TlsAlpn01],
+		};
+		let id = self.new_id();
+		let mut challenges = Vec::new();
+		for challenge_type in challenge_types {
+			let challenge_id = self.new_id();
+			self.challenges.insert(
+				challenge_id,
+				ChallengeRecord {
+					authorization: id,
+					challenge_type: *challenge_type,
+					token: random_token(),
+					status: ChallengeStatus::Pending,
+					validated: None,
+					error: None,
+				},
+			);
+			challenges.push(challenge_id);
+		}
+		self.authorizations.insert(
+			id,
+			AuthorizationRecord {
+				account,
+				identifier: Identifier {
+					id_type: identifier.id_type.clone(),
+					value: value.to_string(),
+				},
+				wildcard,
+				status: AuthorizationStatus::Pending,
+				expires: SystemTime::now() + AUTHORIZATION_LIFETIME,
+				challenges,
+			},
+		);
+		id
+	}
+
+	fn authorization(&mut self, id: u64, req: SignedRequest) -> Result<Reply, Problem> {
+		let authz = self
+			.authorizations
+			.get_mut(&id)
+			.ok_or_else(Problem::not_found)?;
+		req.check_owner(authz.account)?;
+		if let Some(payload) = &req.payload {
+			match payload.get("status") {
+				Some(Value::String(status)) if status == "deactivated" => {
+					authz.status = AuthorizationStatus::Deactivated;
+				}
+				_ => return Err(Problem::malformed("invalid authorization update")),
+			}
+		}
+		Ok(self.authorization_reply(id, StatusCode::OK))
+	}
+
+	fn authorization_reply(&self, id: u64, status: StatusCode) -> Reply {
+		let authz = &self.authorizations[&id];
+		let value = Authorization {
+			identifier: authz.identifier.clone(),
+			status: authz.status.clone(),
+			expires: Some(Timestamp::from(authz.expires)),
+			challenges: authz
+				.challenges
+				.iter()
+				.map(|c| self.challenge_object(*c))
+				.collect(),
+			wildcard: authz.wildcard.then_some(true),
+		};
+		Reply::json(status, &value)
+	}
+
+	fn challenge(&mut self, id: u64, req: SignedRequest) -> Result<Reply, Problem> {
+		let challenge = self.challenges.get(&id).ok_or_else(Problem::not_found)?;
+		let authz_id = challenge.authorization;
+		let authz = &self.authorizations[&authz_id];
+		req.check_owner(authz.account)?;
+		let mut validation = None;
+		if req.payload.is_some()
+			&& challenge.status == ChallengeStatus::Pending
+			&& authz.status == AuthorizationStatus::Pending
+		{
+			let key_authorization = KeyAuthorization::new(&challenge.token, &req.jwk);
+			let challenge_type = challenge.challenge_type;
+			let token = challenge.token.clone();
+			let identifier = authz.identifier.clone();
+			let injected = self
+				.take_fault(|f| *f == TestServerFault::InvalidAuthorization)
+				.is_some();
+			match (
+				injected,
+				self.config.auto_validate,
+				challenge_type,
+				self.config.http01_port,
+			) {
+				(true, _, _, _) => {
+					let error = acme_error("unauthorized", "the challenge could not be validated")
+						.subproblem(
+							&acme_error_type("dns"),
+							Some("no valid A or AAAA records"),
+							Some(&identifier),
+						)
+						.subproblem(
+							&acme_error_type("connection"),
+							Some("connection refused"),
+							Some(&identifier),
+						);
+					self.set_challenge_result(id, Err(error));
+				}
+				(false, true, _, _) => self.set_challenge_result(id, Ok(())),
+				(false, false, ChallengeType::Http01, Some(port)) => {
+					validation = Some(Http01Validation {
+						challenge: id,
+						port,
+						host: identifier.value,
+						token,
+						key_authorization: key_authorization.to_string(),
+					});
+					if let Some(challenge) = self.challenges.get_mut(&id) {
+						challenge.status = ChallengeStatus::Processing;
+					}
+				}
+				(false, false, challenge_type, _) => {
+					let error = format!(
+						"{} challenges cannot be validated by the test server",
+						challenge_type
+					);
+					self.set_challenge_result(id, Err(incorrect_response(error)));
+				}
+			}
+		}
+		let mut reply = Reply::json(StatusCode::OK, &self.challenge_object(id)).header(
+			LINK,
+			&format!("<{}>;rel=\"up\"", self.url("authz", authz_id)),
+		);
+		reply.validation = validation;
+		Ok(reply)
+	}
+
+	fn challenge_object(&self, id: u64) -> Challenge {
+		let challenge = &self.challenges[&id];
+		Challenge {
+			challenge_type: challenge.challenge_type,
+			url: self.url("challenge", id),
+			status: challenge.status.clone(),
+			validated: challenge.validated.map(Timestamp::from),
+			error: challenge.error.clone(),
+			token: Some(challenge.token.clone()),
+		}
+	}
+
+	fn set_challenge_result(&mut self, id: u64, result: Result<(), Error>) {
+		let challenge = match self.challenges.get_mut(&id) {
+			Some(challenge) => challenge,
+			None => return,
+		};
+		let (challenge_status, authz_status) = match result {
+			Ok(()) => {
+				challenge.validated = Some(SystemTime::now());
+				(ChallengeStatus::Valid, AuthorizationStatus::Valid)
+			}
+			Err(e) => {
+				challenge.error = Some(e);
+				(ChallengeStatus::Invalid, AuthorizationStatus::Invalid)
+			}
+		};
+		challenge.status = challenge_status;
+		if let Some(authz) = self.authorizations.get_mut(&challenge.authorization) {
+			authz.status = authz_status;
+		}
+	}
+
+	/// Removes the first queued fault matching the predicate.
+	fn take_fault<F: Fn(&TestServerFault) -> bool>(
+		&mut self,
+		predicate: F,
+	) -> Option<TestServerFault> {
+		let pos = self.faults.iter().position(predicate)?;
+		Some(self.faults.remove(pos))
+	}
+}
+
+async fn validate_http01(state: SharedState, validation: Http01Validation) {
+	let res = tokio::time::timeout(VALIDATION_TIMEOUT, fetch_http01(&validation))
+		.await
+		.unwrap_or_else(|_| Err("timeout".to_string()))
+		.and_then(|body| {
+			if body.trim_end() == validation.key_authorization {
+				Ok(())
+			} else {
+				Err(format!("{}: invalid key authorization", body))
+			}
+		})
+		.map_err(incorrect_response);
+	state
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+		.set_challenge_result(validation.challenge, res);
+}
+
+async fn fetch_http01(validation: &Http01Validation) -> Result<String, String> {
+	let mut stream = TcpStream::connect(("127.0.0.1", validation.port))
+		.await
+		.map_err(|e| e.to_string())?;
+	let req = format!(
+		"GET /.well-known/acme-challenge/{} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+		validation.token, validation.host
+	);
+	stream
+		.write_all(req.as_bytes())
+		.await
+		.map_err(|e| e.to_string())?;
+	let mut resp = Vec::new();
+	stream
+		.read_to_end(&mut resp)
+		.await
+		.map_err(|e| e.to_string())?;
+	let resp = String::from_utf8_lossy(&resp);
+	let (head, body) = resp
+		.split_once("\r\n\r\n")
+		.ok_or_else(|| "invalid HTTP response".to_string())?;
+	let status = head.lines().next().unwrap_or_default();
+	if status.split_whitespace().nth(1) != Some("200") {
+		return Err(format!("{}: unexpected HTTP status", status));
+	}
+	Ok(body.to_string())
+}
+
+/// Nonces issued by the server and not used yet. Only the `MAX_NONCES` most
+/// recent ones are kept, so that clients never using them do not make the
+/// server grow without bound.
+#[derive(Default)]
+struct Nonces {
+	unused: HashSet<String>,
+	issued: VecDeque<String>,
+}
+
+impl Nonces {
+	fn issue(&mut self) -> String {
+		if self.issued.len() == MAX_NONCES {
+			if let Some(oldest) = self.issued.pop_front() {
+				self.unused.remove(&oldest);
+			}
+		}
+		let nonce = random_token();
+		self.unused.insert(nonce.clone());
+		self.issued.push_back(nonce.clone());
+		nonce
+	}
+
+	/// Whether the nonce was issued and not used yet, marking it as used.
+	fn redeem(&mut self, nonce: &str) -> bool {
+		self.unused.remove(nonce)
+	}
+}
+
+fn acme_error_type(name: &str) -> String {
+	format!("urn:ietf:params:acme:error:{}", name)
+}
+
+/// Problem document of an ACME error type, such as `malformed`.
+fn acme_error<T: ToString>(name: &str, detail: T) -> Error {
+	Error::new(&acme_error_type(name), Some(&detail.to_string()))
+}
+
+fn incorrect_response(detail: String) -> Error {
+	acme_error("incorrectResponse", detail)
+}
+
+/// URL of a resource of the server, whose base URL is always valid.
+fn server_url(url: &str) -> Url {
+	Url::from_str(url).expect("the server only builds absolute URLs")
+}
+
+/// Random number in the [0, 1) range.
+fn random_ratio() -> f64 {
+	let mut buf = [0; 4];
+	DefaultBackend::fill_random(&mut buf).expect("unable to generate random data");
+	f64::from(u32::from_be_bytes(buf)) / (f64::from(u32::MAX) + 1.0)
+}
+
+fn random_token() -> String {
+	let mut buf = [0; 16];
+	// The backends only fail if the operating system's RNG does, in which
+	// case nothing can be done anyway.
+	DefaultBackend::fill_random(&mut buf).expect("unable to generate random data");
+	b64(&buf)
+}
+
+fn contacts(payload: &Value) -> Result<Option<Vec<Url>>, Problem> {
+	let contact = match payload.get("contact") {
+		Some(contact) => contact,
+		None => return Ok(None),
+	};
+	let contact: Vec<Url> = serde_json::from_value(contact.clone())
+		.map_err(|e| Problem::malformed(format!("invalid contact: {}", e)))?;
+	if let Some(c) = contact.iter().find(|c| !c.starts_with("mailto:")) {
+		return Err(Problem::new(
+			StatusCode::BAD_REQUEST,
+			"unsupportedContact",
+			format!("{}: only mailto URLs are supported", c),
+		));
+	}
+	Ok(Some(contact))
+}
+
+fn check_identifier(identifier: &Identifier, allow_wildcard: bool) -> Result<(), Problem> {
+	let rejected = |detail: &str| {
+		Problem::new(
+			StatusCode::BAD_REQUEST,
+			"rejectedIdentifier",
+			format!("{}: {}", identifier, detail),
+		)
+	};
+	match identifier.id_type {
+		IdentifierType::Dns => {
+			let (value, wildcard) = split_wildcard(identifier);
+			if wildcard && !allow_wildcard {
+				return Err(rejected("wildcard identifiers are not allowed"));
+			}
+			let valid = !value.is_empty()
+				&& value.split('.').all(|label| {
+					!label.is_empty()
+						&& label.len() <= 63
+						&& label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+				});
+			if !valid {
+				return Err(rejected("invalid domain name"));
+			}
+		}
+		IdentifierType::Ip => {
+			identifier
+				.value
+				.parse::<IpAddr>()
+				.map_err(|_| rejected("invalid IP address"))?;
+		}
+	}
+	Ok(())
+}
+
+fn split_wildcard(identifier: &Identifier) -> (&str, bool) {
+	match identifier.value.strip_prefix("*.") {
+		Some(value) if identifier.id_type == IdentifierType::Dns => (value, true),
+		_ => (identifier.value.as_str(), false),
+	}
+}
+
+fn normalize(identifiers: &[Identifier]) -> Vec<String> {
+	let mut names: Vec<String> = identifiers
+		.iter()
+		.map(|i| match i.id_type {
+			IdentifierType::Dns => format!("dns:{}", i.value.to_ascii_lowercase()),
+			IdentifierType::Ip => match i.value.parse::<IpAddr>() {
+				Ok(ip) => format!("ip:{}", ip),
+				Err(_) => format!("ip:{}", i.value),
+			},
+		})
+		.collect();
+	names.sort();
+	names.dedup();
+	names
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Nonces, TestServer, TestServerConfig, TestServerFault, MAX_NONCES};
+	use crate::crypto::b64;
+	use crate::errors::HttpError;
+	use crate::http::{HttpClient, HttpResponse};
+	use crate::key::{test_keys, AccountKey};
+	use crate::pagination::AccountOrders;
+	use crate::structs::{
+		AccountManagement, AccountResource, Authorization, AuthorizationStatus, CertificateId,
+		Challenge, ChallengeStatus, ChallengeType, Directory, Error, Identifier,
+		JwsProtectedHeader, NewOrder, Order, OrderStatus, Url,
+	};
+	use crate::validation::Validate;
+	use async_trait::async_trait;
+	use serde_json::{json, Value};
+	use std::net::SocketAddr;
+	use std::str::FromStr;
+	use std::sync::Mutex;
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::TcpStream;
+
+	async fn request(addr: SocketAddr, method: &str, path: &str, body: &[u8]) -> HttpResponse {
+		let mut stream = TcpStream::connect(addr).await.unwrap();
+		let mut req = format!(
+			"{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+			method,
+			path,
+			addr,
+			body.len()
+		);
+		if !body.is_empty() {
+			req.push_str("Content-Type: application/jose+json\r\n");
+		}
+		req.push_str("\r\n");
+		stream.write_all(req.as_bytes()).await.unwrap();
+		stream.write_all(body).await.unwrap();
+		let mut resp = Vec::new();
+		stream.read_to_end(&mut resp).await.unwrap();
+		let pos = resp.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+		let head = String::from_utf8(resp[..pos].to_vec()).unwrap();
+		let mut lines = head.lines();
+		let status = lines.next().unwrap().split_whitespace().nth(1).unwrap();
+		let mut res = HttpResponse::new(status.parse().unwrap(), &resp[pos + 4..]);
+		for line in lines {
+			let (name, value) = line.split_once(':').unwrap();
+			res = res.with_header(name, value.trim());
+		}
+		res
+	}
+
+	/// Minimal ACME client.
+	struct TestClient {
+		addr: SocketAddr,
+		key: AccountKey,
+		kid: Mutex<Option<Url>>,
+		nonce: Mutex<Option<String>>,
+	}
+
+	impl TestClient {
+		fn new(server: &TestServer) -> Self {
+			Self {
+				addr: server.local_addr(),
+				key: AccountKey::from_pem(test_keys::P256_PKCS8).unwrap(),
+				kid: Mutex::new(None),
+				nonce: Mutex::new(None),
+			}
+		}
+
+		fn path<'a>(&self, url: &'a str) -> &'a str {
+			url.strip_prefix(&format!("http://{}", self.addr)).unwrap()
+		}
+
+		/// Sends a signed request, or a POST-as-GET one if there is no
+		/// payload.
+		async fn post(&self, url: &str, payload: Option<Value>) -> HttpResponse {
+			let nonce = self.nonce.lock().unwrap().take();
+			let nonce = match nonce {
+				Some(nonce) => nonce,
+				None => {
+					let res = request(self.addr, "HEAD", "/new-nonce", b"").await;
+					res.header("Replay-Nonce").unwrap().to_string()
+				}
+			};
+			let kid = self.kid.lock().unwrap().clone();
+			let url = Url::from_str(url).unwrap();
+			let header = match kid {
+				Some(kid) => {
+					JwsProtectedHeader::new_kid(self.key.algorithm(), &kid, Some(&nonce), &url)
+				}
+				None => JwsProtectedHeader::new_jwk(&self.key.jwk(), Some(&nonce), &url),
+			};
+			let protected = b64(serde_json::to_string(&header).unwrap().as_bytes());
+			let payload = payload
+				.map(|p| b64(p.to_string().as_bytes()))
+				.unwrap_or_default();
+			let signature = self
+				.key
+				.sign(format!("{}.{}", protected, payload).as_bytes())
+				.unwrap();
+			let body = json!({
+				"protected": protected,
+				"payload": payload,
+				"signature": b64(&signature),
+			});
+			let res = request(
+				self.addr,
+				"POST",
+				self.path(url.as_str()),
+				body.to_string().as_bytes(),
+			)
+			.await;
+			if let Some(nonce) = res.header("Replay-Nonce") {
+				*self.nonce.lock().unwrap() = Some(nonce.to_string());
+			}
+			res
+		}
+
+		async fn post_json<T: FromStr>(&self, url: &str, payload: Option<Value>) -> T
+		where
+			T::Err: std::fmt::Debug,
+		{
+			let res = self.post(url, payload).await;
+			let body = String::from_utf8(res.body).unwrap();
+			assert!((200..300).contains(&res.status), "{}: {}", res.status, body);
+			T::from_str(&body).unwrap()
+		}
+
+		async fn register(&self, directory: &Directory) -> AccountResource {
+			let payload = AccountManagement::creation_request(&["mailto:admin@example.org"], true);
+			let res = self
+				.post(&directory.new_account, Some(json!(payload)))
+				.await;
+			assert_eq!(res.status, 201);
+			let kid = Url::from_str(res.header("Location").unwrap()).unwrap();
+			*self.kid.lock().unwrap() = Some(kid);
+			AccountResource::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap()
+		}
+	}
+
+	#[async_trait]
+	impl HttpClient for TestClient {
+		async fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+			Ok(request(self.addr, "GET", self.path(url), b"").await)
+		}
+
+		async fn post_as_get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+			Ok(self.post(url, None).await)
+		}
+	}
+
+	async fn directory(client: &TestClient, server: &TestServer) -> Directory {
+		let res = client.get(&server.directory_url()).await.unwrap();
+		assert_eq!(res.status, 200);
+		Directory::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap()
+	}
+
+	fn csr(names: &[&str]) -> String {
+		let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+		let key = rcgen::KeyPair::generate().unwrap();
+		let params = rcgen::CertificateParams::new(names).unwrap();
+		b64(params.serialize_request(&key).unwrap().der())
+	}
+
+	#[test]
+	fn nonces_bounded() {
+		let mut nonces = Nonces::default();
+		let first = nonces.issue();
+		let second = nonces.issue();
+		assert!(nonces.redeem(&second));
+		assert!(!nonces.redeem(&second));
+		for _ in 0..MAX_NONCES - 1 {
+			nonces.issue();
+		}
+		assert_eq!(nonces.unused.len(), MAX_NONCES - 1);
+		assert!(!nonces.redeem(&first));
+		let last = nonces.issue();
+		assert!(nonces.redeem(&last));
+	}
+
+	#[tokio::test]
+	async fn issuance() {
+		let config = TestServerConfig::new()
+			.auto_validate(true)
+			.orders_per_page(1);
+		let server = TestServer::bind("127.0.0.1:0".parse().unwrap(), config)
+			.await
+			.unwrap();
+		let client = TestClient::new(&server);
+		let directory = directory(&client, &server).await;
+		let account = client.register(&directory).await;
+		assert!(account.orders.is_some());
+		assert!(account.validate().is_ok());
+		assert!(directory.validate().is_ok());
+
+		let identifiers = [
+			Identifier::dns("example.org"),
+			Identifier::dns("*.example.org"),
+		];
+		let new_order = NewOrder::new(&identifiers);
+		let res = client
+			.post(&directory.new_order, Some(json!(new_order)))
+			.await;
+		assert_eq!(res.status, 201);
+		let order_url = res.header("Location").unwrap().to_string();
+		let order = Order::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap();
+		assert_eq!(order.status, OrderStatus::Pending);
+		assert_eq!(order.authorizations.len(), 2);
+
+		let res = client
+			.post(
+				&order.finalize,
+				Some(json!({ "csr": csr(&["example.org"]) })),
+			)
+			.await;
+		assert_eq!(res.status, 403);
+		assert_eq!(res.header("Content-Type"), Some("application/problem+json"));
+
+		for authz_url in &order.authorizations {
+			let authz: Authorization = client.post_json(authz_url, None).await;
+			assert_eq!(authz.status, AuthorizationStatus::Pending);
+			let challenge = authz
+				.challenges
+				.iter()
+				.find(|c| c.challenge_type == ChallengeType::Dns01)
+				.unwrap();
+			let challenge: Challenge = client.post_json(&challenge.url, Some(json!({}))).await;
+			assert_eq!(challenge.status, ChallengeStatus::Valid);
+			let authz: Authorization = client.post_json(authz_url, None).await;
+			assert_eq!(authz.status, AuthorizationStatus::Valid);
+			let res = authz.validate();
+			assert!(res.is_ok(), "{:?}", res);
+		}
+
+		let order: Order = client.post_json(&order_url, None).await;
+		assert_eq!(order.status, OrderStatus::Ready);
+		let res = client
+			.post(
+				&order.finalize,
+				Some(json!({ "csr": csr(&["example.org"]) })),
+			)
+			.await;
+		assert_eq!(res.status, 400);
+		let csr = csr(&["*.example.org", "example.org"]);
+		let order: Order = client
+			.post_json(&order.finalize, Some(json!({ "csr": csr })))
+			.await;
+		assert_eq!(order.status, OrderStatus::Valid);
+		let res = order.validate();
+		assert!(res.is_ok(), "{:?}", res);
+		let res = client.post(&order.certificate.unwrap(), None).await;
+		assert_eq!(res.status, 200);
+		assert_eq!(
+			res.header("Content-Type"),
+			Some("application/pem-certificate-chain")
+		);
+		let chain = String::from_utf8(res.body).unwrap();
+		assert!(chain.ends_with(server.root_certificate()));
+		assert!(CertificateId::from_pem(&chain).is_ok());
+
+		let new_order = NewOrder::new(&[Identifier::dns("example.org")]);
+		let order: Order = client
+			.post_json(&directory.new_order, Some(json!(new_order)))
+			.await;
+		assert_eq!(order.status, OrderStatus::Ready);
+		let orders = AccountOrders::new(&client, account.orders.as_ref().unwrap())
+			.collect()
+			.await
+			.unwrap();
+		assert_eq!(orders.len(), 2);
+		assert_eq!(orders[0], order_url);
+	}
+
+	#[tokio::test]
+	async fn errors() {
+		let server = TestServer::bind("127.0.0.1:0".parse().unwrap(), TestServerConfig::new())
+			.await
+			.unwrap();
+		let client = TestClient::new(&server);
+		let directory = directory(&client, &server).await;
+		let lookup = json!(AccountManagement::lookup_request());
+		let res = client.post(&directory.new_account, Some(lookup)).await;
+		assert_eq!(res.status, 400);
+		let error: Value = serde_json::from_slice(&res.body).unwrap();
+		assert_eq!(
+			error["type"],
+			"urn:ietf:params:acme:error:accountDoesNotExist"
+		);
+		client.register(&directory).await;
+
+		*client.nonce.lock().unwrap() = Some("invalid".to_string());
+		let res = client.post(&directory.new_order, Some(json!({}))).await;
+		let error: Value = serde_json::from_slice(&res.body).unwrap();
+		assert_eq!(error["type"], "urn:ietf:params:acme:error:badNonce");
+
+		let res = request(client.addr, "GET", "/new-nonce", b"").await;
+		assert_eq!(res.status, 204);
+		assert!(res.header("Replay-Nonce").is_some());
+
+		let new_order = NewOrder::new(&[Identifier::dns("invalid_name.example.org")]);
+		let res = client
+			.post(&directory.new_order, Some(json!(new_order)))
+			.await;
+		let error: Value = serde_json::from_slice(&res.body).unwrap();
+		assert_eq!(
+			error["type"],
+			"urn:ietf:params:acme:error:rejectedIdentifier"
+		);
+
+		let new_order = NewOrder::new(&[Identifier::ip("192.0.2.1")]);
+		let res = client
+			.post(&directory.new_order, Some(json!(new_order)))
+			.await;
+		assert_eq!(res.status, 201);
+		let order_url = res.header("Location").unwrap().to_string();
+		let order = Order::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap();
+		let authz: Authorization = client.post_json(&order.authorizations[0], None).await;
+		let challenge = authz
+			.challenges
+			.iter()
+			.find(|c| c.challenge_type == ChallengeType::TlsAlpn01)
+			.unwrap();
+		let challenge: Challenge = client.post_json(&challenge.url, Some(json!({}))).await;
+		assert_eq!(challenge.status, ChallengeStatus::Invalid);
+		assert!(challenge.error.is_some());
+		let order: Order = client.post_json(&order_url, None).await;
+		assert_eq!(order.status, OrderStatus::Invalid);
+
+		let other = TestClient {
+			key: AccountKey::from_pem(test_keys::ED25519_PKCS8).unwrap(),
+			..TestClient::new(&server)
+		};
+		other.register(&directory).await;
+		let res = other.post(&order.authorizations[0], None).await;
+		assert_eq!(res.status, 403);
+	}
+
+	#[tokio::test]
+	async fn faults() {
+		let config = TestServerConfig::new().auto_validate(true);
+		let server = TestServer::bind("127.0.0.1:0".parse().unwrap(), config)
+			.await
+			.unwrap();
+		let client = TestClient::new(&server);
+		let directory = directory(&client, &server).await;
+		client.register(&directory).await;
+		let new_order = json!(NewOrder::new(&[Identifier::dns("example.org")]));
+
+		server.inject(TestServerFault::BadNonce);
+		server.inject(TestServerFault::RateLimited { retry_after: 30 });
+		let res = client
+			.post(&directory.new_order, Some(new_order.clone()))
+			.await;
+		assert_eq!(res.status, 400);
+		let error = Error::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap();
+		assert!(error.to_string().contains(":badNonce"), "{}", error);
+		let res = client
+			.post(&directory.new_order, Some(new_order.clone()))
+			.await;
+		assert_eq!(res.status, 429);
+		assert_eq!(res.header("Retry-After"), Some("30"));
+		let error = Error::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap();
+		assert!(error.to_string().contains(":rateLimited"), "{}", error);
+
+		server.inject(TestServerFault::InvalidAuthorization);
+		let res = client
+			.post(&directory.new_order, Some(new_order.clone()))
+			.await;
+		assert_eq!(res.status, 201);
+		let order_url = res.header("Location").unwrap().to_string();
+		let order = Order::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap();
+		let authz: Authorization = client.post_json(&order.authorizations[0], None).await;
+		let challenge: Challenge = client
+			.post_json(&authz.challenges[0].url, Some(json!({})))
+			.await;
+		assert_eq!(challenge.status, ChallengeStatus::Invalid);
+		let error = challenge.error.unwrap().to_string();
+		assert!(
+			error.contains("urn:ietf:params:acme:error:dns"),
+			"{}",
+			error
+		);
+		assert!(error.contains("example.org"), "{}", error);
+		let order: Order = client.post_json(&order_url, None).await;
+		assert_eq!(order.status, OrderStatus::Invalid);
+		assert!(order.error.unwrap().to_string().contains("example.org"));
+
+		server.inject(TestServerFault::ProcessingOrder {
+			polls: 2,
+			retry_after: 5,
+		});
+		server.inject(TestServerFault::TruncatedChain);
+		let res = client.post(&directory.new_order, Some(new_order)).await;
+		let order_url = res.header("Location").unwrap().to_string();
+		let order = Order::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap();
+		let authz: Authorization = client.post_json(&order.authorizations[0], None).await;
+		let _: Challenge = client
+			.post_json(&authz.challenges[0].url, Some(json!({})))
+			.await;
+		let res = client
+			.post(
+				&order.finalize,
+				Some(json!({ "csr": csr(&["example.org"]) })),
+			)
+			.await;
+		assert_eq!(res.header("Retry-After"), Some("5"));
+		let order = Order::from_str(std::str::from_utf8(&res.body).unwrap()).unwrap();
+		assert_eq!(order.status, OrderStatus::Processing);
+		assert!(order.certificate.is_none());
+		for expected in [
+			OrderStatus::Processing,
+			OrderStatus::Processing,
+			OrderStatus::Valid,
+		] {
+			let order: Order = client.post_json(&order_url, None).await;
+			assert_eq!(order.status, expected);
+		}
+		let order: Order = client.post_json(&order_url, None).await;
+		let res = client.post(&order.certificate.unwrap(), None).await;
+		let chain = String::from_utf8(res.body).unwrap();
+		assert_eq!(chain.matches("-----BEGIN CERTIFICATE-----").count(), 1);
+		assert!(!chain.contains(server.root_certificate()));
+	}
+
+	#[tokio::test]
+	async fn bad_nonce_rate() {
+		let config = TestServerConfig::new().bad_nonce_rate(1.0);
+		let server = TestServer::bind("127.0.0.1:0".parse().unwrap(), config)
+			.await
+			.unwrap();
+		let client = TestClient::new(&server);
+		let directory = directory(&client, &server).await;
+		for _ in 0..3 {
+			let lookup = json!(AccountManagement::lookup_request());
+			let res = client.post(&directory.new_account, Some(lookup)).await;
+			let error: Value = serde_json::from_slice(&res.body).unwrap();
+			assert_eq!(error["type"], "urn:ietf:params:acme:error:badNonce");
+		}
+	}
+
+	#[cfg(feature = "http01_responder")]
+	#[tokio::test]
+	async fn http01_validation() {
+		use crate::solver::{ChallengeSolver, Http01Responder};
+
+		let responder = Http01Responder::bind("127.0.0.1:0".parse().unwrap())
+			.await
+			.unwrap();
+		let config = TestServerConfig::new().http01_port(responder.local_addr().port());
+		let server = TestServer::bind("127.0.0.1:0".parse().unwrap(), config)
+			.await
+			.unwrap();
+		let client = TestClient::new(&server);
+		let directory = directory(&client, &server).await;
+		client.register(&directory).await;
+
+		for (name, present) in [("a.example.org", true), ("b.example.org", false)] {
+			let identifier = Identifier::dns(name);
+			let new_order = NewOrder::new(std::slice::from_ref(&identifier));
+			let order: Order = client
+				.post_json(&directory.new_order, Some(json!(new_order)))
+				.await;
+			let authz: Authorization = client.post_json(&order.authorizations[0], None).await;
+			let challenge = authz
+				.challenges
+				.iter()
+				.find(|c| c.challenge_type == ChallengeType::Http01)
+				.unwrap();
+			let key_auth = challenge.key_authorization(&client.key.jwk()).unwrap();
+			if present {
+				responder.present(&identifier, &key_auth).await.unwrap();
+			}
+			let _: Challenge = client.post_json(&challenge.url, Some(json!({}))).await;
+			let mut status = AuthorizationStatus::Pending;
+			for _ in 0..50 {
+				let authz: Authorization = client.post_json(&order.authorizations[0], None).await;
+				status = authz.status;
+				if status != AuthorizationStatus::Pending {
+					break;
+				}
+				tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+			}
+			let expected = if present {
+				AuthorizationStatus::Valid
+			} else {
+				AuthorizationStatus::Invalid
+			};
+			assert_eq!(status, expected, "{}", name);
+		}
+	}
+}
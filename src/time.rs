@@ -0,0 +1,207 @@
+use std::time::{Duration, SystemTime};
+
+/// Parses a RFC 3339 date-time, as used by ACME for timestamps.
+pub(crate) fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+	let b = s.as_bytes();
+	if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || b[13] != b':' || b[16] != b':' {
+		return None;
+	}
+	if !matches!(b[10], b'T' | b't' | b' ') {
+		return None;
+	}
+	let year = digits(&b[0..4])? as i64;
+	let month = digits(&b[5..7])?;
+	let day = digits(&b[8..10])?;
+	let hour = digits(&b[11..13])?;
+	let minute = digits(&b[14..16])?;
+	let second = digits(&b[17..19])?;
+	if !(1..=12).contains(&month)
+		|| day == 0
+		|| day > days_in_month(year, month)
+		|| hour > 23
+		|| minute > 59
+		|| second > 60
+	{
+		return None;
+	}
+	let mut rest = &b[19..];
+	let mut nanos = 0;
+	if rest.first() == Some(&b'.') {
+		let len = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+		if len == 0 {
+			return None;
+		}
+		for (i, c) in rest[1..=len].iter().take(9).enumerate() {
+			nanos += u32::from(c - b'0') * 10_u32.pow(8 - i as u32);
+		}
+		rest = &rest[len + 1..];
+	}
+	let offset = match rest {
+		[b'Z' | b'z'] => 0,
+		[sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+			let h = digits(&[*h1, *h2])?;
+			let m = digits(&[*m1, *m2])?;
+			if h > 23 || m > 59 {
+				return None;
+			}
+			let offset = i64::from(h * 3600 + m * 60);
+			if *sign == b'-' {
+				-offset
+			} else {
+				offset
+			}
+		}
+		_ => return None,
+	};
+	// A leap second is the same instant as the first second of the next
+	// minute, which is close enough for ACME.
+	let secs = days_from_civil(year, month, day) * 86_400
+		+ i64::from(hour * 3600 + minute * 60 + second)
+		- offset;
+	let time = if secs >= 0 {
+		SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))?
+	} else {
+		SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+	};
+	time.checked_add(Duration::from_nanos(u64::from(nanos)))
+}
+
+/// Formats a date-time as RFC 3339, in UTC and without fractional seconds.
+pub(crate) fn format_rfc3339(time: SystemTime) -> String {
+	let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+		Ok(d) => d.as_secs() as i64,
+		Err(e) => {
+			let d = e.duration();
+			-(d.as_secs() as i64) - i64::from(d.subsec_nanos() > 0)
+		}
+	};
+	let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+	let secs = secs.rem_euclid(86_400);
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+		year,
+		month,
+		day,
+		secs / 3600,
+		secs % 3600 / 60,
+		secs % 60
+	)
+}
+
+/// Calendar date, in UTC.
+#[cfg(any(test, feature = "test_server"))]
+pub(crate) fn civil_date(time: SystemTime) -> (i64, u32, u32) {
+	let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+		Ok(d) => d.as_secs() as i64,
+		Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+	};
+	civil_from_days(secs.div_euclid(86_400))
+}
+
+fn digits(b: &[u8]) -> Option<u32> {
+	b.iter().try_fold(0, |acc, c| {
+		c.is_ascii_digit().then(|| acc * 10 + u32::from(c - b'0'))
+	})
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+	match month {
+		2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+// Days since 1970-01-01, from Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let yoe = year - era * 400;
+	let month = i64::from(month);
+	let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	era * 146_097 + doe - 719_468
+}
+
+// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z - era * 146_097;
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+	(yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{civil_date, format_rfc3339, parse_rfc3339};
+	use std::time::{Duration, SystemTime};
+
+	fn ts(secs: u64, nanos: u32) -> SystemTime {
+		SystemTime::UNIX_EPOCH + Duration::new(secs, nanos)
+	}
+
+	#[test]
+	fn valid() {
+		let tests = [
+			("1970-01-01T00:00:00Z", ts(0, 0)),
+			("2016-01-20T14:09:07.99Z", ts(1_453_298_947, 990_000_000)),
+			("2025-01-02T04:00:00Z", ts(1_735_790_400, 0)),
+			("2025-01-02T06:00:00+02:00", ts(1_735_790_400, 0)),
+			("2025-01-01t22:30:00-05:30", ts(1_735_790_400, 0)),
+			(
+				"2024-02-29T12:00:00.123456789123z",
+				ts(1_709_208_000, 123_456_789),
+			),
+		];
+		for (s, expected) in tests {
+			assert_eq!(parse_rfc3339(s), Some(expected), "{}", s);
+		}
+		assert_eq!(
+			parse_rfc3339("1969-12-31T23:59:59Z"),
+			SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(1))
+		);
+	}
+
+	#[test]
+	fn invalid() {
+		for s in [
+			"",
+			"2025-01-02",
+			"2025-01-02T04:00:00",
+			"2025-01-02T04:00Z",
+			"2025-13-02T04:00:00Z",
+			"2025-02-29T04:00:00Z",
+			"2025-01-02T24:00:00Z",
+			"2025-01-02T04:00:00.Z",
+			"2025-01-02T04:00:00+0200",
+			"2025-01-02T04:00:00Z ",
+			"2025-0a-02T04:00:00Z",
+		] {
+			assert!(parse_rfc3339(s).is_none(), "{}", s);
+		}
+	}
+
+	#[test]
+	fn format() {
+		let tests = [
+			(0, 0, "1970-01-01T00:00:00Z"),
+			(1_453_298_947, 990_000_000, "2016-01-20T14:09:07Z"),
+			(1_709_208_000, 0, "2024-02-29T12:00:00Z"),
+			(32_503_680_000, 0, "3000-01-01T00:00:00Z"),
+		];
+		for (secs, nanos, expected) in tests {
+			assert_eq!(format_rfc3339(ts(secs, nanos)), expected);
+			assert_eq!(parse_rfc3339(expected), Some(ts(secs, 0)));
+		}
+		let before = SystemTime::UNIX_EPOCH - Duration::from_millis(500);
+		assert_eq!(format_rfc3339(before), "1969-12-31T23:59:59Z");
+		assert_eq!(civil_date(ts(1_709_208_000, 0)), (2024, 2, 29));
+		assert_eq!(civil_date(before), (1969, 12, 31));
+	}
+}
@@ -0,0 +1,430 @@
+use crate::errors::ValidationError;
+use crate::structs::{
+	AccountResource, Authorization, AuthorizationStatus, Challenge, ChallengeStatus, ChallengeType,
+	Directory, Error, IdentifierType, Order, OrderStatus, OrdersList, RenewalInfo,
+};
+use std::net::IpAddr;
+
+/// Strict validation of a parsed resource against the specifications.
+///
+/// Parsing only checks that URLs are absolute and that timestamps are RFC 3339
+/// date-times, the consistency between the status of an object and its other
+/// fields is not checked. Validation checks that:
+///
+/// - URLs are https URLs, or http ones for the loopback interface;
+/// - the fields required by the status of the object are present.
+///
+/// Every violation is reported at once, which is useful to qualify a new CA.
+///
+/// ```no_run
+/// # use acme_proto::{Order, Validate};
+/// # use std::str::FromStr;
+/// # fn example(body: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// let order = Order::from_str(body)?;
+/// if let Err(e) = order.validate() {
+///     for violation in e.violations() {
+///         eprintln!("{}", violation);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait Validate {
+	fn validate(&self) -> Result<(), ValidationError>;
+}
+
+/// Violations found so far, each prefixed by the path of the member.
+#[derive(Default)]
+struct Violations {
+	list: Vec<String>,
+}
+
+impl Violations {
+	fn report<F: FnOnce(&mut Self)>(check: F) -> Result<(), ValidationError> {
+		let mut violations = Self::default();
+		check(&mut violations);
+		if violations.list.is_empty() {
+			return Ok(());
+		}
+		Err(ValidationError {
+			violations: violations.list,
+		})
+	}
+
+	fn add(&mut self, path: &str, msg: &str) {
+		self.list.push(format!("{}: {}", path, msg));
+	}
+
+	fn require(&mut self, condition: bool, path: &str, msg: &str) {
+		if !condition {
+			self.add(path, msg);
+		}
+	}
+
+	fn url(&mut self, path: &str, url: &str) {
+		if !is_valid_url(url) {
+			self.add(path, &format!("{}: not an https URL", url));
+		}
+	}
+
+	fn opt_url(&mut self, path: &str, url: Option<&str>) {
+		if let Some(url) = url {
+			self.url(path, url);
+		}
+	}
+
+	fn error(&mut self, path: &str, error: Option<&Error>) {
+		if let Some(error) = error {
+			if !error.error_type().contains(':') {
+				self.add(
+					&format!("{}.type", path),
+					&format!("{}: not a URI", error.error_type()),
+				);
+			}
+		}
+	}
+
+	fn challenge(&mut self, path: &str, challenge: &Challenge) {
+		self.url(&format!("{}.url", path), &challenge.url);
+		self.error(&format!("{}.error", path), challenge.error.as_ref());
+		if challenge.status == ChallengeStatus::Valid {
+			self.require(
+				challenge.validated.is_some(),
+				&format!("{}.validated", path),
+				"required for valid challenges",
+			);
+		}
+		if challenge.challenge_type != ChallengeType::Unknown {
+			// RFC 8555 section 8.3 requires at least 128 bits of entropy.
+			let valid = challenge.token.as_deref().is_some_and(|t| {
+				t.len() >= 22
+					&& t.chars()
+						.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+			});
+			self.require(
+				valid,
+				&format!("{}.token", path),
+				"missing or invalid token",
+			);
+		}
+	}
+}
+
+/// Whether the URL is an absolute https URL, or an http one for the loopback
+/// interface. Only the scheme and the host are checked.
+fn is_valid_url(url: &str) -> bool {
+	let (rest, secure) = match (url.strip_prefix("https://"), url.strip_prefix("http://")) {
+		(Some(rest), _) => (rest, true),
+		(_, Some(rest)) => (rest, false),
+		_ => return false,
+	};
+	if url.contains(char::is_whitespace) {
+		return false;
+	}
+	let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+	let host = authority.rsplit('@').next().unwrap_or_default();
+	let host = match host.strip_prefix('[') {
+		Some(ipv6) => match ipv6.split_once(']') {
+			Some((ipv6, _)) => ipv6,
+			None => return false,
+		},
+		None => host.split(':').next().unwrap_or_default(),
+	};
+	if host.is_empty() {
+		return false;
+	}
+	secure
+		|| host.eq_ignore_ascii_case("localhost")
+		|| host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+impl Validate for Directory {
+	fn validate(&self) -> Result<(), ValidationError> {
+		Violations::report(|v| {
+			v.url("newNonce", &self.new_nonce);
+			v.url("newAccount", &self.new_account);
+			v.url("newOrder", &self.new_order);
+			v.opt_url("newAuthz", self.new_authz.as_deref());
+			v.url("revokeCert", &self.revoke_cert);
+			v.url("keyChange", &self.key_change);
+			v.opt_url("renewalInfo", self.renewal_info.as_deref());
+			if let Some(meta) = &self.meta {
+				v.opt_url("meta.termsOfService", meta.terms_of_service.as_deref());
+				v.opt_url("meta.website", meta.website.as_deref());
+			}
+		})
+	}
+}
+
+impl Validate for AccountResource {
+	fn validate(&self) -> Result<(), ValidationError> {
+		Violations::report(|v| match &self.orders {
+			Some(orders) => v.url("orders", orders),
+			None => v.add("orders", "missing"),
+		})
+	}
+}
+
+impl Validate for Order {
+	fn validate(&self) -> Result<(), ValidationError> {
+		Violations::report(|v| {
+			v.error("error", self.error.as_ref());
+			v.require(!self.identifiers.is_empty(), "identifiers", "empty");
+			for (i, url) in self.authorizations.iter().enumerate() {
+				v.url(&format!("authorizations[{}]", i), url);
+			}
+			v.url("finalize", &self.finalize);
+			v.opt_url("certificate", self.certificate.as_deref());
+			if matches!(self.status, OrderStatus::Pending | OrderStatus::Valid) {
+				v.require(
+					self.expires.is_some(),
+					"expires",
+					"required for pending and valid orders",
+				);
+			}
+			match (&self.status, &self.certificate) {
+				(OrderStatus::Valid, None) => {
+					v.add("certificate", "required for valid orders");
+				}
+				(OrderStatus::Valid, Some(_)) | (_, None) => {}
+				(_, Some(_)) => v.add("certificate", "only allowed for valid orders"),
+			}
+		})
+	}
+}
+
+impl Validate for OrdersList {
+	fn validate(&self) -> Result<(), ValidationError> {
+		Violations::report(|v| {
+			for (i, url) in self.orders.iter().enumerate() {
+				v.url(&format!("orders[{}]", i), url);
+			}
+		})
+	}
+}
+
+impl Validate for Authorization {
+	fn validate(&self) -> Result<(), ValidationError> {
+		Violations::report(|v| {
+			v.require(
+				!self.identifier.value.starts_with("*."),
+				"identifier.value",
+				"wildcard prefix not allowed, see the wildcard field",
+			);
+			if self.wildcard == Some(true) {
+				v.require(
+					self.identifier.id_type == IdentifierType::Dns,
+					"wildcard",
+					"only allowed for dns identifiers",
+				);
+			}
+			v.require(!self.challenges.is_empty(), "challenges", "empty");
+			for (i, challenge) in self.challenges.iter().enumerate() {
+				v.challenge(&format!("challenges[{}]", i), challenge);
+			}
+			if self.status == AuthorizationStatus::Valid {
+				v.require(
+					self.expires.is_some(),
+					"expires",
+					"required for valid authorizations",
+				);
+				v.require(
+					self.challenges
+						.iter()
+						.any(|c| c.status == ChallengeStatus::Valid),
+					"challenges",
+					"valid authorizations require a valid challenge",
+				);
+			}
+		})
+	}
+}
+
+impl Validate for Challenge {
+	fn validate(&self) -> Result<(), ValidationError> {
+		Violations::report(|v| v.challenge("challenge", self))
+	}
+}
+
+impl Validate for RenewalInfo {
+	fn validate(&self) -> Result<(), ValidationError> {
+		Violations::report(|v| {
+			// RFC 9773 requires the end to be after the start, even though an
+			// empty window is still usable, see `SuggestedWindow::random_instant`.
+			let window = &self.suggested_window;
+			v.require(
+				window.start < window.end,
+				"suggestedWindow",
+				"the end is not after the start",
+			);
+			v.opt_url("explanationURL", self.explanation_url.as_deref());
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{is_valid_url, Validate};
+	use crate::structs::{Authorization, Directory, Order, RenewalInfo};
+	use std::str::FromStr;
+
+	#[test]
+	fn urls() {
+		for url in [
+			"https://example.org/acme/new-order",
+			"https://example.org",
+			"https://user@example.org:8443/acme?a=b",
+			"http://localhost:14000/dir",
+			"http://127.0.0.1:14000/dir",
+			"http://[::1]/dir",
+		] {
+			assert!(is_valid_url(url), "{}", url);
+		}
+		for url in [
+			"",
+			"/acme/new-order",
+			"example.org/acme",
+			"http://example.org/acme",
+			"http://192.0.2.1/acme",
+			"https:///acme",
+			"https://[::1/acme",
+			"https://example.org/new order",
+			"ftp://example.org/acme",
+		] {
+			assert!(!is_valid_url(url), "{}", url);
+		}
+	}
+
+	#[test]
+	fn directory() {
+		let data = r#"{
+			"newNonce": "https://example.org/acme/new-nonce",
+			"newAccount": "https://example.org/acme/new-acct",
+			"newOrder": "https://example.org/acme/new-order",
+			"revokeCert": "https://example.org/acme/revoke-cert",
+			"keyChange": "https://example.org/acme/key-change",
+			"meta": {"termsOfService": "https://example.org/tos.pdf"}
+		}"#;
+		assert!(Directory::from_str(data).unwrap().validate().is_ok());
+		let data = r#"{
+			"newNonce": "ftp://example.org/acme/new-nonce",
+			"newAccount": "https://example.org/acme/new-acct",
+			"newOrder": "http://example.org/acme/new-order",
+			"revokeCert": "https://example.org/acme/revoke-cert",
+			"keyChange": "https://example.org/acme/key-change",
+			"meta": {"termsOfService": "http://example.org/tos.pdf"}
+		}"#;
+		let err = Directory::from_str(data).unwrap().validate().unwrap_err();
+		assert_eq!(err.violations().len(), 3);
+		assert!(err.violations()[0].starts_with("newNonce: "));
+		assert!(err.violations()[1].starts_with("newOrder: "));
+		assert!(err.violations()[2].starts_with("meta.termsOfService: "));
+	}
+
+	#[test]
+	fn order() {
+		let data = r#"{
+			"status": "valid",
+			"expires": "2025-01-09T08:26:43Z",
+			"identifiers": [{"type": "dns", "value": "example.org"}],
+			"authorizations": ["https://example.org/acme/authz/1"],
+			"finalize": "https://example.org/acme/order/1/finalize",
+			"certificate": "https://example.org/acme/cert/1"
+		}"#;
+		assert!(Order::from_str(data).unwrap().validate().is_ok());
+		let data = r#"{
+			"status": "valid",
+			"identifiers": [],
+			"authorizations": ["http://example.org/acme/authz/1"],
+			"finalize": "https://example.org/acme/order/1/finalize"
+		}"#;
+		let err = Order::from_str(data).unwrap().validate().unwrap_err();
+		assert_eq!(
+			err.violations(),
+			[
+				"identifiers: empty",
+				"authorizations[0]: http://example.org/acme/authz/1: not an https URL",
+				"expires: required for pending and valid orders",
+				"certificate: required for valid orders",
+			]
+		);
+		let data = r#"{
+			"status": "invalid",
+			"identifiers": [{"type": "dns", "value": "example.org"}],
+			"authorizations": ["https://example.org/acme/authz/1"],
+			"finalize": "https://example.org/acme/order/1/finalize",
+			"certificate": "https://example.org/acme/cert/1"
+		}"#;
+		let err = Order::from_str(data).unwrap().validate().unwrap_err();
+		assert_eq!(
+			err.violations(),
+			["certificate: only allowed for valid orders"]
+		);
+	}
+
+	#[test]
+	fn authorization() {
+		let data = r#"{
+			"status": "valid",
+			"expires": "2025-01-09T08:26:43Z",
+			"identifier": {"type": "dns", "value": "example.org"},
+			"wildcard": true,
+			"challenges": [{
+				"type": "dns-01",
+				"url": "https://example.org/acme/chall/1",
+				"status": "valid",
+				"validated": "2025-01-02T08:26:43Z",
+				"token": "DGyRejmCefe7v4NfDGDKfA"
+			}]
+		}"#;
+		assert!(Authorization::from_str(data).unwrap().validate().is_ok());
+		let data = r#"{
+			"status": "valid",
+			"identifier": {"type": "dns", "value": "*.example.org"},
+			"challenges": [{
+				"type": "dns-01",
+				"url": "https://example.org/acme/chall/1",
+				"status": "valid",
+				"token": "short"
+			}]
+		}"#;
+		let err = Authorization::from_str(data)
+			.unwrap()
+			.validate()
+			.unwrap_err();
+		assert_eq!(
+			err.violations(),
+			[
+				"identifier.value: wildcard prefix not allowed, see the wildcard field",
+				"challenges[0].validated: required for valid challenges",
+				"challenges[0].token: missing or invalid token",
+				"expires: required for valid authorizations",
+			]
+		);
+	}
+
+	#[test]
+	fn renewal_info() {
+		let data = r#"{
+			"suggestedWindow": {
+				"start": "2025-01-03T00:00:00Z",
+				"end": "2025-01-02T00:00:00Z"
+			},
+			"explanationURL": "https://example.org/docs/ari"
+		}"#;
+		let err = RenewalInfo::from_str(data).unwrap().validate().unwrap_err();
+		assert_eq!(
+			err.violations(),
+			["suggestedWindow: the end is not after the start"]
+		);
+		let empty = data.replace("2025-01-03", "2025-01-02");
+		let err = RenewalInfo::from_str(&empty)
+			.unwrap()
+			.validate()
+			.unwrap_err();
+		assert_eq!(
+			err.violations(),
+			["suggestedWindow: the end is not after the start"]
+		);
+	}
+}